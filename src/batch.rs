@@ -0,0 +1,246 @@
+//! Processing every file in a directory at once.
+
+use crate::{
+    error::Error,
+    extractors::{csv::process_csv, sctx::process_sctx, tex::process_tex},
+};
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// What [`process_dir`] did with the files in a directory.
+///
+/// [`process_dir`]: ./fn.process_dir.html
+#[derive(Debug, Default)]
+pub struct Summary {
+    /// Number of files that were processed successfully.
+    pub processed: usize,
+    /// Path of each file that couldn't be processed and the reason, in the
+    /// order of the paths.
+    pub failed: Vec<(PathBuf, Error)>,
+    /// Number of images saved from all of the files.
+    pub images_written: usize,
+    /// Lower resolution variants that weren't processed, with
+    /// [`DirOptions::prefer_highres`], each with the file processed instead.
+    ///
+    /// [`DirOptions::prefer_highres`]: ./struct.DirOptions.html#structfield.prefer_highres
+    pub skipped_variants: Vec<(PathBuf, PathBuf)>,
+}
+
+/// Options that control which files [`process_dir_with_options`] processes.
+///
+/// The default options process every file, like [`process_dir`] does.
+///
+/// [`process_dir_with_options`]: ./fn.process_dir_with_options.html
+/// [`process_dir`]: ./fn.process_dir.html
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DirOptions {
+    /// Processes only the highest resolution variant of each `_tex.sc` file,
+    /// see [`prefer_highres`]. The others are listed in
+    /// [`Summary::skipped_variants`].
+    ///
+    /// [`prefer_highres`]: ./fn.prefer_highres.html
+    /// [`Summary::skipped_variants`]: ./struct.Summary.html#structfield.skipped_variants
+    pub prefer_highres: bool,
+}
+
+/// Kinds of files [`process_dir`] processes, told apart by their names.
+///
+/// [`process_dir`]: ./fn.process_dir.html
+#[derive(Clone, Copy)]
+enum Kind {
+    Tex,
+    Sctx,
+    Csv,
+}
+
+impl Kind {
+    fn from_name(name: &str) -> Option<Self> {
+        if name.ends_with("_tex.sc") {
+            Some(Self::Tex)
+        } else if name.ends_with(".sctx") {
+            Some(Self::Sctx)
+        } else if name.ends_with(".csv") {
+            Some(Self::Csv)
+        } else {
+            None
+        }
+    }
+}
+
+/// Processes a single file, returning the number of images saved.
+fn process_file(
+    path: &Path,
+    name: &str,
+    kind: Kind,
+    out_dir: &Path,
+    parallelize: bool,
+) -> Result<usize, Error> {
+    let data = fs::read(path)?;
+
+    match kind {
+        Kind::Tex => process_tex(&data, name, out_dir, parallelize).map(|e| e.sprites.len()),
+        Kind::Sctx => process_sctx(&data, name, out_dir, parallelize).map(|_| 1),
+        Kind::Csv => process_csv(&data, name, out_dir).map(|_| 0),
+    }
+}
+
+/// Processes every `_tex.sc`, `.sctx` and `.csv` file directly inside `dir`,
+/// saving the output in `out_dir`, and returns a [`Summary`] of the run.
+///
+/// Files are recognised by their names and processed like [`process_tex`],
+/// [`process_sctx`] and [`process_csv`] do. Other files and subdirectories
+/// are ignored. A file that can't be processed doesn't stop the others; it is
+/// listed in [`Summary::failed`] instead.
+///
+/// If `parallelize` is `true`, the files are processed in parallel and the
+/// progress messages of each file are left out.
+///
+/// ## Errors
+///
+/// [`Error::IoError`] is returned if `dir` can't be read or `out_dir` can't be
+/// created. Errors of individual files are returned in the summary.
+///
+/// [`Summary`]: ./struct.Summary.html
+/// [`Summary::failed`]: ./struct.Summary.html#structfield.failed
+/// [`process_tex`]: ./fn.process_tex.html
+/// [`process_sctx`]: ./fn.process_sctx.html
+/// [`process_csv`]: ./fn.process_csv.html
+/// [`Error::IoError`]: ./error/enum.Error.html#variant.IoError
+pub fn process_dir(dir: &Path, out_dir: &Path, parallelize: bool) -> Result<Summary, Error> {
+    process_dir_with_options(dir, out_dir, parallelize, &DirOptions::default())
+}
+
+/// Processes the files directly inside `dir` like [`process_dir`], but only
+/// those that `options` select.
+///
+/// ## Errors
+///
+/// The errors are the same as those of [`process_dir`].
+///
+/// [`process_dir`]: ./fn.process_dir.html
+pub fn process_dir_with_options(
+    dir: &Path,
+    out_dir: &Path,
+    parallelize: bool,
+    options: &DirOptions,
+) -> Result<Summary, Error> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort_unstable();
+
+    let mut summary = Summary::default();
+    if options.prefer_highres {
+        summary.skipped_variants = prefer_highres(&mut paths);
+    }
+
+    let files: Vec<_> = paths
+        .into_iter()
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?.to_string();
+            let kind = Kind::from_name(&name)?;
+            Some((path, name, kind))
+        })
+        .collect();
+
+    fs::create_dir_all(out_dir)?;
+
+    let process = |(path, name, kind): (PathBuf, String, Kind)| {
+        let result = process_file(&path, &name, kind, out_dir, parallelize);
+        (path, result)
+    };
+    let results: Vec<_> = if parallelize {
+        files.into_par_iter().map(process).collect()
+    } else {
+        files.into_iter().map(process).collect()
+    };
+
+    for (path, result) in results {
+        match result {
+            Ok(images) => {
+                summary.processed += 1;
+                summary.images_written += images;
+            }
+            Err(e) => summary.failed.push((path, e)),
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Returns the file that `path` is a resolution variant of, and the rank of
+/// its resolution, if it is a `_tex.sc` file.
+///
+/// The file is the directory, the name without the `_lowres` or `_highres`
+/// part, and whether it is a downloadable, `_dl`, variant. Higher resolutions
+/// have higher ranks.
+fn resolution_variant(path: &Path) -> Option<((PathBuf, String, bool), u8)> {
+    let name = path.file_name()?.to_str()?;
+    let (stem, extension) = ["_tex.sc", "_tex.sc2"]
+        .iter()
+        .find_map(|extension| Some((name.strip_suffix(extension)?, *extension)))?;
+
+    // Only whole parts of the name are compared, so `ui_lowresx_tex.sc` isn't
+    // a variant of `ui_tex.sc`.
+    let mut parts: Vec<_> = stem.split('_').collect();
+    let downloadable = parts.len() > 1 && parts.last() == Some(&"dl");
+    if downloadable {
+        parts.pop();
+    }
+    let rank = match parts.last() {
+        Some(&"lowres") if parts.len() > 1 => 0,
+        Some(&"highres") if parts.len() > 1 => 2,
+        _ => 1,
+    };
+    if rank != 1 {
+        parts.pop();
+    }
+
+    let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let base = format!("{}{}", parts.join("_"), extension);
+    Some(((parent, base, downloadable), rank))
+}
+
+/// Keeps only the highest resolution variant of each `_tex.sc` file in
+/// `paths`, and returns the others, each with the variant kept instead.
+///
+/// `ui_highres_tex.sc` is preferred over `ui_tex.sc`, which is preferred over
+/// `ui_lowres_tex.sc`. Downloadable variants, like `ui_lowres_dl_tex.sc`, are
+/// compared with each other, and only files in the same directory are
+/// compared. Other paths are kept.
+pub fn prefer_highres(paths: &mut Vec<PathBuf>) -> Vec<(PathBuf, PathBuf)> {
+    let mut best: HashMap<_, (u8, PathBuf)> = HashMap::new();
+    for path in paths.iter() {
+        if let Some((file, rank)) = resolution_variant(path) {
+            match best.get(&file) {
+                Some((best_rank, _)) if *best_rank >= rank => {}
+                _ => {
+                    best.insert(file, (rank, path.clone()));
+                }
+            }
+        }
+    }
+
+    let mut skipped = Vec::new();
+    paths.retain(|path| match resolution_variant(path) {
+        Some((file, _)) => {
+            let (_, best_path) = &best[&file];
+            if best_path != path {
+                skipped.push((path.clone(), best_path.clone()));
+            }
+            best_path == path
+        }
+        None => true,
+    });
+    skipped.sort();
+
+    skipped
+}
@@ -0,0 +1,181 @@
+//! Detection of identical sprites across files.
+
+use image::RgbaImage;
+use md5::{Digest, Md5};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// What [`Deduplicator`] does with sprites whose pixels are identical to those
+/// of a sprite saved before.
+///
+/// [`Deduplicator`]: ./struct.Deduplicator.html
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DedupeMode {
+    /// Duplicates aren't saved.
+    #[default]
+    Skip,
+    /// Duplicates are saved as hard links to the first image.
+    HardLink,
+    /// Duplicates are saved as symbolic links to the first image.
+    SymLink,
+    /// Duplicates are saved like any other sprite. They are only recorded.
+    Report,
+}
+
+/// A sprite whose pixels are identical to those of a sprite saved before.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Duplicate {
+    /// Path the duplicate is, or would have been, saved at.
+    pub path: PathBuf,
+    /// Path of the first image with the same pixels.
+    pub original: PathBuf,
+}
+
+/// The images of an original sprite, saved by the first sprite with its
+/// pixels.
+#[derive(Clone, Debug)]
+pub(crate) struct Original {
+    pub path: PathBuf,
+    pub alpha_path: Option<PathBuf>,
+}
+
+/// Finds sprites with identical pixels, across all files it is used for, with
+/// [`TexOptions::dedupe`].
+///
+/// Sprites are compared by the hash of their decoded pixels, after they are
+/// flipped and cropped, so images that are encoded differently are still
+/// found. A single deduplicator can be shared by files extracted in parallel.
+///
+/// [`TexOptions::dedupe`]: ./struct.TexOptions.html#structfield.dedupe
+#[derive(Debug, Default)]
+pub struct Deduplicator {
+    mode: DedupeMode,
+    seen: Mutex<HashMap<[u8; 16], Original>>,
+    duplicates: Mutex<Vec<Duplicate>>,
+}
+
+/// Deduplicators are equal only if they are the same one, as they hold the
+/// sprites that were seen.
+impl PartialEq for Deduplicator {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Deduplicator {
+    /// Creates a deduplicator that hasn't seen any sprite.
+    pub fn new(mode: DedupeMode) -> Self {
+        Self {
+            mode,
+            ..Default::default()
+        }
+    }
+
+    /// What is done with duplicates.
+    pub fn mode(&self) -> DedupeMode {
+        self.mode
+    }
+
+    /// Returns the duplicates found so far, in the order they were found.
+    pub fn duplicates(&self) -> Vec<Duplicate> {
+        self.duplicates
+            .lock()
+            .expect("Expected the duplicates to be readable.")
+            .clone()
+    }
+
+    /// Returns the original of `image` if one was seen before, or records
+    /// `image` as the original of its pixels.
+    ///
+    /// The check and the record happen at once, so only one of the sprites
+    /// with the same pixels that are checked in parallel is the original.
+    pub(crate) fn check(
+        &self,
+        image: &RgbaImage,
+        path: &Path,
+        alpha_path: Option<&Path>,
+    ) -> Option<Original> {
+        let mut hasher = Md5::new();
+        hasher.update(image.width().to_le_bytes());
+        hasher.update(image.height().to_le_bytes());
+        hasher.update(image.as_raw());
+        let hash: [u8; 16] = hasher.finalize().into();
+
+        let mut seen = self.seen.lock().expect("Expected the hashes to be usable.");
+        match seen.get(&hash) {
+            Some(original) => {
+                self.duplicates
+                    .lock()
+                    .expect("Expected the duplicates to be usable.")
+                    .push(Duplicate {
+                        path: path.to_path_buf(),
+                        original: original.path.clone(),
+                    });
+                Some(original.clone())
+            }
+            None => {
+                seen.insert(
+                    hash,
+                    Original {
+                        path: path.to_path_buf(),
+                        alpha_path: alpha_path.map(Path::to_path_buf),
+                    },
+                );
+                None
+            }
+        }
+    }
+
+    /// Saves `path` as a link to `original`, as the mode says. Returns `false`
+    /// if the link couldn't be made.
+    ///
+    /// The original may not have been saved yet if it is extracted in
+    /// parallel, in which case hard links can't be made, and the duplicate
+    /// should be saved as a copy instead.
+    pub(crate) fn link(&self, original: &Path, path: &Path) -> bool {
+        // A link can't replace an existing file.
+        if path.symlink_metadata().is_ok() {
+            let _ = fs::remove_file(path);
+        }
+
+        let result = match self.mode {
+            DedupeMode::HardLink => fs::hard_link(original, path),
+            DedupeMode::SymLink => symlink(original, path),
+            _ => return false,
+        };
+
+        result.is_ok()
+    }
+}
+
+/// Returns the absolute path of `path`, so that links to it stay valid from
+/// other directories.
+fn absolute(path: &Path) -> PathBuf {
+    match path.parent().map(fs::canonicalize) {
+        Some(Ok(dir)) => dir.join(path.file_name().unwrap_or_default()),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(unix)]
+fn symlink(original: &Path, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(absolute(original), path)
+}
+
+#[cfg(windows)]
+fn symlink(original: &Path, path: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(absolute(original), path)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn symlink(_original: &Path, _path: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Symbolic links aren't supported.",
+    ))
+}
@@ -13,6 +13,13 @@ pub enum Error {
     DecompressionError(String),
     /// Returned when an IO operation fails.
     IoError(String),
+    /// Returned when a stream ends before all of the expected data is read.
+    UnexpectedEof(String),
+    /// Returned when the `SC` header of a file has a version that isn't known.
+    UnsupportedVersion(String),
+    /// Returned when the hash in the header of a file doesn't match its
+    /// decompressed data.
+    HashMismatch(String),
     /// Returned when a non-specific, miscellaneous error occurs.
     ///
     /// It is also returned when a string is used to create an `Error` directly.
@@ -26,11 +33,29 @@ impl Error {
             Self::UnknownPixel(e) => e,
             Self::DecompressionError(e) => e,
             Self::IoError(e) => e,
+            Self::UnexpectedEof(e) => e,
+            Self::UnsupportedVersion(e) => e,
+            Self::HashMismatch(e) => e,
             Self::Other(e) => e,
         }
     }
 }
 
+impl Error {
+    /// Adds the name of the file that was being processed to the message of a
+    /// [`DecompressionError`].
+    ///
+    /// [`DecompressionError`]: #variant.DecompressionError
+    pub(crate) fn in_file(self, file_name: &str) -> Self {
+        match self {
+            Self::DecompressionError(e) => {
+                Self::DecompressionError(format!("{}: {}", file_name, e))
+            }
+            e => e,
+        }
+    }
+}
+
 impl StdError for Error {}
 
 impl Display for Error {
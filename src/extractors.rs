@@ -1,3 +1,5 @@
 pub mod csv;
+#[cfg(feature = "fs")]
 pub mod sc;
+pub mod sctx;
 pub mod tex;
@@ -1,31 +1,259 @@
-use crate::{error::Error, utils};
-use colored::Colorize;
+use crate::{
+    error::Error,
+    extractors::tex::{decompress_sc_checked, HashCheck},
+    utils::{self, Decompression},
+};
+use serde_json::{Map, Value};
 use std::{fs, path::Path};
 
+/// Options that control how [`process_csv_with_options`] saves the decoded
+/// file.
+///
+/// The default options save it as a csv file, like [`process_csv`] does.
+///
+/// [`process_csv_with_options`]: ./fn.process_csv_with_options.html
+/// [`process_csv`]: ./fn.process_csv.html
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CsvOptions {
+    /// Saves the table as a JSON array of objects, keyed by the header row,
+    /// instead of a csv file.
+    ///
+    /// The second row of Supercell's csv files holds the type of each column.
+    /// `int` and `boolean` values are saved as JSON numbers and booleans, the
+    /// rest as strings. `boolean` values may be `true`, `false`, `1` or `0`.
+    /// Empty cells are saved as `null`.
+    pub json: bool,
+    /// Largest size of the decompressed file, in bytes. If this is `None`,
+    /// 512 MiB is used. See [`decompress_with_limit`].
+    ///
+    /// [`decompress_with_limit`]: ./fn.decompress_with_limit.html
+    pub max_decompressed_size: Option<usize>,
+    /// Treats the file as csv data that was already decompressed and saves it
+    /// as it is.
+    ///
+    /// Printable data without the signature of a known codec is already
+    /// treated this way, see [`is_decompressed_csv`]. This forces it for data
+    /// that isn't.
+    ///
+    /// [`is_decompressed_csv`]: ./fn.is_decompressed_csv.html
+    pub assume_decompressed: bool,
+}
+
+/// Returns `true` if `data` looks like csv data that was already
+/// decompressed.
+///
+/// The data must not start with the signature of a known codec, see
+/// [`detect_codec`], and must be UTF-8 text, without control characters
+/// other than tabs and line breaks, with at least one comma or line break.
+///
+/// [`detect_codec`]: ./fn.detect_codec.html
+pub fn is_decompressed_csv(data: &[u8]) -> bool {
+    if data.starts_with(b"SC") || utils::detect_codec(data) != utils::Codec::Uncompressed {
+        return false;
+    }
+
+    match std::str::from_utf8(data) {
+        Ok(text) => {
+            !text
+                .chars()
+                .any(|c| c.is_control() && !['\t', '\r', '\n'].contains(&c))
+                && text.contains([',', '\n'])
+        }
+        Err(_) => false,
+    }
+}
+
+/// Splits a line of csv data into its fields.
+///
+/// Fields may be quoted with `"`, in which case they can hold commas. Quotes
+/// inside quoted fields are escaped by doubling them.
+fn split_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields
+}
+
+/// Converts a cell to a JSON value of the column's `kind`.
+///
+/// Returns `None` if the cell is not a valid value of its column's type.
+/// Columns of unknown types hold strings.
+fn to_value(cell: &str, kind: &str) -> Option<Value> {
+    if cell.is_empty() {
+        return Some(Value::Null);
+    }
+
+    match kind.to_ascii_lowercase().as_str() {
+        "int" => cell.parse::<i64>().ok().map(Value::from),
+        "boolean" => match cell.to_ascii_lowercase().as_str() {
+            "true" | "1" => Some(Value::Bool(true)),
+            "false" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        _ => Some(Value::from(cell)),
+    }
+}
+
+/// Converts decoded csv data into a JSON array of objects, one for every row
+/// after the header and type rows. See [`CsvOptions::json`].
+///
+/// ## Error
+///
+/// [`Error::Other`] is returned if a cell doesn't match the type of its
+/// column.
+///
+/// [`CsvOptions::json`]: ./struct.CsvOptions.html#structfield.json
+/// [`Error::Other`]: ./error/enum.Error.html#variant.Other
+fn to_json(data: &str) -> Result<Value, Error> {
+    let mut lines = data
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(number, line)| (number + 1, split_line(line)));
+    let header = lines.next().map(|(_, line)| line).unwrap_or_default();
+    let types = lines.next().map(|(_, line)| line).unwrap_or_default();
+
+    let mut rows = Vec::new();
+    for (number, row) in lines {
+        let mut object = Map::new();
+        for (i, name) in header.iter().enumerate() {
+            let cell = row.get(i).map(String::as_str).unwrap_or_default();
+            let kind = types.get(i).map(String::as_str).unwrap_or_default();
+            let value = to_value(cell, kind).ok_or_else(|| {
+                Error::Other(format!(
+                    "Line {}, column `{}`: `{}` is not a valid {} value.",
+                    number, name, cell, kind
+                ))
+            })?;
+            object.insert(name.clone(), value);
+        }
+
+        rows.push(Value::Object(object));
+    }
+
+    Ok(Value::Array(rows))
+}
+
 /// Processes encoded, raw `.csv` file data.
 ///
 /// The data passed here must be **compressed/raw**. Passing uncompressed or
 /// decoded csv file data will result in [`Error::DecompressionError`].
 ///
+/// Chunked `.sc2` files, whose names end with `.csv.sc2`, start with an `SC`
+/// header and are decompressed like [`decompress_sc`] does. They are saved
+/// without the `.sc2` extension.
+///
+/// Use [`process_csv_with_options`] to save the file as JSON instead.
+///
 /// ## Error
 ///
 /// If decompression is unsuccessful, [`Error::DecompressionError`] is returned.
 ///
+/// The second row of the file declares the type of each column. If a cell
+/// doesn't match the type of its column, like a non-numeric value in an `int`
+/// column, [`Error::Other`] is returned and nothing is saved.
+///
 /// [`Error::IoError`] is returned if an IO operation fails.
 ///
+/// [`decompress_sc`]: ./fn.decompress_sc.html
+/// [`process_csv_with_options`]: ./fn.process_csv_with_options.html
 /// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
+/// [`Error::Other`]: ./error/enum.Error.html#variant.Other
 /// [`Error::IoError`]: ./error/enum.Error.html#variant.IoError
+#[cfg(feature = "fs")]
 pub fn process_csv(data: &[u8], file_name: &str, out_dir: &Path) -> Result<(), Error> {
-    let mut output = Vec::new();
+    process_csv_with_options(data, file_name, out_dir, &CsvOptions::default())
+}
 
-    match utils::decompress(data, &mut output) {
-        Ok(c) => c,
-        Err(e) => return Err(e),
+/// Processes encoded, raw `.csv` file data and saves it as described by
+/// `options`.
+///
+/// This is the same as [`process_csv`], except that the file can be saved as
+/// JSON, with a `.json` extension. See [`CsvOptions`].
+///
+/// ## Error
+///
+/// The errors are the same as those of [`process_csv`].
+///
+/// [`process_csv`]: ./fn.process_csv.html
+/// [`CsvOptions`]: ./struct.CsvOptions.html
+#[cfg(feature = "fs")]
+pub fn process_csv_with_options(
+    data: &[u8],
+    file_name: &str,
+    out_dir: &Path,
+    options: &CsvOptions,
+) -> Result<(), Error> {
+    let limit = options
+        .max_decompressed_size
+        .unwrap_or(utils::DEFAULT_MAX_DECOMPRESSED_SIZE);
+    // Chunked `.sc2` files start with an `SC` header. Plain csv data is saved
+    // as it is.
+    let (output, file_name) = match file_name.strip_suffix(".sc2") {
+        name if options.assume_decompressed || is_decompressed_csv(data) => {
+            (Ok(data.to_vec()), name.unwrap_or(file_name))
+        }
+        Some(name) if data.starts_with(b"SC") => (
+            decompress_sc_checked(
+                data,
+                HashCheck::Ignore,
+                &Decompression {
+                    limit,
+                    ..Default::default()
+                },
+            ),
+            name,
+        ),
+        _ => (utils::decompress_with_limit(data, limit), file_name),
     };
+    let output = output.map_err(|e| e.in_file(file_name))?;
 
-    println!("\nExtracting {} file...", file_name.green().bold());
+    log::info!("Extracting {} file...", file_name);
 
-    fs::write(out_dir.join(file_name), output)?;
+    let table = to_json(&String::from_utf8_lossy(&output))
+        .map_err(|e| Error::Other(format!("{}: {}", file_name, e)))?;
+
+    if options.json {
+        let json = serde_json::to_string_pretty(&table).map_err(|e| Error::Other(e.to_string()))?;
+        fs::write(out_dir.join(file_name).with_extension("json"), json)?;
+    } else {
+        fs::write(out_dir.join(file_name), output)?;
+    }
 
     Ok(())
 }
+
+/// Packs plain `.csv` data back into the compressed layout of the games'
+/// `.csv` files, the inverse of [`process_csv`].
+///
+/// The data is compressed with LZMA. The 9-byte header holds the properties
+/// byte, the dictionary size and the size of `data` as a 32-bit integer,
+/// without the four `\x00` bytes that [`decompress`] adds before
+/// decompressing. `.csv` files have no `SC` header, so there is no hash to
+/// add.
+///
+/// ## Error
+///
+/// If `data` is larger than 4 GiB, [`Error::Other`] is returned.
+///
+/// [`process_csv`]: ./fn.process_csv.html
+/// [`decompress`]: ./fn.decompress.html
+/// [`Error::Other`]: ./error/enum.Error.html#variant.Other
+pub fn pack_csv(data: &[u8]) -> Result<Vec<u8>, Error> {
+    utils::compress(data)
+}
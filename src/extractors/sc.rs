@@ -1,4 +1,4 @@
-use crate::{error::Error, utils::Reader};
+use crate::{error::Error, extractors::tex::SpriteNaming, utils::Reader};
 use colored::Colorize;
 use conv::ValueInto;
 use image::{imageops, GenericImage, GenericImageView, GrayImage, ImageBuffer, Pixel, RgbaImage};
@@ -11,6 +11,7 @@ use imageproc::{
 use rayon::prelude::*;
 use std::{
     cmp::Ordering,
+    collections::HashSet,
     io::Cursor,
     path::Path,
     sync::{Arc, Mutex},
@@ -21,12 +22,11 @@ use std::{
 struct SheetItem {
     x: u32,
     y: u32,
-    divider: u32,
 }
 
 impl SheetItem {
-    fn new(x: u32, y: u32, divider: u32) -> Self {
-        Self { x, y, divider }
+    fn new(x: u32, y: u32) -> Self {
+        Self { x, y }
     }
 }
 
@@ -114,10 +114,30 @@ impl Rotation {
     }
 }
 
+/// Options that control how [`process_sc_with_options`] saves sprites.
+///
+/// The default options save sprites like [`process_sc`] does.
+///
+/// [`process_sc_with_options`]: ./fn.process_sc_with_options.html
+/// [`process_sc`]: ./fn.process_sc.html
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScOptions {
+    /// Names sprites after the exports that use them, instead of numbering
+    /// them.
+    ///
+    /// A sprite is named after the first export whose movie clip uses it. If
+    /// more than one sprite gets the same name, only the first one is named
+    /// after it. The others, and sprites that aren't used by any export, are
+    /// still numbered, since a name with `_`s appended could be the name of
+    /// another export.
+    pub export_names: bool,
+}
+
 /// Processes extracted `.sc` file data.
 ///
 /// This function does NOT process files with `.sc` extension. It process
-/// the file generated after using QuickBMS on the `.sc` file. The png images
+/// the file generated after using QuickBMS on the `.sc` file, which is the
+/// same as the `.sc` file decompressed with [`decompress_sc`]. The png images
 /// extracted from `_tex.sc` file corresponding to this `.sc` file must be
 /// present in `png_dir`.
 ///
@@ -137,6 +157,7 @@ impl Rotation {
 /// If `out_dir` does not exist or if reading images from `png_dir` is not
 /// possible, [`Error::IoError`] is returned.
 ///
+/// [`decompress_sc`]: ./fn.decompress_sc.html
 /// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
 /// [`Error::Other`]: ./error/enum.Error.html#variant.Other
 /// [`Error::IoError`]: ./error/enum.Error.html#variant.IoError
@@ -146,9 +167,39 @@ pub fn process_sc(
     out_dir: &Path,
     png_dir: &Path,
     parallelize: bool,
+) -> Result<(), Error> {
+    process_sc_with_options(
+        data,
+        file_name,
+        out_dir,
+        png_dir,
+        parallelize,
+        &ScOptions::default(),
+    )
+}
+
+/// Processes extracted `.sc` file data and saves the sprites as specified by
+/// `options`.
+///
+/// This is the same as [`process_sc`], except for how the sprites are named.
+/// See [`ScOptions`] for the available options.
+///
+/// ## Errors
+///
+/// The errors are the same as those of [`process_sc`].
+///
+/// [`process_sc`]: ./fn.process_sc.html
+/// [`ScOptions`]: ./struct.ScOptions.html
+pub fn process_sc_with_options(
+    data: &[u8],
+    file_name: &str,
+    out_dir: &Path,
+    png_dir: &Path,
+    parallelize: bool,
+    options: &ScOptions,
 ) -> Result<(), Error> {
     if !parallelize {
-        println!("\nProcessing `{}` image(s)...", file_name.green().bold());
+        log::info!("Processing `{}` image(s)...", file_name);
     }
 
     let mut stream = Reader::new(Cursor::new(data));
@@ -165,7 +216,7 @@ pub fn process_sc(
 
     let mut sheet_data = Vec::new();
     for _ in 0..total_textures {
-        sheet_data.push(SheetItem::new(0, 0, 1));
+        sheet_data.push(SheetItem::new(0, 0));
     }
 
     let mut sprite_data = Vec::new();
@@ -176,9 +227,21 @@ pub fn process_sc(
     let sheet_image = Arc::new(Mutex::new(Vec::new()));
 
     for x in 0..total_textures as usize {
-        let png_path = png_dir.join(format!("{}_tex{}.png", file_name, "_".repeat(x)));
+        // The images may have been saved with either naming.
+        let tex_name = format!("{}_tex", file_name);
+        let candidates: Vec<_> = [SpriteNaming::Numbered, SpriteNaming::Underscores]
+            .iter()
+            .map(|naming| {
+                let name = naming.sprite_name(&tex_name, x, total_textures as usize);
+                png_dir.join(format!("{}.png", name))
+            })
+            .collect();
+        let png_path = candidates
+            .iter()
+            .find(|path| path.exists())
+            .unwrap_or(&candidates[0]);
         if png_path.exists() {
-            let opened_image = match image::open(&png_path) {
+            let opened_image = match image::open(png_path) {
                 Ok(i) => i,
                 Err(_) => {
                     return Err(Error::IoError(format!(
@@ -202,21 +265,26 @@ pub fn process_sc(
         }
     }
 
-    // Read 500 bytes
-    stream.read(5);
+    // Skip 5 bytes.
+    stream.skip(5);
 
     let export_count = stream.read_uint16();
 
+    let mut export_ids = Vec::new();
     for _ in 0..export_count {
-        stream.read_uint16();
+        export_ids.push(stream.read_uint16());
     }
 
+    let mut export_names = Vec::new();
     for _ in 0..export_count {
         let length = stream.read_byte() as usize;
-        stream.read_string(length);
+        export_names.push(stream.read_string(length));
     }
 
-    while stream.len() > 0 {
+    // Movie clip IDs along with the IDs of the shapes and clips they use.
+    let mut clip_binds = Vec::new();
+
+    while !stream.is_empty() {
         let data_block_tag = hex::encode(stream.read(1));
         let data_block_size = stream.read_uint32();
 
@@ -300,7 +368,7 @@ pub fn process_sc(
                 }
             }
 
-            stream.read(5);
+            stream.skip(5);
             offset_shape += 1;
             continue;
         } else if data_block_tag == "08" {
@@ -312,7 +380,7 @@ pub fn process_sc(
             continue;
         } else if data_block_tag == "0c" {
             // An animation.
-            let _clip_id = stream.read_uint16();
+            let clip_id = stream.read_uint16();
             let _clip_fps = stream.read_byte();
             let _clip_frame_count = stream.read_uint16();
 
@@ -325,9 +393,11 @@ pub fn process_sc(
             }
 
             let cnt_2 = stream.read_int16();
+            let mut binds = Vec::new();
             for _ in 0..cnt_2 {
-                stream.read_int16();
+                binds.push(stream.read_uint16());
             }
+            clip_binds.push((clip_id, binds));
 
             for _ in 0..cnt_2 {
                 stream.read_byte();
@@ -340,27 +410,84 @@ pub fn process_sc(
                 }
             }
         } else {
-            stream.read(data_block_size as usize);
+            stream.skip(data_block_size as usize);
         }
     }
 
+    let shape_names = if options.export_names {
+        name_shapes(&sprite_data, &export_ids, &export_names, &clip_binds)
+    } else {
+        vec![None; sprite_data.len()]
+    };
+
     write_shape(
         &mut sprite_data,
         &mut sheet_data,
         shape_count,
         sheet_image,
-        &file_name,
-        &out_dir,
+        file_name,
+        &shape_names,
+        out_dir,
     )
 }
 
+/// Returns the export name of each shape, in the same order as `sprite_data`.
+///
+/// An export refers to a movie clip (or, rarely, a shape) by its ID. Shapes
+/// used by the movie clip are named after the export. Characters that can't
+/// be used in file names are replaced with `_`s.
+fn name_shapes(
+    sprite_data: &[SpriteItem],
+    export_ids: &[u16],
+    export_names: &[String],
+    clip_binds: &[(u16, Vec<u16>)],
+) -> Vec<Option<String>> {
+    let mut shape_names = vec![None; sprite_data.len()];
+    let mut used_names = HashSet::new();
+
+    for (export_id, export_name) in export_ids.iter().zip(export_names) {
+        let bound_ids = match clip_binds.iter().find(|(id, _)| id == export_id) {
+            Some((_, binds)) => binds.clone(),
+            None => vec![*export_id],
+        };
+
+        let name: String = export_name
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+                _ => '_',
+            })
+            .collect();
+
+        for bound_id in bound_ids {
+            let index = sprite_data
+                .iter()
+                .position(|sprite| sprite.id == bound_id as u32);
+
+            if let Some(index) = index {
+                if shape_names[index].is_some() {
+                    continue;
+                }
+
+                // Later shapes with the same name keep their numbers.
+                if used_names.insert(name.clone()) {
+                    shape_names[index] = Some(name.clone());
+                }
+            }
+        }
+    }
+
+    shape_names
+}
+
 /// Writes shapes from the data on images.
 fn write_shape(
-    sprite_data: &mut Vec<SpriteItem>,
-    sheet_data: &mut Vec<SheetItem>,
+    sprite_data: &mut [SpriteItem],
+    sheet_data: &mut [SheetItem],
     shape_count: u16,
     sheet_image: Arc<Mutex<Vec<image::DynamicImage>>>,
     file_name: &str,
+    shape_names: &[Option<String>],
     out_dir: &Path,
 ) -> Result<(), Error> {
     let mut max_left = 0;
@@ -472,10 +599,10 @@ fn write_shape(
                 max_above
             };
 
-            temp_x = (sprite_item.regions[y].sprite_width as i32
-                - sprite_item.regions[y].region_zero_x as i32) as i32;
-            temp_y = (sprite_item.regions[y].sprite_height as i32
-                - sprite_item.regions[y].region_zero_y as i32) as i32;
+            temp_x = sprite_item.regions[y].sprite_width as i32
+                - sprite_item.regions[y].region_zero_x as i32;
+            temp_y = sprite_item.regions[y].sprite_height as i32
+                - sprite_item.regions[y].region_zero_y as i32;
 
             max_right = if temp_x > max_right {
                 temp_x
@@ -569,14 +696,14 @@ fn write_shape(
                     .copy_from(&rotated_image, paste_left, paste_top)
                     .is_err()
                 {
-                    println!(
-                        "{}",
-                        "There was an error processing a portion of the image.".red()
-                    );
+                    log::error!("There was an error processing a portion of the image.");
                 }
             });
 
-        let save_path = out_dir.join(format!("{}_sprite_{:0>2$}.png", file_name, x, max_range));
+        let save_path = match &shape_names[x] {
+            Some(name) => out_dir.join(format!("{}_{}.png", file_name, name)),
+            None => out_dir.join(format!("{}_sprite_{:0>2$}.png", file_name, x, max_range)),
+        };
 
         if out_image.lock().unwrap().save(save_path).is_err() {
             return Err(Error::IoError(format!("{}", "Unable to save image.".red())));
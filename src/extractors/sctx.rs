@@ -0,0 +1,172 @@
+use crate::{
+    error::Error,
+    extractors::tex::{
+        check_size, decode_image, decompress_sc_checked, HashCheck, DEFAULT_MAX_SIZE,
+    },
+    format::{OutputFormat, PngCompression},
+    utils::{self, Decompression, Reader},
+};
+use colored::Colorize;
+use image::DynamicImage;
+use std::{io::Cursor, path::Path, sync::Arc};
+
+/// Processes compressed, raw `.sctx` file data.
+///
+/// `.sctx` files hold a single texture, stored apart from the `.sc` file that
+/// uses it. They are compressed like `_tex.sc` files. The decompressed data
+/// starts with a header: the pixel type (`u8`, same as a `_tex.sc` sub-type),
+/// the width and height (`u16` each) and the number of mipmap levels (`u8`).
+/// Every level follows, largest first, prefixed with its size in bytes
+/// (`u32`).
+///
+/// The header of the compressed file is the same as that of `_tex.sc` files,
+/// including the MD5 hash of the decompressed data. [`process_sctx`] doesn't
+/// check the hash, see [`SctxOptions::hash_check`].
+///
+/// This layout is what the parser expects; it hasn't been checked against
+/// `.sctx` files shipped by the game yet.
+///
+/// Only the largest level is saved, as a png image in the output directory
+/// (`out_dir`). The image is named after the file, without the `.sctx`
+/// extension.
+///
+/// `parallelize` tells if the directory files are processed parallelly. It is
+/// simply used to control the stdout output.
+///
+/// ## Errors
+///
+/// If decompression is unsuccessful, [`Error::DecompressionError`] is returned.
+/// If the data ends before the largest level is read, [`Error::UnexpectedEof`]
+/// is returned. If the pixel type is unknown, [`Error::UnknownPixel`] is
+/// returned. Textures larger than 16384 pixels per side, which are likely
+/// corrupt, return [`Error::Other`].
+///
+/// [`Error::IoError`] is returned if an IO operation fails.
+///
+/// [`process_sctx`]: ./fn.process_sctx.html
+/// [`SctxOptions::hash_check`]: ./struct.SctxOptions.html#structfield.hash_check
+/// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
+/// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+/// [`Error::UnknownPixel`]: ./error/enum.Error.html#variant.UnknownPixel
+/// [`Error::Other`]: ./error/enum.Error.html#variant.Other
+/// [`Error::IoError`]: ./error/enum.Error.html#variant.IoError
+#[cfg(feature = "fs")]
+pub fn process_sctx(
+    raw_data: &[u8],
+    file_name: &str,
+    out_dir: &Path,
+    parallelize: bool,
+) -> Result<(), Error> {
+    process_sctx_with_options(
+        raw_data,
+        file_name,
+        out_dir,
+        parallelize,
+        &SctxOptions::default(),
+    )
+}
+
+/// Options that control how [`process_sctx_with_options`] saves textures.
+///
+/// The default options save the texture as a PNG image, like
+/// [`process_sctx`] does.
+///
+/// [`process_sctx_with_options`]: ./fn.process_sctx_with_options.html
+/// [`process_sctx`]: ./fn.process_sctx.html
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SctxOptions {
+    /// Format in which the image is saved.
+    pub format: OutputFormat,
+    /// How hard PNG images are compressed. It isn't used for other formats.
+    pub png_compression: PngCompression,
+    /// Largest size of the decompressed data, in bytes. If this is `None`,
+    /// 512 MiB is used. See [`TexOptions::max_decompressed_size`].
+    ///
+    /// [`TexOptions::max_decompressed_size`]: ./struct.TexOptions.html#structfield.max_decompressed_size
+    pub max_decompressed_size: Option<usize>,
+    /// The dictionary that zstd-compressed files were compressed with. See
+    /// [`TexOptions::zstd_dictionary`].
+    ///
+    /// [`TexOptions::zstd_dictionary`]: ./struct.TexOptions.html#structfield.zstd_dictionary
+    pub zstd_dictionary: Option<Arc<Vec<u8>>>,
+    /// What to do when the hash in the header doesn't match the decompressed
+    /// data. See [`TexOptions::hash_check`].
+    ///
+    /// [`TexOptions::hash_check`]: ./struct.TexOptions.html#structfield.hash_check
+    pub hash_check: HashCheck,
+}
+
+/// Processes compressed, raw `.sctx` file data and saves the texture as
+/// described by `options`.
+///
+/// This is the same as [`process_sctx`], except that the image is saved in
+/// `options.format`, with its extension instead of `.png`.
+///
+/// ## Errors
+///
+/// The errors are the same as those of [`process_sctx`].
+///
+/// [`process_sctx`]: ./fn.process_sctx.html
+#[cfg(feature = "fs")]
+pub fn process_sctx_with_options(
+    raw_data: &[u8],
+    file_name: &str,
+    out_dir: &Path,
+    parallelize: bool,
+    options: &SctxOptions,
+) -> Result<(), Error> {
+    if !parallelize {
+        log::info!("Extracting {} image...", file_name);
+    }
+
+    let settings = Decompression {
+        limit: options
+            .max_decompressed_size
+            .unwrap_or(utils::DEFAULT_MAX_DECOMPRESSED_SIZE),
+        zstd_dictionary: options.zstd_dictionary.as_deref().map(Vec::as_slice),
+    };
+    let output = decompress_sc_checked(raw_data, options.hash_check, &settings)
+        .map_err(|e| e.in_file(file_name))?;
+    let mut reader = Reader::new(Cursor::new(&output));
+
+    let pixel_type = reader.try_read_byte()?;
+    let width = reader.try_read_uint16()? as u32;
+    let height = reader.try_read_uint16()? as u32;
+    let level_count = reader.try_read_byte()?;
+
+    check_size(width, height, DEFAULT_MAX_SIZE)?;
+
+    if level_count == 0 {
+        return Err(Error::Other("Texture has no mipmap levels.".to_string()));
+    }
+
+    // Smaller levels are skipped.
+    let level_size = reader.try_read_uint32()?;
+    let level = reader.try_slice(level_size as usize)?;
+    let img = decode_image(
+        &mut Reader::new(Cursor::new(level)),
+        pixel_type,
+        width,
+        height,
+        parallelize,
+    )?;
+
+    let path = out_dir.join(format!(
+        "{}.{}",
+        file_name.trim_end_matches(".sctx"),
+        options.format.extension()
+    ));
+    if options
+        .format
+        .save(
+            &DynamicImage::ImageRgba8(img),
+            &path,
+            options.png_compression,
+        )
+        .is_err()
+    {
+        return Err(Error::IoError("Failed to save image!".red().to_string()));
+    }
+
+    Ok(())
+}
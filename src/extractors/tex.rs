@@ -1,108 +1,2243 @@
 use crate::{
+    dedupe::{DedupeMode, Deduplicator},
     error::Error,
-    utils::{self, Reader},
+    format::{self, OutputFormat, PngCompression},
+    utils::{self, Codec, Decompression, Reader},
 };
-use byteorder::{BigEndian, ReadBytesExt};
 use colored::Colorize;
-use image::{Rgba, RgbaImage};
-use std::{io::Cursor, path::Path};
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage, GenericImageView, GrayImage, ImageBuffer, Luma, LumaA, Rgb, RgbImage, Rgba,
+    RgbaImage,
+};
+use md5::{Digest, Md5};
+use rayon::prelude::*;
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::Sha256;
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    fs,
+    io::{self, Cursor, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+mod astc;
+mod atlas;
+mod bc;
+mod etc;
+mod ktx;
+mod pack;
+mod pvrtc;
+
+pub use atlas::{AtlasSprite, TexAtlas};
+pub use pack::{pack_tex, read_tex_sprites, TexSprite};
+
+/// Converts a pixel with premultiplied alpha to straight alpha, dividing its
+/// color by its alpha and rounding. Fully transparent pixels become black.
+fn unpremultiply(pixel: &mut [u8; 4]) {
+    let alpha = pixel[3] as u32;
+    for channel in pixel.iter_mut().take(3) {
+        *channel = match alpha {
+            0 => 0,
+            _ => ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8,
+        };
+    }
+}
+
+/// Extends a 4-bit color channel to 8 bits by replicating its high bits.
+fn extend_4(value: u8) -> u8 {
+    (value << 4) | value
+}
+
+/// Extends a 5-bit color channel to 8 bits by replicating its high bits.
+fn extend_5(value: u8) -> u8 {
+    (value << 3) | (value >> 2)
+}
+
+/// Extends a 6-bit color channel to 8 bits by replicating its high bits.
+fn extend_6(value: u8) -> u8 {
+    (value << 2) | (value >> 4)
+}
+
+/// Decodes a single pixel of `pixel_type` from the start of `bytes` and
+/// returns it as RGBA, along with the number of bytes it took.
+///
+/// One of the following types is valid: `0, 1, 2, 3, 4, 5, 6, 10`. These are
+/// the `_tex.sc` pixel types (sub-types) with a fixed size per pixel. Bytes
+/// after the pixel are ignored, so a row of pixels can be decoded by slicing
+/// off the returned number of bytes each time.
+///
+/// ## Errors
+///
+/// If `pixel_type` is not one of the above, [`Error::UnknownPixel`] is
+/// returned. If `bytes` is shorter than a pixel, [`Error::UnexpectedEof`] is
+/// returned.
+///
+/// [`Error::UnknownPixel`]: ./error/enum.Error.html#variant.UnknownPixel
+/// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+pub fn decode_pixel(bytes: &[u8], pixel_type: u8) -> Result<([u8; 4], usize), Error> {
+    let size = bytes_per_pixel(pixel_type)
+        .ok_or_else(|| Error::UnknownPixel(format!("Unknown pixel type ({}).", pixel_type)))?;
+    if bytes.len() < size {
+        return Err(Error::UnexpectedEof(format!(
+            "Expected {} more byte(s), but only {} are left.",
+            size,
+            bytes.len()
+        )));
+    }
+
+    let word = || u16::from_le_bytes([bytes[0], bytes[1]]);
+    let pixel = match pixel_type {
+        // RGB8888
+        0 | 1 => [bytes[0], bytes[1], bytes[2], bytes[3]],
+        // RGB4444
+        2 => {
+            let pixel = word();
+            [
+                extend_4(((pixel >> 12) & 0xF) as u8),
+                extend_4(((pixel >> 8) & 0xF) as u8),
+                extend_4(((pixel >> 4) & 0xF) as u8),
+                extend_4((pixel & 0xF) as u8),
+            ]
+        }
+        // RGBA5551, laid out like OpenGL's `GL_UNSIGNED_SHORT_5_5_5_1`: red in
+        // the highest bits and alpha in the lowest bit (not ARGB1555).
+        3 => {
+            let pixel = word();
+            [
+                extend_5(((pixel >> 11) & 0x1F) as u8),
+                extend_5(((pixel >> 6) & 0x1F) as u8),
+                extend_5(((pixel >> 1) & 0x1F) as u8),
+                // Alpha is a single bit.
+                if pixel & 0x1 == 1 { 255 } else { 0 },
+            ]
+        }
+        // RGB565
+        4 => {
+            let pixel = word();
+            [
+                extend_5(((pixel >> 11) & 0x1F) as u8),
+                extend_6(((pixel >> 5) & 0x3F) as u8),
+                extend_5((pixel & 0x1F) as u8),
+                // Alpha channel must always be 255 for type 4.
+                255,
+            ]
+        }
+        // LA44
+        5 => {
+            let luminance = extend_4(bytes[0] >> 4);
+            [luminance, luminance, luminance, extend_4(bytes[0] & 0xF)]
+        }
+        // LA88
+        6 => {
+            let pixel = word();
+            [
+                (pixel >> 8) as u8,
+                (pixel >> 8) as u8,
+                (pixel >> 8) as u8,
+                (pixel & 0xFF) as u8,
+            ]
+        }
+        // L8
+        _ => [bytes[0]; 4],
+    };
+
+    Ok((pixel, size))
+}
+
+/// Reads a single pixel from the stream and decodes it with
+/// [`decode_pixel`].
+///
+/// The stream is left as it is if the pixel type is unknown or the stream ends
+/// before the pixel.
+///
+/// ## Arguments
+///
+/// * `reader`: `Reader` representing the data stream.
+/// * `pixel_type`: The type of pixel. For `_tex.sc` data, it is the image sub-type.
+///
+/// [`decode_pixel`]: ./fn.decode_pixel.html
+fn convert_pixel(reader: &mut Reader<Cursor<&[u8]>>, pixel_type: u8) -> Result<[u8; 4], Error> {
+    let size = bytes_per_pixel(pixel_type).unwrap_or(0);
+    decode_pixel(reader.try_slice(size)?, pixel_type).map(|(pixel, _)| pixel)
+}
+
+/// Returns the number of bytes a single pixel of `pixel_type` takes, or `None`
+/// if the type is unknown or block-compressed.
+fn bytes_per_pixel(pixel_type: u8) -> Option<usize> {
+    match pixel_type {
+        0 | 1 => Some(4),
+        2 | 3 | 4 | 6 => Some(2),
+        5 | 10 => Some(1),
+        _ => None,
+    }
+}
+
+/// Returns the size in bytes of `count` items of `size` bytes each, or an
+/// error if it overflows `usize`, which it can on 32-bit targets.
+fn checked_size(count: usize, size: usize) -> Result<usize, Error> {
+    count.checked_mul(size).ok_or_else(|| {
+        Error::Other(format!(
+            "Image data size ({} x {} bytes) is too large.",
+            count, size
+        ))
+    })
+}
+
+/// Reads all pixels of `canvas` from the stream and places them as they are
+/// read.
+///
+/// See [`decode_pixel`] for the valid pixel types. The pixel type and the size
+/// of the data are checked before anything is read, so on error, the stream is
+/// left where it was. If `parallel` is `true`, large untiled images are
+/// decoded in bands of rows on several threads.
+///
+/// [`decode_pixel`]: ./fn.decode_pixel.html
+fn convert_pixels(
+    reader: &mut Reader<Cursor<&[u8]>>,
+    pixel_type: u8,
+    canvas: &mut Canvas,
+    parallel: bool,
+) -> Result<(), Error> {
+    let pixel_size = bytes_per_pixel(pixel_type)
+        .ok_or_else(|| Error::UnknownPixel(format!("Unknown pixel type ({}).", pixel_type)))?;
+
+    let size = checked_size(canvas.len() as usize, pixel_size)?;
+    if size > reader.len() {
+        return Err(Error::UnexpectedEof(format!(
+            "Expected {} more byte(s) of pixels, but only {} are left.",
+            size,
+            reader.len()
+        )));
+    }
+
+    let data = reader.try_slice(size)?;
+
+    // Untiled pixels are stored in rows, like the image holds them, so large
+    // images are decoded in bands of rows in parallel.
+    if parallel && canvas.tile_size.is_none() && canvas.len() >= PARALLEL_PIXELS {
+        let width = canvas.width() as usize;
+        return canvas
+            .image()
+            .par_chunks_mut(width * 4 * BAND_ROWS)
+            .zip(data.par_chunks(width * pixel_size * BAND_ROWS))
+            .try_for_each(|(band, pixels)| {
+                for (output, pixel) in band
+                    .chunks_exact_mut(4)
+                    .zip(pixels.chunks_exact(pixel_size))
+                {
+                    output.copy_from_slice(&decode_pixel(pixel, pixel_type)?.0);
+                }
+                Ok(())
+            });
+    }
+
+    for (i, pixel) in data.chunks_exact(pixel_size).enumerate() {
+        canvas.put(i as u32, decode_pixel(pixel, pixel_type)?.0);
+    }
+
+    Ok(())
+}
+
+/// Number of pixels from which untiled images are decoded in parallel, if
+/// they may be.
+const PARALLEL_PIXELS: u32 = 1 << 16;
+
+/// Number of rows each thread decodes at a time, for images decoded in
+/// parallel.
+const BAND_ROWS: usize = 64;
+
+/// Returns `true` if `pixel_type` is a block-compressed pixel type.
+///
+/// Block-compressed types can't be converted pixel by pixel. They are decoded
+/// with [`convert_blocks`] instead.
+///
+/// [`convert_blocks`]: ./fn.convert_blocks.html
+fn is_block_type(pixel_type: u8) -> bool {
+    matches!(pixel_type, 11..=18)
+}
+
+/// Reads block-compressed data from the stream and places the pixels of the
+/// whole image in `canvas`.
+///
+/// Blocks are stored left to right, top to bottom. If the image dimensions
+/// are not a multiple of the block dimensions, the blocks on the right and
+/// bottom edges are clamped to the image.
+///
+/// The following block-compressed types are valid: `11` (ETC1), `12` (ETC2 RGB),
+/// `13` (ETC2 RGBA), `14` (ASTC), `15` (PVRTC 4bpp), `16` (BC1), `17` (BC3) and
+/// `18` (BC3 with premultiplied alpha). ASTC data starts with an `.astc` header,
+/// which holds the block footprint. PVRTC blocks are stored in Morton order and
+/// are reordered into scanlines while decoding. Premultiplied alpha is converted
+/// to straight alpha.
+///
+/// If the stream ends in the middle of a block, [`Error::UnexpectedEof`] is
+/// returned.
+///
+/// ## Arguments
+///
+/// * `reader`: `Reader` representing the data stream.
+/// * `pixel_type`: The type of pixel. For `_tex.sc` data, it is the image sub-type.
+/// * `canvas`: The image the pixels are placed in. It holds the dimensions.
+///
+/// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+fn convert_blocks(
+    reader: &mut Reader<Cursor<&[u8]>>,
+    pixel_type: u8,
+    canvas: &mut Canvas,
+) -> Result<(), Error> {
+    // PVRTC pixels depend on the neighboring blocks, so the image is decoded
+    // as a whole.
+    if pixel_type == 15 {
+        let (width, height) = (canvas.width(), canvas.height());
+        let size = pvrtc::data_size(width, height);
+        let pixels = pvrtc::decode_4bpp(reader.try_slice(size)?, width, height);
+        for (i, pixel) in pixels.into_iter().enumerate() {
+            canvas.put(i as u32, pixel);
+        }
+        return Ok(());
+    }
+
+    let (block_width, block_height, block_size, decode) = block_format(reader, pixel_type)?;
+    decode_blocks(
+        reader,
+        (block_width, block_height, block_size),
+        &decode,
+        canvas,
+    )
+}
+
+/// Decodes a single block into its pixels in row-major order.
+type BlockDecoder = Box<dyn Fn(&[u8]) -> Vec<[u8; 4]>>;
+
+/// Returns the width and height of the blocks of `pixel_type` in pixels, their
+/// size in bytes and their decoder.
+///
+/// The ASTC header is read from the stream. PVRTC (`15`) isn't decoded block
+/// by block, so [`Error::UnknownPixel`] is returned for it.
+///
+/// [`Error::UnknownPixel`]: ./error/enum.Error.html#variant.UnknownPixel
+fn block_format(
+    reader: &mut Reader<Cursor<&[u8]>>,
+    pixel_type: u8,
+) -> Result<(u32, u32, usize, BlockDecoder), Error> {
+    let format: (u32, u32, usize, BlockDecoder) = match pixel_type {
+        // ETC1
+        11 => (4, 4, 8, Box::new(etc::decode_etc1_block)),
+        // ETC2 RGB
+        12 => (4, 4, 8, Box::new(etc::decode_etc2_block)),
+        // ETC2 RGBA, with EAC alpha
+        13 => (4, 4, 16, Box::new(etc::decode_etc2_rgba_block)),
+        // ASTC, with any 2D block footprint
+        14 => {
+            let (block_width, block_height) = astc::read_header(reader)?;
+            (
+                block_width,
+                block_height,
+                16,
+                Box::new(move |block| astc::decode_block(block, block_width, block_height)),
+            )
+        }
+        // BC1 (DXT1), with 1-bit alpha
+        16 => (4, 4, 8, Box::new(bc::decode_bc1_block)),
+        // BC3 (DXT5)
+        17 => (4, 4, 16, Box::new(bc::decode_bc3_block)),
+        // BC3 with premultiplied alpha (DXT4)
+        18 => (4, 4, 16, Box::new(bc::decode_bc3_premultiplied_block)),
+        _ => {
+            return Err(Error::UnknownPixel(format!(
+                "Unknown pixel type ({}).",
+                pixel_type
+            )))
+        }
+    };
+
+    Ok(format)
+}
+
+/// Reads the blocks of all pixels of `canvas` from the stream, decodes them
+/// with `decode` and places them one block at a time.
+///
+/// `block` holds the width and height of a block in pixels and its size in
+/// bytes. See [`convert_blocks`] for the block layout.
+///
+/// [`convert_blocks`]: ./fn.convert_blocks.html
+fn decode_blocks(
+    reader: &mut Reader<Cursor<&[u8]>>,
+    (block_width, block_height, block_size): (u32, u32, usize),
+    decode: &dyn Fn(&[u8]) -> Vec<[u8; 4]>,
+    canvas: &mut Canvas,
+) -> Result<(), Error> {
+    let (width, height) = (canvas.width(), canvas.height());
+    let block_count = (width.div_ceil(block_width) * height.div_ceil(block_height)) as usize;
+    let size = checked_size(block_count, block_size)?;
+    if size > reader.len() {
+        return Err(Error::UnexpectedEof(format!(
+            "Expected {} more byte(s) of blocks, but only {} are left.",
+            size,
+            reader.len()
+        )));
+    }
+
+    for block_y in 0..height.div_ceil(block_height) {
+        for block_x in 0..width.div_ceil(block_width) {
+            let block = decode(reader.try_slice(block_size)?);
+
+            for y in 0..block_height {
+                for x in 0..block_width {
+                    let (pixel_x, pixel_y) =
+                        (block_x * block_width + x, block_y * block_height + y);
+                    if pixel_x < width && pixel_y < height {
+                        canvas.put(
+                            pixel_y * width + pixel_x,
+                            block[(y * block_width + x) as usize],
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if sprites of `file_type` are wrapped in a KTX container.
+fn is_ktx_type(file_type: u8) -> bool {
+    matches!(file_type, 45 | 47)
+}
+
+/// Reads a KTX container from the stream and places the pixels of its first
+/// mipmap level in `canvas`.
+///
+/// The container is prefixed with its size in bytes. Its dimensions must match
+/// those of the sprite. `parallel` is passed on to [`convert_image`].
+///
+/// [`convert_image`]: ./fn.convert_image.html
+fn convert_ktx(
+    reader: &mut Reader<Cursor<&[u8]>>,
+    canvas: &mut Canvas,
+    parallel: bool,
+) -> Result<(), Error> {
+    let (width, height) = (canvas.width(), canvas.height());
+    let size = reader.try_read_uint32()?;
+    let texture = ktx::parse(reader.try_slice(size as usize)?)?;
+
+    if (texture.width, texture.height) != (width, height) {
+        return Err(Error::Other(format!(
+            "KTX texture size ({}x{}) doesn't match the sprite size ({}x{}).",
+            texture.width, texture.height, width, height
+        )));
+    }
+
+    let mut reader = Reader::new(Cursor::new(texture.data));
+    match texture.encoding {
+        ktx::Encoding::SubType(sub_type) => convert_image(&mut reader, sub_type, canvas, parallel),
+        ktx::Encoding::Astc(block_width, block_height) => decode_blocks(
+            &mut reader,
+            (block_width, block_height, 16),
+            &|block| astc::decode_block(block, block_width, block_height),
+            canvas,
+        ),
+    }
+}
+
+/// Returns the size of the square tiles the pixels of `file_type` are stored
+/// in, or `None` if they are stored in scanlines.
+///
+/// The tile size isn't stored in the file, so it is derived from the file
+/// type. It can be overridden with [`TexOptions::block_size`]:
+///
+/// | File type  | Pixel layout                                      |
+/// |------------|---------------------------------------------------|
+/// | `1`, `24`  | scanlines, left to right, top to bottom           |
+/// | `27`, `28` | 32x32 tiles, see [`tiled_position`]               |
+/// | `45`, `47` | a KTX container, whose pixels are laid out by KTX |
+///
+/// Type `1` is the oldest sprite type. Its pixels are plain scanlines, like
+/// those of type `24`, and aren't reassembled like those of the tiled types.
+///
+/// [`tiled_position`]: ./fn.tiled_position.html
+/// [`TexOptions::block_size`]: ./struct.TexOptions.html#structfield.block_size
+fn tile_size(file_type: u8) -> Option<u32> {
+    match file_type {
+        27 | 28 => Some(32),
+        _ => None,
+    }
+}
+
+/// Returns the position of the `index`th stored pixel of a `width` by `height`
+/// image whose pixels are stored in square tiles of `tile_size` pixels.
+///
+/// Tiles are stored left to right, top to bottom, and the pixels of each tile
+/// in row-major order. Tiles on the right and bottom edges are clamped to the
+/// image.
+fn tiled_position(index: u32, width: u32, height: u32, tile_size: u32) -> (u32, u32) {
+    // Every row of tiles but the last one is `tile_size` pixels high.
+    let tile_y = index / (tile_size * width);
+    let row_height = tile_size.min(height - tile_y * tile_size);
+    let index = index - tile_y * tile_size * width;
+
+    // Every tile in a row but the last one is `tile_size` pixels wide.
+    let tile_x = index / (row_height * tile_size);
+    let tile_width = tile_size.min(width - tile_x * tile_size);
+    let index = index - tile_x * row_height * tile_size;
+
+    (
+        tile_x * tile_size + index % tile_width,
+        tile_y * tile_size + index / tile_width,
+    )
+}
+
+/// Largest width or height of a sprite that is decoded, unless
+/// [`TexOptions::max_size`] says otherwise.
+///
+/// [`TexOptions::max_size`]: ./struct.TexOptions.html#structfield.max_size
+pub(crate) const DEFAULT_MAX_SIZE: u32 = 16384;
+
+/// Returns an error if `width` or `height` is larger than `max_size`.
+///
+/// Corrupt headers can claim sizes that don't fit in memory, so this is checked
+/// before anything is allocated.
+pub(crate) fn check_size(width: u32, height: u32, max_size: u32) -> Result<(), Error> {
+    if width > max_size || height > max_size {
+        return Err(Error::Other(format!(
+            "Image size ({}x{}) is larger than the maximum of {} pixels per side.",
+            width, height, max_size
+        )));
+    }
+
+    Ok(())
+}
+
+/// An image that decoded pixels are placed in as soon as they are read.
+///
+/// Pixels are placed by their index in the order they are stored, so they
+/// don't have to be buffered and reordered afterwards. The image is only
+/// allocated when the first pixel is placed, after the size of the data has
+/// been checked, and `RgbaImage::new` allocates all of its pixels at once, so
+/// the image never grows while it is decoded.
+struct Canvas {
+    width: u32,
+    height: u32,
+    /// Size of the square tiles the pixels are stored in, if any.
+    tile_size: Option<u32>,
+    image: Option<RgbaImage>,
+}
+
+impl Canvas {
+    /// Creates a transparent `width` by `height` canvas. See [`tile_size`].
+    ///
+    /// [`tile_size`]: ./fn.tile_size.html
+    fn new(width: u32, height: u32, tile_size: Option<u32>) -> Self {
+        // A tile larger than the image holds all of it, like a tile as large
+        // as the image does.
+        let tile_size = tile_size.map(|size| size.min(width.max(height)));
+
+        Self {
+            width,
+            height,
+            tile_size,
+            image: None,
+        }
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Number of pixels in the canvas.
+    fn len(&self) -> u32 {
+        self.width * self.height
+    }
+
+    fn image(&mut self) -> &mut RgbaImage {
+        let (width, height) = (self.width, self.height);
+        self.image
+            .get_or_insert_with(|| RgbaImage::new(width, height))
+    }
+
+    /// Places the `index`th pixel in the order the pixels are stored.
+    fn put(&mut self, index: u32, pixel: [u8; 4]) {
+        let (x, y) = match self.tile_size {
+            Some(tile_size) => tiled_position(index, self.width, self.height, tile_size),
+            None => (index % self.width, index / self.width),
+        };
+
+        self.image().put_pixel(x, y, Rgba(pixel));
+    }
+
+    /// Sets every pixel to `pixel`.
+    fn fill(&mut self, pixel: [u8; 4]) {
+        for p in self.image().pixels_mut() {
+            *p = Rgba(pixel);
+        }
+    }
+
+    fn into_image(self) -> RgbaImage {
+        let (width, height) = (self.width, self.height);
+        self.image.unwrap_or_else(|| RgbaImage::new(width, height))
+    }
+}
+
+/// A decoded sprite, along with its index in the file, the sub-type its
+/// pixels were stored in and whether some of them couldn't be decoded.
+struct Sprite {
+    index: usize,
+    partial: bool,
+    file_type: u8,
+    sub_type: u8,
+    chunk_size: u32,
+    image: RgbaImage,
+}
+
+impl Sprite {
+    /// Converts the sprite into the image that is saved.
+    ///
+    /// If `grayscale` is `true`, LA88 (`6`) sprites are converted to
+    /// grayscale-alpha images and L8 (`10`) sprites to grayscale images. Their
+    /// color channels are all equal, so no information is lost. Other sprites
+    /// stay RGBA.
+    fn into_output_image(self, grayscale: bool) -> DynamicImage {
+        let image = self.image;
+        match (grayscale, self.sub_type) {
+            (true, 6) => DynamicImage::ImageLumaA8(ImageBuffer::from_fn(
+                image.width(),
+                image.height(),
+                |x, y| {
+                    let pixel = image.get_pixel(x, y);
+                    LumaA([pixel[0], pixel[3]])
+                },
+            )),
+            (true, 10) => DynamicImage::ImageLuma8(ImageBuffer::from_fn(
+                image.width(),
+                image.height(),
+                |x, y| Luma([image.get_pixel(x, y)[0]]),
+            )),
+            _ => DynamicImage::ImageRgba8(image),
+        }
+    }
+
+    /// Converts the sprite into the images that are saved with
+    /// [`TexOptions::split_alpha`]: its color channels, and its alpha channel.
+    ///
+    /// The color image is grayscale if [`into_output_image`] would make a
+    /// grayscale image, otherwise RGB.
+    ///
+    /// [`TexOptions::split_alpha`]: ./struct.TexOptions.html#structfield.split_alpha
+    /// [`into_output_image`]: #method.into_output_image
+    fn into_split_images(self, grayscale: bool) -> (DynamicImage, DynamicImage) {
+        let (_, alpha) = split_alpha(&self.image);
+
+        let color = match self.into_output_image(grayscale) {
+            DynamicImage::ImageLumaA8(image) => DynamicImage::ImageLuma8(ImageBuffer::from_fn(
+                image.width(),
+                image.height(),
+                |x, y| Luma([image.get_pixel(x, y)[0]]),
+            )),
+            DynamicImage::ImageLuma8(image) => DynamicImage::ImageLuma8(image),
+            image => DynamicImage::ImageRgb8(image.to_rgb8()),
+        };
+
+        (color, DynamicImage::ImageLuma8(alpha))
+    }
+}
+
+/// Removes the alpha channel of an image, for [`TexOptions::no_alpha`].
+///
+/// Opaque images lose their alpha channel. Images with transparent pixels are
+/// blended onto `background`, or returned as `Err` if it is `None`.
+///
+/// [`TexOptions::no_alpha`]: ./struct.TexOptions.html#structfield.no_alpha
+fn remove_alpha(
+    image: DynamicImage,
+    background: Option<[u8; 3]>,
+) -> Result<DynamicImage, DynamicImage> {
+    let opaque = match &image {
+        DynamicImage::ImageRgba8(image) => image.pixels().all(|p| p[3] == 255),
+        DynamicImage::ImageLumaA8(image) => image.pixels().all(|p| p[1] == 255),
+        // The other images that are saved have no alpha channel.
+        _ => return Ok(image),
+    };
+
+    match (opaque, background) {
+        (true, _) => Ok(match image {
+            DynamicImage::ImageLumaA8(_) => DynamicImage::ImageLuma8(image.to_luma8()),
+            _ => DynamicImage::ImageRgb8(image.to_rgb8()),
+        }),
+        (false, Some(background)) => Ok(DynamicImage::ImageRgb8(format::blend(
+            &image.to_rgba8(),
+            background,
+        ))),
+        (false, None) => Err(image),
+    }
+}
+
+/// Splits an image into its color channels and an 8-bit grayscale image of its
+/// alpha channel.
+///
+/// The alpha channel is dropped from the color image, not blended. Sprites of
+/// pixel types without alpha, like RGB565 (`4`), have an alpha image that is
+/// entirely `255`. [`TexOptions::split_alpha`] saves sprites split like this.
+///
+/// [`TexOptions::split_alpha`]: ./struct.TexOptions.html#structfield.split_alpha
+pub fn split_alpha(image: &RgbaImage) -> (RgbImage, GrayImage) {
+    let (width, height) = image.dimensions();
+    let color = RgbImage::from_fn(width, height, |x, y| {
+        let [r, g, b, _] = image.get_pixel(x, y).0;
+        Rgb([r, g, b])
+    });
+    let alpha = GrayImage::from_fn(width, height, |x, y| Luma([image.get_pixel(x, y)[3]]));
+
+    (color, alpha)
+}
+
+/// A chunk of a type that isn't decoded, kept for [`TexOptions::dump_unknown`].
+///
+/// [`TexOptions::dump_unknown`]: ./struct.TexOptions.html#structfield.dump_unknown
+#[derive(Serialize)]
+struct UnknownChunk {
+    /// Offset of the chunk in the decompressed data.
+    offset: usize,
+    file_type: u8,
+    size: usize,
+    #[serde(skip)]
+    data: Vec<u8>,
+}
+
+/// Information about a sprite, saved next to its image by
+/// [`process_tex_with`] if [`TexOptions::metadata`] is `true`.
+///
+/// [`process_tex_with`]: ./fn.process_tex_with.html
+/// [`TexOptions::metadata`]: ./struct.TexOptions.html#structfield.metadata
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct TexMetadata {
+    /// Index of the sprite in the file.
+    pub index: usize,
+    /// File type of the sprite's chunk.
+    pub file_type: u8,
+    /// Sub-type (pixel type) of the sprite.
+    pub sub_type: u8,
+    /// Width of the sprite.
+    pub width: u32,
+    /// Height of the sprite.
+    pub height: u32,
+    /// Whether some pixels of the sprite couldn't be decoded and were filled
+    /// with magenta. See [`TexOptions::fill_invalid`].
+    ///
+    /// [`TexOptions::fill_invalid`]: ./struct.TexOptions.html#structfield.fill_invalid
+    pub partial: bool,
+    /// Where the sprite was in its original image, if it was cropped with
+    /// [`TexOptions::crop`]. `width` and `height` are those of the cropped
+    /// image then.
+    ///
+    /// [`TexOptions::crop`]: ./struct.TexOptions.html#structfield.crop
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crop: Option<SpriteCrop>,
+}
+
+/// Where a sprite cropped with [`TexOptions::crop`] was in its original image.
+///
+/// The original image can be restored by placing the cropped image at `x`,
+/// `y` of a transparent `original_width` by `original_height` image.
+///
+/// [`TexOptions::crop`]: ./struct.TexOptions.html#structfield.crop
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct SpriteCrop {
+    /// Horizontal offset of the cropped image in the original image.
+    pub x: u32,
+    /// Vertical offset of the cropped image in the original image.
+    pub y: u32,
+    /// Width of the original image.
+    pub original_width: u32,
+    /// Height of the original image.
+    pub original_height: u32,
+}
+
+/// Crops the fully transparent borders off `image`, for [`TexOptions::crop`].
+///
+/// A fully transparent image is cropped to its top-left pixel, so that no
+/// image is empty.
+///
+/// [`TexOptions::crop`]: ./struct.TexOptions.html#structfield.crop
+fn crop_transparent(image: RgbaImage) -> (RgbaImage, SpriteCrop) {
+    let (width, height) = image.dimensions();
+
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if pixel[3] != 0 {
+            bounds = Some(match bounds {
+                Some((left, top, right, bottom)) => {
+                    (left.min(x), top.min(y), right.max(x), bottom.max(y))
+                }
+                None => (x, y, x, y),
+            });
+        }
+    }
+    let (left, top, right, bottom) = bounds.unwrap_or((0, 0, 0, 0));
+
+    let crop = SpriteCrop {
+        x: left,
+        y: top,
+        original_width: width,
+        original_height: height,
+    };
+    let cropped =
+        image::imageops::crop_imm(&image, left, top, right - left + 1, bottom - top + 1).to_image();
+
+    (cropped, crop)
+}
+
+/// A sprite saved by [`process_tex_with`].
+///
+/// [`process_tex_with`]: ./fn.process_tex_with.html
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpriteInfo {
+    /// Details of the sprite, like its index and size.
+    pub metadata: TexMetadata,
+    /// Size of the sprite's chunk in the decompressed data, in bytes.
+    pub chunk_size: u32,
+    /// Path of the saved image.
+    pub path: PathBuf,
+    /// Path of the saved alpha image, if the sprite was saved with
+    /// [`TexOptions::split_alpha`].
+    ///
+    /// [`TexOptions::split_alpha`]: ./struct.TexOptions.html#structfield.split_alpha
+    pub alpha_path: Option<PathBuf>,
+    /// Path of the first image with the same pixels, if the sprite is a
+    /// duplicate found by [`TexOptions::dedupe`].
+    ///
+    /// [`TexOptions::dedupe`]: ./struct.TexOptions.html#structfield.dedupe
+    pub duplicate_of: Option<PathBuf>,
+    /// Path of the saved thumbnail, if the sprite was saved with
+    /// [`TexOptions::thumbnail_size`].
+    ///
+    /// [`TexOptions::thumbnail_size`]: ./struct.TexOptions.html#structfield.thumbnail_size
+    pub thumbnail_path: Option<PathBuf>,
+}
+
+/// What [`process_tex_with`] extracted from a file.
+///
+/// [`process_tex_with`]: ./fn.process_tex_with.html
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TexExtraction {
+    /// The saved sprites, in the order they are stored in the file.
+    ///
+    /// Sprites combined into an atlas with [`TexOptions::combine`] aren't
+    /// saved on their own, so they are only listed in [`atlas`].
+    ///
+    /// [`TexOptions::combine`]: ./struct.TexOptions.html#structfield.combine
+    /// [`atlas`]: #structfield.atlas
+    pub sprites: Vec<SpriteInfo>,
+    /// The atlas the sprites were combined into, with
+    /// [`TexOptions::combine`].
+    ///
+    /// [`TexOptions::combine`]: ./struct.TexOptions.html#structfield.combine
+    pub atlas: Option<TexAtlas>,
+}
+
+/// How [`process_tex_with`] names the images of the sprites in a
+/// file.
+///
+/// [`process_tex_with`]: ./fn.process_tex_with.html
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SpriteNaming {
+    /// `name.png`, `name_.png`, `name__.png` and so on. An `_` is appended
+    /// for every sprite before the image's sprite in the file.
+    #[default]
+    Underscores,
+    /// `name_0.png`, `name_1.png` and so on. The indices are zero-padded to
+    /// the same width, so the names sort in the order of the sprites. Files
+    /// with a single sprite are saved as `name.png`.
+    Numbered,
+}
+
+impl SpriteNaming {
+    /// Returns the name, without the extension, of the image of the `index`th
+    /// sprite in a file with `count` sprites. `name` is the name of the file
+    /// without `.sc`, e.g. `ui_tex`.
+    pub fn sprite_name(self, name: &str, index: usize, count: usize) -> String {
+        match self {
+            Self::Underscores => format!("{}{}", name, "_".repeat(index)),
+            Self::Numbered if count <= 1 => name.to_string(),
+            Self::Numbered => {
+                let width = (count - 1).to_string().len();
+                format!("{}_{:0width$}", name, index, width = width)
+            }
+        }
+    }
+}
+
+/// What [`process_tex_with`] does when the hash in the header of a
+/// file doesn't match its decompressed data, with [`TexOptions::hash_check`].
+///
+/// The hash is an MD5, SHA-1 or SHA-256 hash, told apart by its length. Hashes
+/// of other lengths can't be verified, so they are skipped with a warning.
+///
+/// [`process_tex_with`]: ./fn.process_tex_with.html
+/// [`TexOptions::hash_check`]: ./struct.TexOptions.html#structfield.hash_check
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum HashCheck {
+    /// The hash isn't checked.
+    #[default]
+    Ignore,
+    /// A mismatch is printed as a warning, and the file is still extracted.
+    Warn,
+    /// A mismatch fails the file with [`Error::HashMismatch`].
+    ///
+    /// [`Error::HashMismatch`]: ./error/enum.Error.html#variant.HashMismatch
+    Fail,
+}
+
+/// How [`process_tex_with`] resizes the images of the sprites before
+/// saving them, with [`TexOptions::resize`].
+///
+/// The aspect ratio is preserved, and no side is made smaller than a pixel.
+///
+/// [`process_tex_with`]: ./fn.process_tex_with.html
+/// [`TexOptions::resize`]: ./struct.TexOptions.html#structfield.resize
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resize {
+    /// Scales both sides by the factor, like `0.5` for half the size. It
+    /// must be positive.
+    Scale(f32),
+    /// Scales images whose width or height is larger than the given number
+    /// of pixels down to fit it. Smaller images are kept as they are. It must
+    /// not be zero.
+    MaxDimension(u32),
+}
+
+impl Resize {
+    /// Returns the size a `width` by `height` image is resized to.
+    pub fn dimensions(self, width: u32, height: u32) -> (u32, u32) {
+        let factor = match self {
+            Self::Scale(factor) => factor as f64,
+            Self::MaxDimension(max) if width.max(height) > max => {
+                max as f64 / width.max(height) as f64
+            }
+            Self::MaxDimension(_) => 1.0,
+        };
+        let scale = |side: u32| ((side as f64 * factor).round() as u32).max(1);
+
+        (scale(width), scale(height))
+    }
+
+    /// Returns an error if the scale isn't positive or the maximum dimension
+    /// is zero.
+    fn check(self) -> Result<(), Error> {
+        match self {
+            Self::Scale(factor) if !(factor > 0.0 && factor.is_finite()) => Err(Error::Other(
+                format!("Scale must be a positive number, not {}.", factor),
+            )),
+            Self::MaxDimension(0) => Err(Error::Other(
+                "Maximum dimension must not be zero.".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Options that control how [`process_tex_with`] saves images.
+///
+/// The default options save every sprite as an RGBA PNG image, like
+/// [`process_tex`] does. New options are added as fields here rather than as
+/// parameters of [`process_tex`], so only the options that differ from the
+/// defaults need to be set, with `..Default::default()` filling in the rest,
+/// and code that sets them keeps compiling as more are added.
+///
+/// The options can also be built from [`new`], with a method for each field:
+/// `TexOptions::new().format(OutputFormat::Webp).crop(true)`. Fields of
+/// `Option` types are set to `Some` of the given value.
+///
+/// [`process_tex_with`]: ./fn.process_tex_with.html
+/// [`process_tex`]: ./fn.process_tex.html
+/// [`new`]: #method.new
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TexOptions {
+    /// Format in which the images are saved.
+    pub format: OutputFormat,
+    /// Saves LA88 (sub-type `6`) sprites as grayscale-alpha images and L8
+    /// (sub-type `10`) sprites as grayscale images instead of RGBA images.
+    ///
+    /// The pixel values are preserved. Formats without grayscale support still
+    /// save RGBA images.
+    pub grayscale: bool,
+    /// Size of the square tiles the pixels of tiled sprites (file types `27`
+    /// and `28`) are stored in.
+    ///
+    /// The tile size isn't stored in the file. If this is `None`, the usual
+    /// size for the file type, 32, is used. It must not be zero.
+    pub block_size: Option<u32>,
+    /// Saves a [`TexMetadata`] JSON file next to each image, with the same name
+    /// as the image.
+    ///
+    /// [`TexMetadata`]: ./struct.TexMetadata.html
+    pub metadata: bool,
+    /// Index of the only sprite to decode and save.
+    ///
+    /// If this is `Some`, the other sprites are skipped without being decoded.
+    /// The sprite is saved with the same name as when all sprites are saved.
+    pub only_index: Option<usize>,
+    /// Ranges of indices of the sprites to decode and save.
+    ///
+    /// If this is `Some`, sprites whose indices aren't in any of the ranges
+    /// are skipped without being decoded, like with [`only_index`]. Sprites
+    /// are counted from 0, in the order they are stored in the file.
+    ///
+    /// [`only_index`]: #structfield.only_index
+    pub sprites: Option<Vec<RangeInclusive<usize>>>,
+    /// Saves the data of chunks with unknown file types, for analysis.
+    ///
+    /// Each chunk is saved as `<name>_<offset>_type<file type>.bin`, where the
+    /// offset is that of the chunk in the decompressed data. A
+    /// `<name>_unknown.json` file lists the offset, file type and size of
+    /// every chunk.
+    pub dump_unknown: bool,
+    /// Fills pixels that can't be decoded with magenta, instead of skipping
+    /// the sprite.
+    ///
+    /// Sprites with unknown sub-types are filled entirely. If a sprite's data
+    /// ends early, the pixels that are present are decoded. Such sprites are
+    /// reported as partially decoded, in [`TexMetadata::partial`].
+    ///
+    /// [`TexMetadata::partial`]: ./struct.TexMetadata.html#structfield.partial
+    pub fill_invalid: bool,
+    /// Largest width or height of a sprite that is decoded.
+    ///
+    /// Sprites with larger sizes in their headers are skipped without
+    /// allocating their images. If this is `None`, 16384 is used.
+    pub max_size: Option<u32>,
+    /// Largest size of the decompressed data of a file, in bytes.
+    ///
+    /// Decompression stops once the data is larger, so that corrupt files
+    /// can't use up the memory. If this is `None`, 512 MiB is used.
+    pub max_decompressed_size: Option<usize>,
+    /// The dictionary that zstd-compressed files were compressed with.
+    ///
+    /// Some newer files need a dictionary shipped with the game, and can't be
+    /// decompressed without it. It isn't used for other codecs.
+    pub zstd_dictionary: Option<Arc<Vec<u8>>>,
+    /// How the images are named.
+    pub naming: SpriteNaming,
+    /// How hard PNG images are compressed. It isn't used for other formats.
+    pub png_compression: PngCompression,
+    /// Saves the alpha channel of each sprite as a separate, grayscale image.
+    ///
+    /// The sprite is saved without its alpha channel, and its alpha channel is
+    /// saved next to it as `<name>_alpha`. See [`split_alpha`].
+    ///
+    /// [`split_alpha`]: ./fn.split_alpha.html
+    pub split_alpha: bool,
+    /// Saves sprites without an alpha channel, as RGB (or grayscale) images.
+    ///
+    /// Opaque sprites, like those of pixel type `4`, lose nothing. Sprites with
+    /// transparent pixels are blended onto [`alpha_background`], or keep their
+    /// alpha channel with a warning if it is `None`. It isn't used with
+    /// [`split_alpha`].
+    ///
+    /// [`alpha_background`]: #structfield.alpha_background
+    /// [`split_alpha`]: #structfield.split_alpha
+    pub no_alpha: bool,
+    /// Converts the pixels of sprites from premultiplied alpha to straight
+    /// alpha after they are decoded, for games that premultiply their
+    /// uncompressed textures.
+    ///
+    /// The color of each pixel is divided by its alpha, and fully
+    /// transparent pixels become black. BC3 sprites with premultiplied alpha
+    /// (sub-type `18`) are always converted, so they aren't converted again.
+    pub unmultiply_alpha: bool,
+    /// The RGB color transparent sprites are blended onto with [`no_alpha`].
+    ///
+    /// [`no_alpha`]: #structfield.no_alpha
+    pub alpha_background: Option<[u8; 3]>,
+    /// Crops the fully transparent borders off every sprite before saving it.
+    ///
+    /// The sprite's position in its original image is kept in
+    /// [`TexMetadata::crop`], which is saved with [`metadata`]. Fully
+    /// transparent sprites are saved as a single transparent pixel.
+    ///
+    /// [`TexMetadata::crop`]: ./struct.TexMetadata.html#structfield.crop
+    /// [`metadata`]: #structfield.metadata
+    pub crop: bool,
+    /// Skips sprites whose pixels are all fully transparent, with a note,
+    /// instead of saving them.
+    pub skip_transparent: bool,
+    /// Reports the sprites that would be saved without decoding or saving
+    /// anything.
+    ///
+    /// Only the headers of the sprites are read, and their pixels are skipped.
+    /// The returned [`SpriteInfo`]s hold the paths the images would be saved
+    /// at, and the sizes from the headers. Nothing is written, not even
+    /// [`metadata`] or [`dump_unknown`] files. [`crop`] and
+    /// [`skip_transparent`] need the pixels, so they aren't applied.
+    ///
+    /// [`SpriteInfo`]: ./struct.SpriteInfo.html
+    /// [`metadata`]: #structfield.metadata
+    /// [`dump_unknown`]: #structfield.dump_unknown
+    /// [`crop`]: #structfield.crop
+    /// [`skip_transparent`]: #structfield.skip_transparent
+    pub dry_run: bool,
+    /// Flips every sprite upside down, for engines whose texture coordinates
+    /// start at the bottom.
+    ///
+    /// The sprite is flipped after its pixels are placed, so tiled sprites
+    /// are flipped as a whole, not tile by tile. [`crop`] offsets are those in
+    /// the flipped image.
+    ///
+    /// [`crop`]: #structfield.crop
+    pub flip_vertical: bool,
+    /// Mirrors every sprite left to right, like [`flip_vertical`].
+    ///
+    /// [`flip_vertical`]: #structfield.flip_vertical
+    pub flip_horizontal: bool,
+    /// Resizes the images before saving them, with a triangle (bilinear)
+    /// filter.
+    ///
+    /// The metadata keeps the size of the sprite. If this is `None`, the
+    /// images are saved at their full size.
+    pub resize: Option<Resize>,
+    /// Also saves a PNG thumbnail of every sprite next to its image, as
+    /// `<name>.thumb.png`, scaled down to fit in the given width and height.
+    ///
+    /// Thumbnails are made from the decoded sprite, after it is flipped and
+    /// cropped, so the images aren't read again. Smaller sprites are saved at
+    /// their size. The size must not be zero.
+    pub thumbnail_size: Option<u32>,
+    /// Doesn't save where each sprite came from in its PNG images.
+    ///
+    /// By default, PNG images (and thumbnails) hold text chunks with the name
+    /// of the file, the index, pixel type and size of the sprite before it
+    /// was cropped or resized, and the version of `sc_extract`. They never
+    /// change between runs, but images without them are a little smaller.
+    pub no_png_text: bool,
+    /// Finds sprites whose pixels are identical to those of a sprite saved
+    /// before, in this file or any other file it is used for.
+    ///
+    /// What is done with the duplicates depends on the [`DedupeMode`] of the
+    /// [`Deduplicator`]. Skipped duplicates aren't returned, and no metadata
+    /// is saved for them. Others are returned with
+    /// [`SpriteInfo::duplicate_of`]. If a link can't be made, the duplicate is
+    /// saved as a copy.
+    ///
+    /// [`DedupeMode`]: ./enum.DedupeMode.html
+    /// [`Deduplicator`]: ./struct.Deduplicator.html
+    /// [`SpriteInfo::duplicate_of`]: ./struct.SpriteInfo.html#structfield.duplicate_of
+    pub dedupe: Option<Arc<Deduplicator>>,
+    /// Skips decoding the file if the images of all its sprites already exist
+    /// and were modified at or after this time, usually when the file itself
+    /// was modified.
+    ///
+    /// The expected images are found from the headers of the sprites, as with
+    /// [`dry_run`], and returned as they are. Alpha images and [`metadata`]
+    /// files must exist too, if they are enabled. With [`skip_transparent`],
+    /// or with a [`Deduplicator`] that skips duplicates, the images that
+    /// should exist aren't known without decoding, so the file is always
+    /// extracted.
+    ///
+    /// [`dry_run`]: #structfield.dry_run
+    /// [`metadata`]: #structfield.metadata
+    /// [`skip_transparent`]: #structfield.skip_transparent
+    /// [`Deduplicator`]: ./struct.Deduplicator.html
+    pub skip_existing: Option<SystemTime>,
+    /// Checks the hash in the header of the file against the hash of the
+    /// decompressed data, to catch truncated or badly repacked files before
+    /// their sprites are decoded.
+    ///
+    /// Both hashes are shown in hex if they differ. See [`HashCheck`] for the
+    /// hashes that can be verified.
+    ///
+    /// [`HashCheck`]: ./enum.HashCheck.html
+    pub hash_check: HashCheck,
+    /// Combines all sprites into a single atlas image, `<name>_atlas.png`,
+    /// instead of saving an image for each sprite.
+    ///
+    /// The sprites are packed into rows, and a JSON manifest,
+    /// `<name>_atlas.json`, holds the index of each sprite and its rectangle
+    /// in the atlas, see [`AtlasSprite`]. The sprites are flipped and
+    /// transparent ones skipped as usual, but options that change or add
+    /// files for single sprites, like [`crop`], [`resize`], [`split_alpha`],
+    /// [`metadata`], thumbnails and [`dedupe`], aren't used.
+    ///
+    /// [`AtlasSprite`]: ./struct.AtlasSprite.html
+    /// [`crop`]: #structfield.crop
+    /// [`resize`]: #structfield.resize
+    /// [`split_alpha`]: #structfield.split_alpha
+    /// [`metadata`]: #structfield.metadata
+    /// [`dedupe`]: #structfield.dedupe
+    pub combine: bool,
+    /// Treats the file as data that was already decompressed, without an
+    /// `SC` header, and decodes its sprites directly.
+    ///
+    /// Data without a known header or compression signature is already
+    /// treated this way if its chunks look valid, see
+    /// [`is_decompressed_tex`]. This forces it for data that doesn't.
+    ///
+    /// [`is_decompressed_tex`]: ./fn.is_decompressed_tex.html
+    pub assume_decompressed: bool,
+    /// Leaves out the progress messages of the file, because files are
+    /// processed in parallel, and decodes the pixels of large images on
+    /// several threads.
+    pub parallelize: bool,
+}
+
+impl TexOptions {
+    /// Returns the default options, to be changed with the methods named
+    /// after the fields.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets [`format`](#structfield.format).
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets [`grayscale`](#structfield.grayscale).
+    pub fn grayscale(mut self, grayscale: bool) -> Self {
+        self.grayscale = grayscale;
+        self
+    }
+
+    /// Sets [`block_size`](#structfield.block_size).
+    pub fn block_size(mut self, block_size: u32) -> Self {
+        self.block_size = Some(block_size);
+        self
+    }
+
+    /// Sets [`metadata`](#structfield.metadata).
+    pub fn metadata(mut self, metadata: bool) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Sets [`only_index`](#structfield.only_index).
+    pub fn only_index(mut self, only_index: usize) -> Self {
+        self.only_index = Some(only_index);
+        self
+    }
+
+    /// Sets [`sprites`](#structfield.sprites).
+    pub fn sprites(mut self, sprites: Vec<RangeInclusive<usize>>) -> Self {
+        self.sprites = Some(sprites);
+        self
+    }
+
+    /// Sets [`dump_unknown`](#structfield.dump_unknown).
+    pub fn dump_unknown(mut self, dump_unknown: bool) -> Self {
+        self.dump_unknown = dump_unknown;
+        self
+    }
+
+    /// Sets [`fill_invalid`](#structfield.fill_invalid).
+    pub fn fill_invalid(mut self, fill_invalid: bool) -> Self {
+        self.fill_invalid = fill_invalid;
+        self
+    }
+
+    /// Sets [`max_size`](#structfield.max_size).
+    pub fn max_size(mut self, max_size: u32) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Sets [`max_decompressed_size`](#structfield.max_decompressed_size).
+    pub fn max_decompressed_size(mut self, max_decompressed_size: usize) -> Self {
+        self.max_decompressed_size = Some(max_decompressed_size);
+        self
+    }
+
+    /// Sets [`zstd_dictionary`](#structfield.zstd_dictionary).
+    pub fn zstd_dictionary(mut self, zstd_dictionary: Arc<Vec<u8>>) -> Self {
+        self.zstd_dictionary = Some(zstd_dictionary);
+        self
+    }
+
+    /// Sets [`naming`](#structfield.naming).
+    pub fn naming(mut self, naming: SpriteNaming) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Sets [`png_compression`](#structfield.png_compression).
+    pub fn png_compression(mut self, png_compression: PngCompression) -> Self {
+        self.png_compression = png_compression;
+        self
+    }
+
+    /// Sets [`split_alpha`](#structfield.split_alpha).
+    pub fn split_alpha(mut self, split_alpha: bool) -> Self {
+        self.split_alpha = split_alpha;
+        self
+    }
+
+    /// Sets [`no_alpha`](#structfield.no_alpha).
+    pub fn no_alpha(mut self, no_alpha: bool) -> Self {
+        self.no_alpha = no_alpha;
+        self
+    }
+
+    /// Sets [`unmultiply_alpha`](#structfield.unmultiply_alpha).
+    pub fn unmultiply_alpha(mut self, unmultiply_alpha: bool) -> Self {
+        self.unmultiply_alpha = unmultiply_alpha;
+        self
+    }
+
+    /// Sets [`alpha_background`](#structfield.alpha_background).
+    pub fn alpha_background(mut self, alpha_background: [u8; 3]) -> Self {
+        self.alpha_background = Some(alpha_background);
+        self
+    }
+
+    /// Sets [`crop`](#structfield.crop).
+    pub fn crop(mut self, crop: bool) -> Self {
+        self.crop = crop;
+        self
+    }
+
+    /// Sets [`skip_transparent`](#structfield.skip_transparent).
+    pub fn skip_transparent(mut self, skip_transparent: bool) -> Self {
+        self.skip_transparent = skip_transparent;
+        self
+    }
+
+    /// Sets [`dry_run`](#structfield.dry_run).
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Sets [`flip_vertical`](#structfield.flip_vertical).
+    pub fn flip_vertical(mut self, flip_vertical: bool) -> Self {
+        self.flip_vertical = flip_vertical;
+        self
+    }
+
+    /// Sets [`flip_horizontal`](#structfield.flip_horizontal).
+    pub fn flip_horizontal(mut self, flip_horizontal: bool) -> Self {
+        self.flip_horizontal = flip_horizontal;
+        self
+    }
+
+    /// Sets [`resize`](#structfield.resize).
+    pub fn resize(mut self, resize: Resize) -> Self {
+        self.resize = Some(resize);
+        self
+    }
+
+    /// Sets [`thumbnail_size`](#structfield.thumbnail_size).
+    pub fn thumbnail_size(mut self, thumbnail_size: u32) -> Self {
+        self.thumbnail_size = Some(thumbnail_size);
+        self
+    }
+
+    /// Sets [`no_png_text`](#structfield.no_png_text).
+    pub fn no_png_text(mut self, no_png_text: bool) -> Self {
+        self.no_png_text = no_png_text;
+        self
+    }
+
+    /// Sets [`dedupe`](#structfield.dedupe).
+    pub fn dedupe(mut self, dedupe: Arc<Deduplicator>) -> Self {
+        self.dedupe = Some(dedupe);
+        self
+    }
+
+    /// Sets [`skip_existing`](#structfield.skip_existing).
+    pub fn skip_existing(mut self, skip_existing: SystemTime) -> Self {
+        self.skip_existing = Some(skip_existing);
+        self
+    }
+
+    /// Sets [`hash_check`](#structfield.hash_check).
+    pub fn hash_check(mut self, hash_check: HashCheck) -> Self {
+        self.hash_check = hash_check;
+        self
+    }
+
+    /// Sets [`combine`](#structfield.combine).
+    pub fn combine(mut self, combine: bool) -> Self {
+        self.combine = combine;
+        self
+    }
+
+    /// Sets [`assume_decompressed`](#structfield.assume_decompressed).
+    pub fn assume_decompressed(mut self, assume_decompressed: bool) -> Self {
+        self.assume_decompressed = assume_decompressed;
+        self
+    }
+
+    /// Sets [`parallelize`](#structfield.parallelize).
+    pub fn parallelize(mut self, parallelize: bool) -> Self {
+        self.parallelize = parallelize;
+        self
+    }
+}
+
+/// The latest version of the `SC` header that is known.
+const MAX_SC_VERSION: u32 = 6;
+
+/// The header of a compressed, raw `_tex.sc` (or `.sctx`) file, read by
+/// [`read_sc_header`].
+///
+/// [`read_sc_header`]: ./fn.read_sc_header.html
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScHeader {
+    /// Version of the file. Version `4` files have a second version field,
+    /// which isn't kept.
+    pub version: u32,
+    /// Hash of the decompressed data. It is an MD5, SHA-1 or SHA-256 hash if
+    /// it is 16, 20 or 32 bytes long.
+    pub hash: Vec<u8>,
+    /// Offset of the compressed data, right after the header.
+    pub data_offset: usize,
+    /// Codec of the compressed data. If the data is split into sections, it
+    /// is the codec of the first one.
+    pub codec: Codec,
+}
+
+/// Reads the header of compressed, raw `_tex.sc` (or `.sctx`) file data,
+/// without decompressing the rest.
+///
+/// The header starts with the `SC` magic, followed by the version of the file
+/// and the hash of its decompressed data. Versions `0` to `6` are known, and
+/// the codec of their data is found from its magic bytes. Version `4` files
+/// have a second version field before the hash.
+///
+/// ## Errors
+///
+/// If the data is too short, doesn't start with `SC` or ends before the hash
+/// length in its header says, [`Error::DecompressionError`] is returned.
+/// [`Error::UnsupportedVersion`] is returned for other versions, instead of
+/// guessing how their data is laid out.
+///
+/// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
+/// [`Error::UnsupportedVersion`]: ./error/enum.Error.html#variant.UnsupportedVersion
+pub fn read_sc_header(raw_data: &[u8]) -> Result<ScHeader, Error> {
+    if raw_data.len() < 35 {
+        return Err(Error::DecompressionError(
+            "Size of file is too small".to_string(),
+        ));
+    }
+
+    let mut reader = Reader::new(Cursor::new(raw_data));
+
+    if !raw_data.starts_with(b"SC") {
+        return Err(Error::DecompressionError(
+            "File doesn't start with the `SC` magic".to_string(),
+        ));
+    }
+    reader.try_skip(2)?;
+
+    let version = reader.try_read_uint32_be()?;
+    if version > MAX_SC_VERSION {
+        return Err(Error::UnsupportedVersion(format!(
+            "Unsupported `SC` header version ({}).",
+            version
+        )));
+    }
+    if version == 4 {
+        // Version 4 files have a second version field.
+        reader.try_skip(4)?;
+    }
+
+    // Corrupt headers can claim a hash longer than the file.
+    let hash_length = reader.try_read_uint32_be()? as usize;
+    if hash_length > reader.len() {
+        return Err(Error::DecompressionError(format!(
+            "Hash length in the header ({} bytes) is larger than the rest of the file ({} bytes).",
+            hash_length,
+            reader.len()
+        )));
+    }
+    let hash = reader.try_slice(hash_length)?.to_vec();
+
+    let data_offset = raw_data.len() - reader.len();
+    Ok(ScHeader {
+        version,
+        hash,
+        data_offset,
+        codec: utils::payload_codec(&raw_data[data_offset..]),
+    })
+}
+
+/// Strips the header of compressed, raw `_tex.sc` (or `.sctx`) file data and
+/// decompresses the rest.
+///
+/// The header is read with [`read_sc_header`]. The rest may be split into
+/// several independently compressed sections, each prefixed with its size,
+/// whose outputs are concatenated. The hash in the header isn't checked.
+///
+/// Chunked `.sc2` files have the same header, and their payload is split into
+/// chunks with 8-byte headers: the size of the compressed data and the size
+/// of the decompressed data, both `u32` little-endian. Each chunk may use a
+/// different codec, and must decompress to the size in its header.
+///
+/// ## Errors
+///
+/// The errors of [`read_sc_header`] are returned. If decompression is
+/// unsuccessful, [`Error::DecompressionError`] is returned.
+///
+/// [`read_sc_header`]: ./fn.read_sc_header.html
+/// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
+pub fn decompress_sc(raw_data: &[u8]) -> Result<Vec<u8>, Error> {
+    decompress_sc_checked(raw_data, HashCheck::Ignore, &Decompression::default())
+}
+
+/// Like [`decompress_sc`], but the hash in the header is checked against the
+/// decompressed data, see [`HashCheck`], and the data is decompressed as
+/// `settings` say.
+///
+/// ## Errors
+///
+/// If the hash is checked and doesn't match, [`Error::HashMismatch`] is
+/// returned.
+///
+/// [`decompress_sc`]: ./fn.decompress_sc.html
+/// [`HashCheck`]: ./enum.HashCheck.html
+/// [`Error::HashMismatch`]: ../error/enum.Error.html#variant.HashMismatch
+pub(crate) fn decompress_sc_checked(
+    raw_data: &[u8],
+    hash_check: HashCheck,
+    settings: &Decompression,
+) -> Result<Vec<u8>, Error> {
+    let header = read_sc_header(raw_data)?;
+    let hash = header.hash.as_slice();
+
+    let output = utils::decompress_sections(&raw_data[header.data_offset..], settings)?;
+
+    if hash_check != HashCheck::Ignore {
+        match hash_with_length(&output, hash.len()) {
+            Some(actual) if actual != hash => {
+                let message = format!(
+                    "Hash of the decompressed data ({}) doesn't match the hash in the header ({}).",
+                    hex::encode(actual),
+                    hex::encode(hash)
+                );
+                if hash_check == HashCheck::Fail {
+                    return Err(Error::HashMismatch(message));
+                }
+                log::warn!("{}", message);
+            }
+            Some(_) => {}
+            None => log::warn!(
+                "Hash in the header ({}) is {} byte(s) long, which isn't the length of an MD5, \
+                 SHA-1 or SHA-256 hash, so it isn't verified.",
+                hex::encode(hash),
+                hash.len()
+            ),
+        }
+    }
+
+    Ok(output)
+}
+
+/// Hashes `data` with the algorithm whose hashes are `length` bytes long: MD5
+/// for 16 bytes, SHA-1 for 20 and SHA-256 for 32.
+fn hash_with_length(data: &[u8], length: usize) -> Option<Vec<u8>> {
+    match length {
+        16 => Some(Md5::digest(data).to_vec()),
+        20 => Some(Sha1::digest(data).to_vec()),
+        32 => Some(Sha256::digest(data).to_vec()),
+        _ => None,
+    }
+}
+
+/// Reads an image of `pixel_type` pixels, as large as `canvas`, from the stream
+/// and places its pixels in `canvas`.
+///
+/// Block-compressed types are decoded with [`convert_blocks`], the rest with
+/// [`convert_pixels`], in parallel if `parallel` is `true`.
+///
+/// [`convert_blocks`]: ./fn.convert_blocks.html
+/// [`convert_pixels`]: ./fn.convert_pixels.html
+fn convert_image(
+    reader: &mut Reader<Cursor<&[u8]>>,
+    pixel_type: u8,
+    canvas: &mut Canvas,
+    parallel: bool,
+) -> Result<(), Error> {
+    if is_block_type(pixel_type) {
+        convert_blocks(reader, pixel_type, canvas)
+    } else {
+        convert_pixels(reader, pixel_type, canvas, parallel)
+    }
+}
+
+/// Color of the pixels that can't be decoded when [`TexOptions::fill_invalid`]
+/// is `true`.
+///
+/// [`TexOptions::fill_invalid`]: ./struct.TexOptions.html#structfield.fill_invalid
+const PLACEHOLDER: [u8; 4] = [255, 0, 255, 255];
+
+/// Like [`convert_image`], but pixels that can't be decoded are filled with
+/// [`PLACEHOLDER`] instead of returning an error.
+///
+/// All pixels are filled if the pixel type is unknown. If the stream ends
+/// early, the pixels (or blocks) that are present are decoded and the rest are
+/// filled. PVRTC images are decoded as a whole, so they are filled entirely.
+///
+/// Returns whether any pixels were filled.
+///
+/// [`convert_image`]: ./fn.convert_image.html
+/// [`PLACEHOLDER`]: ./constant.PLACEHOLDER.html
+fn convert_image_filled(
+    reader: &mut Reader<Cursor<&[u8]>>,
+    pixel_type: u8,
+    canvas: &mut Canvas,
+) -> bool {
+    let count = canvas.len();
+
+    if let Some(pixel_size) = bytes_per_pixel(pixel_type) {
+        let available = ((reader.len() / pixel_size) as u32).min(count);
+        for i in 0..count {
+            let pixel = if i < available {
+                convert_pixel(reader, pixel_type).unwrap_or(PLACEHOLDER)
+            } else {
+                PLACEHOLDER
+            };
+            canvas.put(i, pixel);
+        }
+
+        return available < count;
+    }
+
+    if pixel_type == 15 {
+        return match convert_blocks(reader, pixel_type, canvas) {
+            Ok(()) => false,
+            Err(_) => {
+                canvas.fill(PLACEHOLDER);
+                true
+            }
+        };
+    }
+
+    let (block_width, block_height, block_size, decode) = match block_format(reader, pixel_type) {
+        Ok(format) => format,
+        Err(_) => {
+            canvas.fill(PLACEHOLDER);
+            return true;
+        }
+    };
+
+    let total =
+        (canvas.width().div_ceil(block_width) * canvas.height().div_ceil(block_height)) as usize;
+    let available = (reader.len() / block_size).min(total);
+
+    let size = match checked_size(total, block_size) {
+        Ok(size) => size,
+        Err(_) => {
+            canvas.fill(PLACEHOLDER);
+            return true;
+        }
+    };
+
+    // Missing blocks are read as zeros and filled instead of being decoded.
+    let mut data = reader.try_read(available * block_size).unwrap_or_default();
+    data.resize(size, 0);
+    let decoded = Cell::new(0);
+    let decode_or_fill = |block: &[u8]| {
+        decoded.set(decoded.get() + 1);
+        if decoded.get() > available {
+            vec![PLACEHOLDER; (block_width * block_height) as usize]
+        } else {
+            decode(block)
+        }
+    };
+
+    if decode_blocks(
+        &mut Reader::new(Cursor::new(&data)),
+        (block_width, block_height, block_size),
+        &decode_or_fill,
+        canvas,
+    )
+    .is_err()
+    {
+        canvas.fill(PLACEHOLDER);
+        return true;
+    }
+
+    available < total
+}
 
-/// Reads some data from the stream and returns appropriate pixel data.
+/// Reads a `width` by `height` image of `pixel_type` pixels, stored in
+/// scanlines, from the stream.
 ///
-/// The bitwise transformations depend on the type of the pixel. One of the following
-/// types is valid: `0, 1, 2, 3, 4, 6, 10`.
+/// See [`convert_image`].
 ///
-/// If `pixel_type` is not one of the above, `UnknownPixel` is raised. Otherwise, an array
-/// of four `u8`s is returned, wrapped around by `Ok`.
+/// [`convert_image`]: ./fn.convert_image.html
+pub(crate) fn decode_image(
+    reader: &mut Reader<Cursor<&[u8]>>,
+    pixel_type: u8,
+    width: u32,
+    height: u32,
+    parallel: bool,
+) -> Result<RgbaImage, Error> {
+    let mut canvas = Canvas::new(width, height, None);
+    convert_image(reader, pixel_type, &mut canvas, parallel)?;
+
+    Ok(canvas.into_image())
+}
+
+/// File types of the chunks that hold sprites.
+const SPRITE_TYPES: [u8; 6] = [1, 24, 27, 28, 45, 47];
+
+/// Returns the number of sprites in decompressed `_tex.sc` data, without
+/// decoding them.
+fn count_sprites(output: &[u8]) -> usize {
+    let mut reader = Reader::new(Cursor::new(output));
+
+    let mut count = 0;
+    while let (Ok(file_type), Ok(file_size)) = (reader.try_read_byte(), reader.try_read_uint32()) {
+        if SPRITE_TYPES.contains(&file_type) {
+            count += 1;
+        }
+
+        if reader
+            .try_skip((file_size as usize).min(reader.len()))
+            .is_err()
+        {
+            break;
+        }
+    }
+
+    count
+}
+
+/// Returns `true` if `data` looks like decompressed `_tex.sc` data, such as
+/// files saved by other tools after stripping the header and decompressing
+/// them.
 ///
-/// ## Arguments
+/// The data must not start with an `SC` header or the signature of a known
+/// codec, see [`detect_codec`]. Its chunks, each a type byte and a
+/// little-endian `u32` size, must end exactly where the data ends, where an
+/// empty chunk of type `0` may mark the end, and at least one of them must
+/// hold a sprite.
 ///
-/// * `reader`: `Reader` representing the data stream.
-/// * `pixel_type`: The type of pixel. For `_tex.sc` data, it is the image sub-type.
-fn convert_pixel(reader: &mut Reader, pixel_type: u8) -> Result<[u8; 4], Error> {
-    match pixel_type {
-        // RGB8888
-        0 | 1 => {
-            let pixel = reader.read(4);
-            Ok([pixel[0], pixel[1], pixel[2], pixel[3]])
+/// [`detect_codec`]: ./fn.detect_codec.html
+pub fn is_decompressed_tex(data: &[u8]) -> bool {
+    if data.starts_with(b"SC") || utils::detect_codec(data) != Codec::Uncompressed {
+        return false;
+    }
+
+    let mut reader = Reader::new(Cursor::new(data));
+
+    let mut sprites = 0;
+    while !reader.is_empty() {
+        let (file_type, file_size) = match (reader.try_read_byte(), reader.try_read_uint32()) {
+            (Ok(file_type), Ok(file_size)) => (file_type, file_size as usize),
+            _ => return false,
+        };
+
+        if file_type == 0 && file_size == 0 {
+            return sprites > 0 && reader.is_empty();
         }
-        // RGB4444
-        2 => {
-            let pixel = reader.read_uint16();
-            Ok([
-                (((pixel >> 12) & 0xF) << 4) as u8,
-                (((pixel >> 8) & 0xF) << 4) as u8,
-                (((pixel >> 4) & 0xF) << 4) as u8,
-                ((pixel & 0xF) << 4) as u8,
-            ])
-        }
-        // RGBA5551
-        3 => {
-            let pixel = reader.read_uint16();
-            Ok([
-                (((pixel >> 11) & 0x1F) << 3) as u8,
-                (((pixel >> 6) & 0x1F) << 3) as u8,
-                (((pixel >> 1) & 0x1F) << 3) as u8,
-                ((pixel & 0xFF) << 7) as u8,
-            ])
+        if SPRITE_TYPES.contains(&file_type) {
+            // The sub type, width and height.
+            if file_size < 5 {
+                return false;
+            }
+            sprites += 1;
         }
-        // RGB565
-        4 => {
-            let pixel = reader.read_uint16();
-            Ok([
-                (((pixel >> 11) & 0x1F) << 3) as u8,
-                (((pixel >> 5) & 0x3F) << 2) as u8,
-                ((pixel & 0x1F) << 3) as u8,
-                // Alpha channel must always be 255 for type 4.
-                255,
-            ])
+        if file_size > reader.len() || reader.try_skip(file_size).is_err() {
+            return false;
         }
-        // LA88
-        6 => {
-            let pixel = reader.read_uint16();
-            Ok([
-                (pixel >> 8) as u8,
-                (pixel >> 8) as u8,
-                (pixel >> 8) as u8,
-                (pixel & 0xFF) as u8,
-            ])
+    }
+
+    sprites > 0
+}
+
+/// Returns `true` if the sprite with `index` is selected by
+/// `options.only_index` and `options.sprites`.
+fn is_selected(options: &TexOptions, index: usize) -> bool {
+    !(options.only_index.is_some_and(|i| i != index)
+        || options
+            .sprites
+            .as_ref()
+            .is_some_and(|ranges| !ranges.iter().any(|r| r.contains(&index))))
+}
+
+/// Returns the size in bytes of the pixels of a `width` by `height` sprite of
+/// `pixel_type`, or `None` if the type is unknown or block-compressed.
+fn pixel_data_size(pixel_type: u8, width: u32, height: u32) -> Option<Result<usize, Error>> {
+    bytes_per_pixel(pixel_type).map(|size| checked_size(width as usize * height as usize, size))
+}
+
+/// The header of a sprite's chunk, read by [`scan_sprites`].
+///
+/// [`scan_sprites`]: ./fn.scan_sprites.html
+struct SpriteHeader {
+    index: usize,
+    file_type: u8,
+    sub_type: u8,
+    width: u32,
+    height: u32,
+    chunk_size: u32,
+}
+
+/// Reads the headers of the sprites in decompressed `_tex.sc` file data, like
+/// [`decode_sprites`], but skips their pixels without decoding them or
+/// allocating their images.
+///
+/// The same sprites are skipped, with the same messages, as far as the
+/// headers tell. The size of the pixel data is checked for pixel types with a
+/// fixed size per pixel, so only errors in the pixels themselves, or in
+/// block-compressed data, go unnoticed.
+///
+/// Sprites that are skipped are reported only if `report` is `true`.
+///
+/// [`decode_sprites`]: ./fn.decode_sprites.html
+fn scan_sprites(
+    output: &[u8],
+    options: &TexOptions,
+    report: bool,
+) -> Result<Vec<SpriteHeader>, Error> {
+    if options.block_size == Some(0) {
+        return Err(Error::Other("Block size must not be zero.".to_string()));
+    }
+
+    let mut reader = Reader::new(Cursor::new(output));
+
+    let mut headers = Vec::new();
+
+    let mut index = 0;
+    while !reader.is_empty() {
+        let file_type = reader.try_read_byte()?;
+        let file_size = reader.try_read_uint32()?;
+        let sprite_end = reader.len().saturating_sub(file_size as usize);
+
+        if !SPRITE_TYPES.contains(&file_type) {
+            reader.try_skip((file_size as usize).min(reader.len()))?;
+            continue;
         }
-        10 => {
-            let pixel = reader.read_byte();
-            Ok([pixel; 4])
+
+        let sub_type = reader.try_read_byte()?;
+        let width = reader.try_read_uint16()? as u32;
+        let height = reader.try_read_uint16()? as u32;
+
+        // The pixels are skipped along with the rest of the chunk.
+        let data_size = reader.len().saturating_sub(sprite_end);
+        reader.try_skip(data_size)?;
+        let sprite_index = index;
+        index += 1;
+
+        if !is_selected(options, sprite_index) {
+            continue;
         }
-        _ => Err(Error::UnknownPixel(format!(
-            "Unknown pixel type ({}).",
-            pixel_type
-        ))),
+
+        let skip = |message: &str| {
+            if report {
+                log::error!("Skipping sprite {}: {}", sprite_index, message);
+            }
+        };
+
+        if width == 0 || height == 0 {
+            skip("Zero width or height.");
+            continue;
+        }
+
+        if let Err(e) = check_size(width, height, options.max_size.unwrap_or(DEFAULT_MAX_SIZE)) {
+            skip(e.inner());
+            continue;
+        }
+
+        // Pixels that are missing or of unknown types are filled instead.
+        if !is_ktx_type(file_type) && !options.fill_invalid {
+            match pixel_data_size(sub_type, width, height) {
+                Some(Ok(size)) if size > data_size => {
+                    skip(&format!(
+                        "Expected {} more byte(s) of pixels, but only {} are left.",
+                        size, data_size
+                    ));
+                    continue;
+                }
+                Some(Err(e)) => {
+                    skip(e.inner());
+                    continue;
+                }
+                None if !is_block_type(sub_type) => {
+                    skip(&format!("Unknown pixel type ({}).", sub_type));
+                    continue;
+                }
+                _ => (),
+            }
+        }
+
+        headers.push(SpriteHeader {
+            index: sprite_index,
+            file_type,
+            sub_type,
+            width,
+            height,
+            chunk_size: file_size,
+        });
     }
+
+    Ok(headers)
 }
 
-/// Adjusts some pixels.
-fn adjust_pixels(img: &mut RgbaImage, pixels: Vec<[u8; 4]>, height: u32, width: u32) {
-    let mut i = 0;
-    let block_size = 32;
-    let h_limit = (height as f64 / block_size as f64).ceil() as u32;
-    let w_limit = (width as f64 / block_size as f64).ceil() as u32;
-
-    for _h in 0..h_limit {
-        for _w in 0..w_limit {
-            let mut h = _h * block_size;
-            while h != (_h + 1) * block_size && h < height as u32 {
-                let mut w = _w * block_size;
-                while w != (_w + 1) * block_size && w < width as u32 {
-                    img.put_pixel(
-                        w,
-                        h,
-                        Rgba([pixels[i][0], pixels[i][1], pixels[i][2], pixels[i][3]]),
-                    );
-                    i += 1;
-                    w += 1;
+/// The data that [`decode_sprites`] decodes sprites from.
+///
+/// [`decode_sprites`]: ./fn.decode_sprites.html
+enum SpriteSource<'a> {
+    /// Decompressed `_tex.sc` file data.
+    Decompressed(&'a [u8]),
+    /// Compressed, raw `_tex.sc` file data, which is decoded as it is
+    /// decompressed as `settings` say. The hash in the header isn't checked.
+    Compressed {
+        raw_data: &'a [u8],
+        settings: Decompression<'a>,
+    },
+}
+
+/// Decodes `_tex.sc` file data into sprites and passes each sprite to
+/// `on_sprite` as soon as it is decoded, so only one sprite is held at a
+/// time.
+///
+/// Compressed data is decoded as it comes out of the decompressor, one chunk
+/// at a time, so the whole decompressed data is never held either. Sprites
+/// before the point where decompression fails are still passed to
+/// `on_sprite`.
+///
+/// Chunks with unknown file types are returned if `options.dump_unknown` is
+/// `true`. Only `options.block_size`, `options.only_index`, `options.sprites`,
+/// `options.dump_unknown`, `options.fill_invalid`, `options.max_size` and
+/// `options.unmultiply_alpha` are used here. See [`extract_tex`] for details.
+///
+/// [`extract_tex`]: ./fn.extract_tex.html
+fn decode_sprites(
+    source: &SpriteSource,
+    options: &TexOptions,
+    on_sprite: impl FnMut(Sprite) -> Result<(), Error>,
+) -> Result<Vec<UnknownChunk>, Error> {
+    if options.block_size == Some(0) {
+        return Err(Error::Other("Block size must not be zero.".to_string()));
+    }
+
+    let mut decoder = SpriteDecoder {
+        options,
+        on_sprite,
+        pending: Vec::new(),
+        offset: 0,
+        index: 0,
+        unknown_chunks: Vec::new(),
+        error: None,
+    };
+
+    let result = match source {
+        SpriteSource::Decompressed(output) => decoder.write_all(output).map_err(Error::from),
+        SpriteSource::Compressed { raw_data, settings } => {
+            read_sc_header(raw_data).and_then(|header| {
+                utils::decompress_sections_into(
+                    &raw_data[header.data_offset..],
+                    &mut decoder,
+                    settings,
+                )
+            })
+        }
+    };
+
+    // Errors of the sprites stop decompression, and come first.
+    if let Some(e) = decoder.error.take() {
+        return Err(e);
+    }
+    result?;
+
+    decoder.finish()
+}
+
+/// Splits decompressed `_tex.sc` file data into its chunks as it is written,
+/// and decodes the sprites in them, for [`decode_sprites`].
+///
+/// Each chunk is a type byte and a little-endian `u32` size, followed by as
+/// many bytes of data. Chunks that are written whole are decoded without
+/// copying them; the rest are gathered in `pending` first.
+///
+/// [`decode_sprites`]: ./fn.decode_sprites.html
+struct SpriteDecoder<'a, F> {
+    options: &'a TexOptions,
+    on_sprite: F,
+    /// The start of a chunk that hasn't been written whole yet, with its
+    /// header.
+    pending: Vec<u8>,
+    /// Offset of the next chunk in the decompressed data.
+    offset: usize,
+    /// Index of the next sprite.
+    index: usize,
+    unknown_chunks: Vec<UnknownChunk>,
+    /// The error that stopped decoding, if any. It is returned instead of the
+    /// IO error the writer fails with.
+    error: Option<Error>,
+}
+
+/// Size of the header of a chunk: its type and the size of its data.
+const CHUNK_HEADER_SIZE: usize = 5;
+
+/// Returns the size of a chunk, with its header, from the header at the start
+/// of `data`, or `None` if the header isn't whole yet.
+fn chunk_size(data: &[u8]) -> Option<usize> {
+    let size = data.get(1..CHUNK_HEADER_SIZE)?;
+    let size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize;
+
+    Some(size.saturating_add(CHUNK_HEADER_SIZE))
+}
+
+impl<F: FnMut(Sprite) -> Result<(), Error>> SpriteDecoder<'_, F> {
+    /// Decodes a chunk, with its header. The data of the last chunk may be
+    /// shorter than its header says.
+    fn decode_chunk(&mut self, chunk: &[u8]) -> Result<(), Error> {
+        let offset = self.offset;
+        self.offset += chunk.len();
+
+        let file_type = chunk[0];
+        let data = &chunk[CHUNK_HEADER_SIZE..];
+
+        if !SPRITE_TYPES.contains(&file_type) {
+            // The end of the file is marked by an empty chunk of type `0`.
+            if self.options.dump_unknown && file_type != 0 {
+                self.unknown_chunks.push(UnknownChunk {
+                    offset,
+                    file_type,
+                    size: data.len(),
+                    data: data.to_vec(),
+                });
+            }
+            return Ok(());
+        }
+
+        let options = self.options;
+        let file_size = u32::from_le_bytes([chunk[1], chunk[2], chunk[3], chunk[4]]);
+
+        // Pixels are decoded from the sprite's own data, so a sprite with
+        // less data than its size needs can't run into the next one.
+        let mut sprite_reader = Reader::new(Cursor::new(data));
+        let sub_type = sprite_reader.try_read_byte()?;
+        let width = sprite_reader.try_read_uint16()? as u32;
+        let height = sprite_reader.try_read_uint16()? as u32;
+
+        let sprite_index = self.index;
+        self.index += 1;
+
+        if !is_selected(options, sprite_index) {
+            return Ok(());
+        }
+
+        if width == 0 || height == 0 {
+            log::error!("Skipping sprite {}: Zero width or height.", sprite_index);
+            return Ok(());
+        }
+
+        if let Err(e) = check_size(width, height, options.max_size.unwrap_or(DEFAULT_MAX_SIZE)) {
+            log::error!("Skipping sprite {}: {}", sprite_index, e.inner());
+            return Ok(());
+        }
+
+        // Tiled pixels are placed in their tiles while they are decoded.
+        let tile_size = tile_size(file_type).map(|size| options.block_size.unwrap_or(size));
+        let mut canvas = Canvas::new(width, height, tile_size);
+
+        let mut partial = false;
+        let result = if is_ktx_type(file_type) {
+            convert_ktx(&mut sprite_reader, &mut canvas, options.parallelize)
+        } else if options.fill_invalid {
+            partial = convert_image_filled(&mut sprite_reader, sub_type, &mut canvas);
+            Ok(())
+        } else {
+            convert_image(
+                &mut sprite_reader,
+                sub_type,
+                &mut canvas,
+                options.parallelize,
+            )
+        };
+        if let Err(e) = result {
+            log::error!("Skipping sprite {}: {}", sprite_index, e.inner());
+            return Ok(());
+        }
+
+        if partial {
+            log::warn!(
+                "Sprite {} was partially decoded. The invalid pixels are magenta.",
+                sprite_index
+            );
+        }
+
+        // Extra data, like mipmaps or padding, was skipped along with the
+        // rest of the chunk.
+        if !partial && !sprite_reader.is_empty() {
+            log::warn!(
+                "Sprite {} has {} byte(s) of data after its pixels.",
+                sprite_index,
+                sprite_reader.len()
+            );
+        }
+
+        let mut image = canvas.into_image();
+        if options.unmultiply_alpha && sub_type != 18 {
+            image
+                .pixels_mut()
+                .for_each(|pixel| unpremultiply(&mut pixel.0));
+        }
+
+        (self.on_sprite)(Sprite {
+            index: sprite_index,
+            partial,
+            file_type,
+            sub_type,
+            chunk_size: file_size,
+            image,
+        })
+    }
+
+    /// Decodes the last chunk, if the data ended before it was written whole,
+    /// and returns the chunks with unknown file types.
+    ///
+    /// [`Error::UnexpectedEof`] is returned if the data ended in the middle
+    /// of the header of a chunk.
+    ///
+    /// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+    fn finish(mut self) -> Result<Vec<UnknownChunk>, Error> {
+        if !self.pending.is_empty() {
+            if self.pending.len() < CHUNK_HEADER_SIZE {
+                return Err(Error::UnexpectedEof(format!(
+                    "Expected {} more byte(s), but only {} are left.",
+                    CHUNK_HEADER_SIZE,
+                    self.pending.len()
+                )));
+            }
+
+            let pending = std::mem::take(&mut self.pending);
+            self.decode_chunk(&pending)?;
+        }
+
+        Ok(self.unknown_chunks)
+    }
+}
+
+impl<F: FnMut(Sprite) -> Result<(), Error>> Write for SpriteDecoder<'_, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.error.is_some() {
+            return Err(io::Error::other("Decoding the sprites failed."));
+        }
+
+        let mut rest = buf;
+        while !rest.is_empty() {
+            let result = if self.pending.is_empty() {
+                match chunk_size(rest) {
+                    Some(size) if size <= rest.len() => {
+                        let (chunk, next) = rest.split_at(size);
+                        rest = next;
+                        self.decode_chunk(chunk)
+                    }
+                    _ => {
+                        self.pending.extend_from_slice(rest);
+                        rest = &[];
+                        Ok(())
+                    }
+                }
+            } else {
+                // The header is completed first, then the rest of the chunk.
+                let needed = chunk_size(&self.pending).unwrap_or(CHUNK_HEADER_SIZE);
+                let (part, next) = rest.split_at((needed - self.pending.len()).min(rest.len()));
+                rest = next;
+                self.pending.extend_from_slice(part);
+
+                match chunk_size(&self.pending) {
+                    Some(size) if size == self.pending.len() => {
+                        let pending = std::mem::take(&mut self.pending);
+                        self.decode_chunk(&pending)
+                    }
+                    _ => Ok(()),
                 }
-                h += 1;
+            };
+
+            if let Err(e) = result {
+                self.error = Some(e);
+                return Err(io::Error::other("Decoding the sprites failed."));
             }
         }
+
+        Ok(buf.len())
     }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Decodes compressed, raw `_tex.sc` file data into images.
+///
+/// This does the same decompression and pixel conversion as [`process_tex`],
+/// but the decoded sprites are returned instead of being saved as png images.
+/// The images are in the same order as they are stored in the file.
+///
+/// ## Errors
+///
+/// If decompression is unsuccessful, [`Error::DecompressionError`] is returned.
+/// If the data ends in the middle of a sprite's header, [`Error::UnexpectedEof`]
+/// is returned. Pixel conversion errors are handled in the function itself.
+///
+/// [`process_tex`]: ./fn.process_tex.html
+/// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
+/// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+pub fn extract_tex(raw_data: &[u8]) -> Result<Vec<RgbaImage>, Error> {
+    let source = SpriteSource::Compressed {
+        raw_data,
+        settings: Decompression::default(),
+    };
+
+    let mut images = Vec::new();
+    decode_sprites(&source, &TexOptions::default(), |sprite| {
+        images.push(sprite.image);
+        Ok(())
+    })?;
+
+    Ok(images)
+}
+
+/// Decodes compressed, raw `_tex.sc` file data into PNG images, without
+/// touching the filesystem.
+///
+/// This is the same as [`extract_tex`], except that the images are returned
+/// encoded as PNG files, in the same order, with the default compression and
+/// without text chunks. It suits callers that have no filesystem, like a web
+/// page that shows the sprites of a file.
+///
+/// ## Errors
+///
+/// The errors are the same as those of [`extract_tex`]. [`Error::IoError`] is
+/// returned if an image can't be encoded.
+///
+/// [`extract_tex`]: ./fn.extract_tex.html
+/// [`Error::IoError`]: ./error/enum.Error.html#variant.IoError
+pub fn extract_tex_to_pngs(raw_data: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+    let source = SpriteSource::Compressed {
+        raw_data,
+        settings: Decompression::default(),
+    };
+
+    let mut pngs = Vec::new();
+    decode_sprites(&source, &TexOptions::default(), |sprite| {
+        let mut png = Vec::new();
+        format::write_png(
+            &DynamicImage::ImageRgba8(sprite.image),
+            &mut png,
+            PngCompression::default(),
+            &[],
+        )?;
+        pngs.push(png);
+        Ok(())
+    })?;
+
+    Ok(pngs)
 }
 
 /// Processes compressed, raw `_tex.sc` file data.
@@ -112,107 +2247,466 @@ fn adjust_pixels(img: &mut RgbaImage, pixels: Vec<[u8; 4]>, height: u32, width:
 ///
 /// A single `_tex.sc` file can contain data for multiple sprites. All of the
 /// sprites are extracted and saved by this process. `_`s are appended to the
-/// file name in cases of multiple sprites.
+/// file name in cases of multiple sprites, one for each sprite before it in the
+/// file. [`TexOptions::naming`] can number them instead.
 ///
 /// `parallelize` tells if the directory files are processed parallelly. It is
 /// simply used to control the stdout output.
 ///
+/// Use [`extract_tex`] to get the decoded images without saving them, or
+/// [`process_tex_with`] to save them differently.
+///
+/// The details and paths of the saved sprites are returned in a
+/// [`TexExtraction`].
+///
 /// ## Errors
 ///
 /// If decompression is unsuccessful, [`Error::DecompressionError`] is returned.
-/// Pixel conversion errors are handled in the function itself.
+/// If the data ends in the middle of a sprite's header, [`Error::UnexpectedEof`]
+/// is returned. Pixel conversion errors are handled in the function itself.
 ///
 /// [`Error::IoError`] is returned if an IO operation fails.
 ///
+/// [`TexOptions::naming`]: ./struct.TexOptions.html#structfield.naming
+/// [`TexExtraction`]: ./struct.TexExtraction.html
+/// [`extract_tex`]: ./fn.extract_tex.html
+/// [`process_tex_with`]: ./fn.process_tex_with.html
 /// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
+/// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
 /// [`Error::IoError`]: ./error/enum.Error.html#variant.IoError
+#[cfg(feature = "fs")]
 pub fn process_tex(
     raw_data: &[u8],
     file_name: &str,
     out_dir: &Path,
     parallelize: bool,
-) -> Result<(), Error> {
-    if raw_data.len() < 35 {
-        return Err(Error::DecompressionError(
-            "Size of file is too small".to_string(),
-        ));
+) -> Result<TexExtraction, Error> {
+    process_tex_with(
+        raw_data,
+        file_name,
+        out_dir,
+        &TexOptions::new().parallelize(parallelize),
+    )
+}
+
+/// Processes compressed, raw `_tex.sc` file data and saves the images as
+/// described by `options`.
+///
+/// This is the same as [`process_tex`], except that the images are saved in
+/// `options.format`, with its extension instead of `.png`. See [`TexOptions`]
+/// for the other options. The options are usually built with
+/// [`TexOptions::new`]:
+/// `process_tex_with(data, name, out_dir, &TexOptions::new().crop(true))`.
+///
+/// The sprites are decoded as the data is decompressed, a chunk at a time, so
+/// the whole decompressed data isn't held in memory. Options that need all of
+/// it first, to check its hash, number the sprites, or read their headers for
+/// a dry run or to skip up-to-date files, decompress it whole instead.
+///
+/// ## Errors
+///
+/// The errors are the same as those of [`process_tex`]. If decompression fails
+/// partway, the sprites before that point have already been saved.
+///
+/// [`process_tex`]: ./fn.process_tex.html
+/// [`TexOptions`]: ./struct.TexOptions.html
+/// [`TexOptions::new`]: ./struct.TexOptions.html#method.new
+#[cfg(feature = "fs")]
+pub fn process_tex_with(
+    raw_data: &[u8],
+    file_name: &str,
+    out_dir: &Path,
+    options: &TexOptions,
+) -> Result<TexExtraction, Error> {
+    if !options.parallelize {
+        log::info!("Extracting {} image(s)...", file_name);
+    }
+
+    if let Some(resize) = options.resize {
+        resize.check()?;
+    }
+    if options.thumbnail_size == Some(0) {
+        return Err(Error::Other("Thumbnail size must not be zero.".to_string()));
     }
 
-    let version = (&raw_data[2..6])
-        .read_u32::<BigEndian>()
-        .unwrap_or_default();
+    let settings = Decompression {
+        limit: options
+            .max_decompressed_size
+            .unwrap_or(utils::DEFAULT_MAX_DECOMPRESSED_SIZE),
+        zstd_dictionary: options.zstd_dictionary.as_deref().map(Vec::as_slice),
+    };
+    // Skipped duplicates and transparent sprites aren't known from the
+    // headers, and atlases are always made again.
+    let can_skip_existing = options.skip_existing.is_some()
+        && !options.combine
+        && !options.skip_transparent
+        && !options
+            .dedupe
+            .as_ref()
+            .is_some_and(|dedupe| dedupe.mode() == DedupeMode::Skip);
+
+    // The data is decoded as it is decompressed, unless all of it is needed
+    // first: to check its hash, to count the sprites for their names, or to
+    // read the headers of the sprites.
+    let output = if options.assume_decompressed || is_decompressed_tex(raw_data) {
+        Some(Cow::Borrowed(raw_data))
+    } else if options.hash_check != HashCheck::Ignore
+        || options.naming != SpriteNaming::Underscores
+        || options.dry_run
+        || can_skip_existing
+    {
+        let output = decompress_sc_checked(raw_data, options.hash_check, &settings)
+            .map_err(|e| e.in_file(file_name))?;
+        Some(Cow::Owned(output))
+    } else {
+        None
+    };
+    let source = match &output {
+        Some(output) => SpriteSource::Decompressed(output),
+        None => SpriteSource::Compressed { raw_data, settings },
+    };
 
-    let (start, end) = if version == 4 { (10, 14) } else { (6, 10) };
+    let format = options.format;
+    // Only the extension is stripped, `.sc` may appear elsewhere in the name.
+    let name = file_name
+        .strip_suffix(".sc")
+        .or_else(|| file_name.strip_suffix(".sc2"))
+        .unwrap_or(file_name);
+    let initial_path = out_dir.join(name);
+    // Underscored names don't depend on the number of sprites.
+    let sprite_count = output.as_deref().map_or(0, count_sprites);
 
-    let hash_length = (&raw_data[start..end])
-        .read_u32::<BigEndian>()
-        .unwrap_or(16) as usize;
+    let mut extraction = TexExtraction::default();
 
-    let mut output = Vec::new();
-    match version {
-        0 | 1 | 3 | 4 => utils::decompress(&raw_data[end + hash_length..], &mut output)?,
-        _ => output = raw_data.to_vec(),
+    let alpha_path = |sprite_name: &str| {
+        options
+            .split_alpha
+            .then(|| out_dir.join(format!("{}_alpha.{}", sprite_name, format.extension())))
+    };
+    let thumbnail_path = |sprite_name: &str| {
+        options
+            .thumbnail_size
+            .map(|_| out_dir.join(format!("{}.thumb.png", sprite_name)))
     };
 
-    let mut reader = Reader::new(Cursor::new(&output));
+    if let Some(output) = output
+        .as_deref()
+        .filter(|_| options.dry_run || can_skip_existing)
+    {
+        for header in scan_sprites(output, options, options.dry_run)? {
+            let sprite_name = options.naming.sprite_name(name, header.index, sprite_count);
 
-    let mut pic_count = 0;
-    let possible_types = [1, 24, 27, 28];
+            extraction.sprites.push(SpriteInfo {
+                metadata: TexMetadata {
+                    index: header.index,
+                    file_type: header.file_type,
+                    sub_type: header.sub_type,
+                    width: header.width,
+                    height: header.height,
+                    partial: false,
+                    crop: None,
+                },
+                chunk_size: header.chunk_size,
+                path: out_dir.join(format!("{}.{}", sprite_name, format.extension())),
+                alpha_path: alpha_path(&sprite_name),
+                duplicate_of: None,
+                thumbnail_path: thumbnail_path(&sprite_name),
+            });
+        }
 
-    if !parallelize {
-        println!("\nExtracting {} image(s)...", file_name);
-    }
+        if options.dry_run {
+            return Ok(extraction);
+        }
 
-    'main: while reader.len() > 0 {
-        let file_type = reader.read_byte();
-        let file_size = reader.read_uint32();
+        let is_newer = |path: &Path| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| options.skip_existing.is_some_and(|time| modified >= time))
+        };
+        let is_complete = extraction.sprites.iter().all(|sprite| {
+            is_newer(&sprite.path)
+                && sprite.alpha_path.as_deref().is_none_or(is_newer)
+                && sprite.thumbnail_path.as_deref().is_none_or(is_newer)
+                && (!options.metadata || is_newer(&sprite.path.with_extension("json")))
+        });
 
-        if !possible_types.contains(&file_type) {
-            reader.read(file_size as usize);
-            continue;
+        if !extraction.sprites.is_empty() && is_complete {
+            log::info!("Skipping {}: its images are up to date.", file_name);
+            return Ok(extraction);
         }
 
-        let sub_type = reader.read_byte();
-        let width = reader.read_uint16() as u32;
-        let height = reader.read_uint16() as u32;
-
-        println!(
-            "file_type: {}, file_size: {}, sub_type: {}, width: {}, height: {}",
-            file_type.to_string().cyan().bold(),
-            file_size.to_string().cyan().bold(),
-            sub_type.to_string().cyan().bold(),
-            width.to_string().cyan().bold(),
-            height.to_string().cyan().bold()
-        );
-
-        let mut pixels = Vec::new();
-        let mut img = RgbaImage::new(width, height);
-        for y in 0..height {
-            for x in 0..width {
-                let pixel_data = match convert_pixel(&mut reader, sub_type) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        println!("Error: {}", e.inner().red());
-                        continue 'main;
-                    }
-                };
-                pixels.push(pixel_data);
+        extraction.sprites.clear();
+    }
+
+    // Every sprite is saved as soon as it is decoded, so large files don't
+    // need to be held in memory all at once.
+    let save = |img: &DynamicImage, path: &Path, text: &[(&str, String)]| {
+        let resized = options.resize.and_then(|resize| {
+            let (width, height) = img.dimensions();
+            let size = resize.dimensions(width, height);
+            (size != (width, height))
+                .then(|| img.resize_exact(size.0, size.1, FilterType::Triangle))
+        });
+
+        format
+            .save_with_text(
+                resized.as_ref().unwrap_or(img),
+                path,
+                options.png_compression,
+                text,
+            )
+            .map_err(|_| Error::IoError("Failed to save image!".red().to_string()))
+    };
 
-                img.put_pixel(x, y, Rgba(pixel_data));
+    if options.combine {
+        let mut images = Vec::new();
+        let unknown_chunks = decode_sprites(&source, options, |mut sprite| {
+            if options.flip_vertical {
+                imageops::flip_vertical_in_place(&mut sprite.image);
             }
+            if options.flip_horizontal {
+                imageops::flip_horizontal_in_place(&mut sprite.image);
+            }
+
+            if options.skip_transparent && sprite.image.pixels().all(|p| p[3] == 0) {
+                log::warn!("Skipping sprite {}: it is fully transparent.", sprite.index);
+            } else {
+                images.push((sprite.index, sprite.image));
+            }
+            Ok(())
+        })
+        .map_err(|e| e.in_file(file_name))?;
+
+        if !images.is_empty() {
+            let (atlas, sprites) = atlas::build_atlas(&images);
+            drop(images);
+
+            let path = out_dir.join(format!("{}_atlas.{}", name, format.extension()));
+            let text = if options.no_png_text {
+                Vec::new()
+            } else {
+                vec![
+                    ("Source file", file_name.to_string()),
+                    (
+                        "Software",
+                        format!("sc_extract {}", env!("CARGO_PKG_VERSION")),
+                    ),
+                ]
+            };
+            format
+                .save_with_text(
+                    &DynamicImage::ImageRgba8(atlas),
+                    &path,
+                    options.png_compression,
+                    &text,
+                )
+                .map_err(|_| Error::IoError("Failed to save image!".red().to_string()))?;
+
+            let manifest_path = out_dir.join(format!("{}_atlas.json", name));
+            let json =
+                serde_json::to_string_pretty(&sprites).map_err(|e| Error::Other(e.to_string()))?;
+            fs::write(&manifest_path, json)?;
+
+            extraction.atlas = Some(TexAtlas {
+                path,
+                manifest_path,
+                sprites,
+            });
+        }
+
+        save_unknown_chunks(options, &initial_path, &unknown_chunks)?;
+        return Ok(extraction);
+    }
+
+    let unknown_chunks = decode_sprites(&source, options, |mut sprite| {
+        let sprite_name = options.naming.sprite_name(name, sprite.index, sprite_count);
+        let path = out_dir.join(format!("{}.{}", sprite_name, format.extension()));
+
+        if options.flip_vertical {
+            imageops::flip_vertical_in_place(&mut sprite.image);
+        }
+        if options.flip_horizontal {
+            imageops::flip_horizontal_in_place(&mut sprite.image);
+        }
+
+        if options.skip_transparent && sprite.image.pixels().all(|p| p[3] == 0) {
+            log::warn!("Skipping sprite {}: it is fully transparent.", sprite.index);
+            return Ok(());
+        }
+
+        let text = if options.no_png_text {
+            Vec::new()
+        } else {
+            let (width, height) = sprite.image.dimensions();
+            vec![
+                ("Source file", file_name.to_string()),
+                ("Sprite index", sprite.index.to_string()),
+                ("Pixel type", sprite.sub_type.to_string()),
+                ("Original size", format!("{}x{}", width, height)),
+                (
+                    "Software",
+                    format!("sc_extract {}", env!("CARGO_PKG_VERSION")),
+                ),
+            ]
+        };
+
+        let mut crop = None;
+        if options.crop {
+            let (image, sprite_crop) = crop_transparent(sprite.image);
+            sprite.image = image;
+            crop = Some(sprite_crop);
+        }
+
+        let alpha_path = alpha_path(&sprite_name);
+        let original = options
+            .dedupe
+            .as_ref()
+            .and_then(|dedupe| dedupe.check(&sprite.image, &path, alpha_path.as_deref()));
+        let mode = options.dedupe.as_ref().map(|dedupe| dedupe.mode());
+        if let (Some(original), Some(DedupeMode::Skip)) = (&original, mode) {
+            log::warn!(
+                "Skipping sprite {}: it is a duplicate of {}.",
+                sprite.index,
+                original.path.display()
+            );
+            return Ok(());
         }
 
-        if file_type == 27 || file_type == 28 {
-            adjust_pixels(&mut img, pixels, height, width);
+        let metadata = TexMetadata {
+            index: sprite.index,
+            file_type: sprite.file_type,
+            sub_type: sprite.sub_type,
+            width: sprite.image.width(),
+            height: sprite.image.height(),
+            partial: sprite.partial,
+            crop,
+        };
+        if options.metadata {
+            let json =
+                serde_json::to_string_pretty(&metadata).map_err(|e| Error::Other(e.to_string()))?;
+            fs::write(path.with_extension("json"), json)?;
         }
 
-        let initial_path = out_dir.join(file_name.replace(".sc", ""));
-        let path = format!("{}{}.png", initial_path.display(), "_".repeat(pic_count));
-        if img.save(path).is_err() {
-            return Err(Error::IoError("Failed to save image!".red().to_string()));
+        let thumbnail_path = thumbnail_path(&sprite_name);
+        if let (Some(size), Some(thumbnail_path)) = (options.thumbnail_size, &thumbnail_path) {
+            let (width, height) = sprite.image.dimensions();
+            let (thumbnail_width, thumbnail_height) =
+                Resize::MaxDimension(size).dimensions(width, height);
+            let thumbnail = if (thumbnail_width, thumbnail_height) == (width, height) {
+                sprite.image.clone()
+            } else {
+                imageops::thumbnail(&sprite.image, thumbnail_width, thumbnail_height)
+            };
+
+            OutputFormat::Png
+                .save_with_text(
+                    &DynamicImage::ImageRgba8(thumbnail),
+                    thumbnail_path,
+                    options.png_compression,
+                    &text,
+                )
+                .map_err(|_| Error::IoError("Failed to save thumbnail!".red().to_string()))?;
+        }
+
+        // Links are made instead of saving the images, if they can be.
+        let linked = match (&original, &options.dedupe) {
+            (Some(original), Some(dedupe)) => {
+                dedupe.link(&original.path, &path)
+                    && match (&original.alpha_path, &alpha_path) {
+                        (Some(original), Some(alpha_path)) => dedupe.link(original, alpha_path),
+                        _ => true,
+                    }
+            }
+            _ => false,
+        };
+
+        let chunk_size = sprite.chunk_size;
+        let alpha_path = if linked {
+            alpha_path
+        } else if let Some(alpha_path) = alpha_path {
+            let (color, alpha) = sprite.into_split_images(options.grayscale);
+
+            save(&color, &path, &text)?;
+            save(&alpha, &alpha_path, &text)?;
+            Some(alpha_path)
+        } else {
+            let index = sprite.index;
+            let mut img = sprite.into_output_image(options.grayscale);
+            if options.no_alpha {
+                img = remove_alpha(img, options.alpha_background).unwrap_or_else(|img| {
+                    log::warn!(
+                        "Sprite {} has transparent pixels, so its alpha channel is kept.",
+                        index
+                    );
+                    img
+                });
+            }
+
+            save(&img, &path, &text)?;
+            None
+        };
+
+        extraction.sprites.push(SpriteInfo {
+            metadata,
+            chunk_size,
+            path,
+            alpha_path,
+            duplicate_of: original.map(|original| original.path),
+            thumbnail_path,
+        });
+        Ok(())
+    })
+    .map_err(|e| e.in_file(file_name))?;
+
+    save_unknown_chunks(options, &initial_path, &unknown_chunks)?;
+
+    Ok(extraction)
+}
+
+/// Processes compressed, raw `_tex.sc` file data and saves the images as
+/// described by `options`, with `parallelize` in place of
+/// [`TexOptions::parallelize`].
+///
+/// [`TexOptions::parallelize`]: ./struct.TexOptions.html#structfield.parallelize
+#[cfg(feature = "fs")]
+#[deprecated(note = "use `process_tex_with`, which reads `TexOptions::parallelize`")]
+pub fn process_tex_with_options(
+    raw_data: &[u8],
+    file_name: &str,
+    out_dir: &Path,
+    parallelize: bool,
+    options: &TexOptions,
+) -> Result<TexExtraction, Error> {
+    let options = TexOptions {
+        parallelize,
+        ..options.clone()
+    };
+    process_tex_with(raw_data, file_name, out_dir, &options)
+}
+
+/// Saves the chunks with unknown file types next to the images, with
+/// `options.dump_unknown`. `initial_path` is the output directory joined with
+/// the name of the file.
+#[cfg(feature = "fs")]
+fn save_unknown_chunks(
+    options: &TexOptions,
+    initial_path: &Path,
+    unknown_chunks: &[UnknownChunk],
+) -> Result<(), Error> {
+    if options.dump_unknown && !unknown_chunks.is_empty() {
+        for chunk in unknown_chunks {
+            let path = format!(
+                "{}_{}_type{}.bin",
+                initial_path.display(),
+                chunk.offset,
+                chunk.file_type
+            );
+            fs::write(path, &chunk.data)?;
         }
 
-        pic_count += 1;
+        let json = serde_json::to_string_pretty(unknown_chunks)
+            .map_err(|e| Error::Other(e.to_string()))?;
+        fs::write(format!("{}_unknown.json", initial_path.display()), json)?;
     }
 
     Ok(())
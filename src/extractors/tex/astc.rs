@@ -0,0 +1,778 @@
+//! Decoding of ASTC block-compressed pixel data.
+//!
+//! Every ASTC block is 16 bytes long, regardless of its footprint. The
+//! footprint (block width and height) is not stored in the blocks, so it is
+//! read from the `.astc` header that precedes them.
+//!
+//! Only the LDR profile is supported. HDR blocks, as well as blocks with
+//! invalid encodings, are decoded to the error color (magenta), as the ASTC
+//! specification asks LDR decoders to do.
+
+use crate::{error::Error, utils::Reader};
+use std::io::{Read, Seek};
+
+/// Magic number at the start of an `.astc` header.
+const MAGIC: [u8; 4] = [0x13, 0xAB, 0xA1, 0x5C];
+
+/// Block footprints allowed for 2D ASTC textures.
+pub(crate) const FOOTPRINTS: [(u32, u32); 14] = [
+    (4, 4),
+    (5, 4),
+    (5, 5),
+    (6, 5),
+    (6, 6),
+    (8, 5),
+    (8, 6),
+    (8, 8),
+    (10, 5),
+    (10, 6),
+    (10, 8),
+    (10, 10),
+    (12, 10),
+    (12, 12),
+];
+
+/// Color returned for invalid and HDR blocks.
+const ERROR_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+/// Quantization levels usable for color endpoint values, in ascending order.
+const COLOR_LEVELS: [u32; 21] = [
+    2, 3, 4, 5, 6, 8, 10, 12, 16, 20, 24, 32, 40, 48, 64, 80, 96, 128, 160, 192, 256,
+];
+
+/// Reads the `.astc` header before the blocks and returns the block footprint.
+///
+/// The header is 16 bytes long: the magic number, the block width, height and
+/// depth, and the 24-bit texture dimensions.
+pub(crate) fn read_header<R: Read + Seek>(reader: &mut Reader<R>) -> Result<(u32, u32), Error> {
+    let header = reader.try_read(16)?;
+    if header[..4] != MAGIC {
+        return Err(Error::Other("Invalid ASTC header.".to_string()));
+    }
+
+    let footprint = (header[4] as u32, header[5] as u32);
+    if header[6] != 1 || !FOOTPRINTS.contains(&footprint) {
+        return Err(Error::Other(format!(
+            "Unsupported ASTC block footprint ({}x{}x{}).",
+            header[4], header[5], header[6]
+        )));
+    }
+
+    Ok(footprint)
+}
+
+/// Returns `length` bits of `bits` starting at `start`.
+fn get_bits(bits: u128, start: u32, length: u32) -> u32 {
+    ((bits >> start) & ((1 << length) - 1)) as u32
+}
+
+/// Returns how a range with `levels` quantization levels is encoded, as the
+/// number of plain bits, and whether a trit or a quint is used.
+fn ise_encoding(levels: u32) -> (u32, bool, bool) {
+    match levels {
+        3 => (0, true, false),
+        5 => (0, false, true),
+        6 => (1, true, false),
+        10 => (1, false, true),
+        12 => (2, true, false),
+        20 => (2, false, true),
+        24 => (3, true, false),
+        40 => (3, false, true),
+        48 => (4, true, false),
+        80 => (4, false, true),
+        96 => (5, true, false),
+        160 => (5, false, true),
+        192 => (6, true, false),
+        _ => (levels.trailing_zeros(), false, false),
+    }
+}
+
+/// Returns the number of bits needed to encode `count` values with `levels`
+/// quantization levels.
+fn ise_size(count: u32, levels: u32) -> u32 {
+    let (bits, trit, quint) = ise_encoding(levels);
+    if trit {
+        count * bits + (8 * count).div_ceil(5)
+    } else if quint {
+        count * bits + (7 * count).div_ceil(3)
+    } else {
+        count * bits
+    }
+}
+
+/// Decodes the 8 bits of a trit group into five trits.
+fn decode_trits(t: u32) -> [u32; 5] {
+    let bit = |v: u32, i: u32| (v >> i) & 1;
+
+    let (c, t3, t4) = if (t >> 2) & 0x7 == 0x7 {
+        (((t >> 5) & 0x7) << 2 | (t & 0x3), 2, 2)
+    } else if (t >> 5) & 0x3 == 0x3 {
+        (t & 0x1F, bit(t, 7), 2)
+    } else {
+        (t & 0x1F, (t >> 5) & 0x3, bit(t, 7))
+    };
+
+    let (t0, t1, t2) = if c & 0x3 == 0x3 {
+        (
+            (bit(c, 3) << 1) | (bit(c, 2) & !bit(c, 3) & 1),
+            bit(c, 4),
+            2,
+        )
+    } else if (c >> 2) & 0x3 == 0x3 {
+        (c & 0x3, 2, 2)
+    } else {
+        (
+            (bit(c, 1) << 1) | (bit(c, 0) & !bit(c, 1) & 1),
+            (c >> 2) & 0x3,
+            bit(c, 4),
+        )
+    };
+
+    [t0, t1, t2, t3, t4]
+}
+
+/// Decodes the 7 bits of a quint group into three quints.
+fn decode_quints(q: u32) -> [u32; 3] {
+    let bit = |v: u32, i: u32| (v >> i) & 1;
+
+    if (q >> 1) & 0x3 == 0x3 && (q >> 5) & 0x3 == 0 {
+        let q0 = bit(q, 0);
+        return [
+            4,
+            4,
+            (q0 << 2) | ((bit(q, 4) & !q0 & 1) << 1) | (bit(q, 3) & !q0 & 1),
+        ];
+    }
+
+    let (c, q2) = if (q >> 1) & 0x3 == 0x3 {
+        (
+            (((q >> 3) & 0x3) << 3) | ((!(q >> 5) & 0x3) << 1) | bit(q, 0),
+            4,
+        )
+    } else {
+        (q & 0x1F, (q >> 5) & 0x3)
+    };
+
+    let (q0, q1) = if c & 0x7 == 0x5 {
+        ((c >> 3) & 0x3, 4)
+    } else {
+        (c & 0x7, (c >> 3) & 0x3)
+    };
+
+    [q0, q1, q2]
+}
+
+/// Decodes `count` integer sequence encoded values with `levels` quantization
+/// levels, starting at bit `start` of `bits`.
+///
+/// Each value is returned as the plain bits and the trit or quint, if any.
+fn decode_ise(bits: u128, start: u32, count: u32, levels: u32) -> Vec<(u32, u32)> {
+    let (bit_count, trit, quint) = ise_encoding(levels);
+    let end = start + ise_size(count, levels);
+
+    // Bits past the end of the sequence are read as zeros.
+    let read = |position: &mut u32, length: u32| {
+        let mut value = 0;
+        for i in 0..length {
+            if *position + i < end && *position + i < 128 {
+                value |= get_bits(bits, *position + i, 1) << i;
+            }
+        }
+        *position += length;
+        value
+    };
+
+    let mut position = start;
+    let mut values = Vec::new();
+    while (values.len() as u32) < count {
+        if trit {
+            // Five values share 8 bits, interleaved as 2, 2, 1, 2 and 1 bits.
+            let mut plain = [0; 5];
+            let mut packed = 0;
+            for (i, shift, length) in [(0, 0, 2), (1, 2, 2), (2, 4, 1), (3, 5, 2), (4, 7, 1)] {
+                plain[i] = read(&mut position, bit_count);
+                packed |= read(&mut position, length) << shift;
+            }
+
+            for (plain, trit) in plain.iter().zip(decode_trits(packed).iter()) {
+                values.push((*plain, *trit));
+            }
+        } else if quint {
+            // Three values share 7 bits, interleaved as 3, 2 and 2 bits.
+            let mut plain = [0; 3];
+            let mut packed = 0;
+            for (i, shift, length) in [(0, 0, 3), (1, 3, 2), (2, 5, 2)] {
+                plain[i] = read(&mut position, bit_count);
+                packed |= read(&mut position, length) << shift;
+            }
+
+            for (plain, quint) in plain.iter().zip(decode_quints(packed).iter()) {
+                values.push((*plain, *quint));
+            }
+        } else {
+            values.push((read(&mut position, bit_count), 0));
+        }
+    }
+
+    values.truncate(count as usize);
+    values
+}
+
+/// Replicates the lowest `from` bits of `value` to fill `to` bits.
+fn replicate(value: u32, from: u32, to: u32) -> u32 {
+    if from == 0 {
+        return 0;
+    }
+
+    let mut result = 0;
+    let mut filled = 0;
+    while filled < to {
+        result = (result << from) | value;
+        filled += from;
+    }
+
+    result >> (filled - to)
+}
+
+/// Unquantizes a color endpoint value to the range `0..=255`.
+fn unquantize_color((plain, extra): (u32, u32), levels: u32) -> u32 {
+    let (bit_count, trit, quint) = ise_encoding(levels);
+    if !trit && !quint {
+        return replicate(plain, bit_count, 8);
+    }
+
+    let bit = |i: u32| (plain >> i) & 1;
+    let a = if plain & 1 == 1 { 0x1FF } else { 0 };
+    let (b, c) = match (trit, bit_count) {
+        (true, 1) => (0, 204),
+        (true, 2) => (
+            (bit(1) << 8) | (bit(1) << 4) | (bit(1) << 2) | (bit(1) << 1),
+            93,
+        ),
+        (true, 3) => {
+            let cb = (plain >> 1) & 0x3;
+            ((cb << 7) | (cb << 2) | cb, 44)
+        }
+        (true, 4) => {
+            let dcb = (plain >> 1) & 0x7;
+            ((dcb << 6) | dcb, 22)
+        }
+        (true, 5) => {
+            let edcb = (plain >> 1) & 0xF;
+            ((edcb << 5) | (edcb >> 2), 11)
+        }
+        (true, _) => {
+            let fedcb = (plain >> 1) & 0x1F;
+            ((fedcb << 4) | (fedcb >> 4), 5)
+        }
+        (false, 1) => (0, 113),
+        (false, 2) => ((bit(1) << 8) | (bit(1) << 3) | (bit(1) << 2), 54),
+        (false, 3) => {
+            let cb = (plain >> 1) & 0x3;
+            ((cb << 7) | (cb << 1) | (cb >> 1), 26)
+        }
+        (false, 4) => {
+            let dcb = (plain >> 1) & 0x7;
+            ((dcb << 6) | (dcb >> 1), 13)
+        }
+        (false, _) => {
+            let edcb = (plain >> 1) & 0xF;
+            ((edcb << 5) | (edcb >> 3), 6)
+        }
+    };
+
+    let t = (extra * c + b) ^ a;
+    (a & 0x80) | (t >> 2)
+}
+
+/// Unquantizes a weight to the range `0..=64`.
+fn unquantize_weight((plain, extra): (u32, u32), levels: u32) -> u32 {
+    let (bit_count, trit, quint) = ise_encoding(levels);
+
+    let value = if !trit && !quint {
+        replicate(plain, bit_count, 6)
+    } else if bit_count == 0 {
+        return if trit { extra * 32 } else { extra * 16 };
+    } else {
+        let bit = |i: u32| (plain >> i) & 1;
+        let a = if plain & 1 == 1 { 0x7F } else { 0 };
+        let (b, c) = match (trit, bit_count) {
+            (true, 1) => (0, 50),
+            (true, 2) => ((bit(1) << 6) | (bit(1) << 2) | bit(1), 23),
+            (true, _) => {
+                let cb = (plain >> 1) & 0x3;
+                ((cb << 5) | cb, 11)
+            }
+            (false, 1) => (0, 28),
+            (false, _) => ((bit(1) << 6) | (bit(1) << 1), 13),
+        };
+
+        let t = (extra * c + b) ^ a;
+        (a & 0x20) | (t >> 2)
+    };
+
+    if value > 32 {
+        value + 1
+    } else {
+        value
+    }
+}
+
+/// Hash used to select the partition of a texel.
+fn hash52(mut p: u32) -> u32 {
+    p ^= p >> 15;
+    p = p.wrapping_sub(p << 17);
+    p = p.wrapping_add(p << 7);
+    p = p.wrapping_add(p << 4);
+    p ^= p >> 5;
+    p = p.wrapping_add(p << 16);
+    p ^= p >> 7;
+    p ^= p >> 3;
+    p ^= p << 6;
+    p ^= p >> 17;
+    p
+}
+
+/// Returns the partition of the texel at (`x`, `y`).
+fn select_partition(seed: u32, x: u32, y: u32, count: u32, small_block: bool) -> usize {
+    let (x, y) = if small_block {
+        (x << 1, y << 1)
+    } else {
+        (x, y)
+    };
+
+    let seed = seed + (count - 1) * 1024;
+    let rnum = hash52(seed);
+
+    let mut seeds = [0u32; 12];
+    for (i, shift) in [0u32, 4, 8, 12, 16, 20, 24, 28, 18, 22, 26]
+        .iter()
+        .enumerate()
+    {
+        seeds[i] = (rnum >> shift) & 0xF;
+    }
+    seeds[11] = rnum.rotate_left(2) & 0xF;
+    for s in seeds.iter_mut() {
+        *s *= *s;
+    }
+
+    let (sh1, sh2) = if seed & 1 == 1 {
+        (
+            if seed & 2 == 2 { 4 } else { 5 },
+            if count == 3 { 6 } else { 5 },
+        )
+    } else {
+        (
+            if count == 3 { 6 } else { 5 },
+            if seed & 2 == 2 { 4 } else { 5 },
+        )
+    };
+    let sh3 = if seed & 0x10 == 0x10 { sh1 } else { sh2 };
+
+    for (i, s) in seeds.iter_mut().enumerate() {
+        *s >>= match i {
+            8..=11 => sh3,
+            _ if i % 2 == 0 => sh1,
+            _ => sh2,
+        };
+    }
+
+    // `z` is always zero for 2D textures, so `seeds[8..]` don't contribute.
+    let a = (seeds[0] * x + seeds[1] * y + (rnum >> 14)) & 0x3F;
+    let b = (seeds[2] * x + seeds[3] * y + (rnum >> 10)) & 0x3F;
+    let c = if count >= 3 {
+        (seeds[4] * x + seeds[5] * y + (rnum >> 6)) & 0x3F
+    } else {
+        0
+    };
+    let d = if count >= 4 {
+        (seeds[6] * x + seeds[7] * y + (rnum >> 2)) & 0x3F
+    } else {
+        0
+    };
+
+    if a >= b && a >= c && a >= d {
+        0
+    } else if b >= c && b >= d {
+        1
+    } else if c >= d {
+        2
+    } else {
+        3
+    }
+}
+
+/// Transfers the top bit of `b` to `a`, turning `b` into a signed offset.
+fn bit_transfer_signed(a: &mut i32, b: &mut i32) {
+    *b = (*b >> 1) | (*a & 0x80);
+    *a = (*a >> 1) & 0x3F;
+    if *a & 0x20 != 0 {
+        *a -= 0x40;
+    }
+}
+
+/// Moves blue towards red and green, which some endpoint modes use to gain
+/// precision.
+fn blue_contract(color: [i32; 4]) -> [i32; 4] {
+    [
+        (color[0] + color[2]) >> 1,
+        (color[1] + color[2]) >> 1,
+        color[2],
+        color[3],
+    ]
+}
+
+/// Decodes a pair of color endpoints. `None` is returned for HDR modes.
+fn decode_endpoints(mode: u32, v: &[u32]) -> Option<[[u8; 4]; 2]> {
+    let mut v: Vec<i32> = v.iter().map(|x| *x as i32).collect();
+
+    let (first, second) = match mode {
+        // Luminance, direct.
+        0 => ([v[0], v[0], v[0], 255], [v[1], v[1], v[1], 255]),
+        // Luminance, base and offset.
+        1 => {
+            let l0 = (v[0] >> 2) | (v[1] & 0xC0);
+            let l1 = (l0 + (v[1] & 0x3F)).min(255);
+            ([l0, l0, l0, 255], [l1, l1, l1, 255])
+        }
+        // Luminance and alpha, direct.
+        4 => ([v[0], v[0], v[0], v[2]], [v[1], v[1], v[1], v[3]]),
+        // Luminance and alpha, base and offset.
+        5 => {
+            let (mut v0, mut v1, mut v2, mut v3) = (v[0], v[1], v[2], v[3]);
+            bit_transfer_signed(&mut v1, &mut v0);
+            bit_transfer_signed(&mut v3, &mut v2);
+            ([v0, v0, v0, v2], [v0 + v1, v0 + v1, v0 + v1, v2 + v3])
+        }
+        // RGB, base and scale.
+        6 => (
+            [
+                (v[0] * v[3]) >> 8,
+                (v[1] * v[3]) >> 8,
+                (v[2] * v[3]) >> 8,
+                255,
+            ],
+            [v[0], v[1], v[2], 255],
+        ),
+        // RGB and RGBA, direct.
+        8 | 12 => {
+            let (a0, a1) = if mode == 12 { (v[6], v[7]) } else { (255, 255) };
+            if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+                ([v[0], v[2], v[4], a0], [v[1], v[3], v[5], a1])
+            } else {
+                (
+                    blue_contract([v[1], v[3], v[5], a1]),
+                    blue_contract([v[0], v[2], v[4], a0]),
+                )
+            }
+        }
+        // RGB and RGBA, base and offset.
+        9 | 13 => {
+            for i in (0..v.len()).step_by(2) {
+                let (mut base, mut offset) = (v[i], v[i + 1]);
+                bit_transfer_signed(&mut offset, &mut base);
+                v[i] = base;
+                v[i + 1] = offset;
+            }
+
+            let (a0, a1) = if mode == 13 {
+                (v[6], v[6] + v[7])
+            } else {
+                (255, 255)
+            };
+            let offset = [v[0] + v[1], v[2] + v[3], v[4] + v[5], a1];
+            if v[1] + v[3] + v[5] >= 0 {
+                ([v[0], v[2], v[4], a0], offset)
+            } else {
+                (blue_contract(offset), blue_contract([v[0], v[2], v[4], a0]))
+            }
+        }
+        // RGB, base and scale, plus two alphas.
+        10 => (
+            [
+                (v[0] * v[3]) >> 8,
+                (v[1] * v[3]) >> 8,
+                (v[2] * v[3]) >> 8,
+                v[4],
+            ],
+            [v[0], v[1], v[2], v[5]],
+        ),
+        _ => return None,
+    };
+
+    let clamp = |c: [i32; 4]| {
+        [
+            c[0].clamp(0, 255) as u8,
+            c[1].clamp(0, 255) as u8,
+            c[2].clamp(0, 255) as u8,
+            c[3].clamp(0, 255) as u8,
+        ]
+    };
+
+    Some([clamp(first), clamp(second)])
+}
+
+/// Layout of a block, as described by its block mode.
+struct BlockMode {
+    weight_width: u32,
+    weight_height: u32,
+    weight_levels: u32,
+    dual_plane: bool,
+}
+
+/// Decodes the 11-bit block mode. `None` is returned for reserved modes.
+fn decode_block_mode(mode: u32) -> Option<BlockMode> {
+    let bit = |i: u32| (mode >> i) & 1;
+    let a = (mode >> 5) & 0x3;
+
+    let (range, high_precision, dual_plane, weight_width, weight_height);
+    if mode & 0x3 != 0 {
+        range = ((mode & 0x3) << 1) | bit(4);
+        high_precision = bit(9) == 1;
+        dual_plane = bit(10) == 1;
+
+        let b = (mode >> 7) & 0x3;
+        let (w, h) = match (mode >> 2) & 0x3 {
+            0 => (b + 4, a + 2),
+            1 => (b + 8, a + 2),
+            2 => (a + 2, b + 8),
+            _ if bit(8) == 0 => (a + 2, bit(7) + 6),
+            _ => (bit(7) + 2, a + 2),
+        };
+        weight_width = w;
+        weight_height = h;
+    } else {
+        range = (((mode >> 2) & 0x3) << 1) | bit(4);
+        if range == 0 {
+            return None;
+        }
+
+        let b = (mode >> 9) & 0x3;
+        let (w, h, high, dual) = match (mode >> 7) & 0x3 {
+            0 => (12, a + 2, bit(9), bit(10)),
+            1 => (a + 2, 12, bit(9), bit(10)),
+            2 => (a + 6, b + 6, 0, 0),
+            _ => match (mode >> 5) & 0x3 {
+                0 => (6, 10, bit(9), bit(10)),
+                1 => (10, 6, bit(9), bit(10)),
+                _ => return None,
+            },
+        };
+        weight_width = w;
+        weight_height = h;
+        high_precision = high == 1;
+        dual_plane = dual == 1;
+    }
+
+    if range < 2 {
+        return None;
+    }
+
+    let levels = [2, 3, 4, 5, 6, 8];
+    let high_levels = [10, 12, 16, 20, 24, 32];
+    let weight_levels = if high_precision {
+        high_levels[range as usize - 2]
+    } else {
+        levels[range as usize - 2]
+    };
+
+    Some(BlockMode {
+        weight_width,
+        weight_height,
+        weight_levels,
+        dual_plane,
+    })
+}
+
+/// Decodes a void-extent block, which has a single color.
+fn decode_void_extent(bits: u128, block_width: u32, block_height: u32) -> Vec<[u8; 4]> {
+    let pixel_count = (block_width * block_height) as usize;
+
+    // HDR void-extent blocks can't be decoded by an LDR decoder, and the
+    // reserved bits must be set.
+    if get_bits(bits, 9, 1) == 1 || get_bits(bits, 10, 2) != 3 {
+        return vec![ERROR_COLOR; pixel_count];
+    }
+
+    // The extent is ignored, but it must be empty (all bits set) or valid.
+    let (s_low, s_high) = (get_bits(bits, 12, 13), get_bits(bits, 25, 13));
+    let (t_low, t_high) = (get_bits(bits, 38, 13), get_bits(bits, 51, 13));
+    let empty = [s_low, s_high, t_low, t_high].iter().all(|&c| c == 0x1FFF);
+    if !empty && (s_low >= s_high || t_low >= t_high) {
+        return vec![ERROR_COLOR; pixel_count];
+    }
+
+    let color = [
+        (get_bits(bits, 64, 16) >> 8) as u8,
+        (get_bits(bits, 80, 16) >> 8) as u8,
+        (get_bits(bits, 96, 16) >> 8) as u8,
+        (get_bits(bits, 112, 16) >> 8) as u8,
+    ];
+
+    vec![color; pixel_count]
+}
+
+/// Decodes a single 16-byte ASTC block with the given footprint.
+///
+/// The pixels of the block are returned in row-major order.
+pub(crate) fn decode_block(block: &[u8], block_width: u32, block_height: u32) -> Vec<[u8; 4]> {
+    let pixel_count = (block_width * block_height) as usize;
+
+    let mut bytes = [0; 16];
+    bytes.copy_from_slice(&block[..16]);
+    let bits = u128::from_le_bytes(bytes);
+
+    if get_bits(bits, 0, 9) == 0x1FC {
+        return decode_void_extent(bits, block_width, block_height);
+    }
+
+    let mode = match decode_block_mode(get_bits(bits, 0, 11)) {
+        Some(m) => m,
+        None => return vec![ERROR_COLOR; pixel_count],
+    };
+
+    let partition_count = get_bits(bits, 11, 2) + 1;
+    let planes = if mode.dual_plane { 2 } else { 1 };
+    let weight_count = mode.weight_width * mode.weight_height * planes;
+    let weight_bits = ise_size(weight_count, mode.weight_levels);
+
+    if mode.weight_width > block_width
+        || mode.weight_height > block_height
+        || weight_count > 64
+        || !(24..=96).contains(&weight_bits)
+        || (mode.dual_plane && partition_count == 4)
+    {
+        return vec![ERROR_COLOR; pixel_count];
+    }
+
+    // Read the color endpoint modes of the partitions.
+    let mut below_weights = 128 - weight_bits;
+    let (endpoint_modes, color_start) = if partition_count == 1 {
+        (vec![get_bits(bits, 13, 4)], 17)
+    } else {
+        let selector = get_bits(bits, 23, 6);
+        if selector & 0x3 == 0 {
+            (vec![selector >> 2; partition_count as usize], 29)
+        } else {
+            // The extra bits of the modes are stored right below the weights.
+            let extra_bits = 3 * partition_count - 4;
+            below_weights -= extra_bits;
+            let selector = selector | (get_bits(bits, below_weights, extra_bits) << 6);
+
+            let base_class = (selector & 0x3) - 1;
+            let modes = (0..partition_count)
+                .map(|i| {
+                    let class = base_class + ((selector >> (2 + i)) & 1);
+                    let mode = (selector >> (2 + partition_count + 2 * i)) & 0x3;
+                    (class << 2) | mode
+                })
+                .collect();
+
+            (modes, 29)
+        }
+    };
+
+    let component = if mode.dual_plane {
+        below_weights -= 2;
+        Some(get_bits(bits, below_weights, 2) as usize)
+    } else {
+        None
+    };
+
+    // Pick the largest color range that fits in the available bits.
+    let value_count: u32 = endpoint_modes.iter().map(|m| 2 * ((m >> 2) + 1)).sum();
+    if value_count > 18 || below_weights <= color_start {
+        return vec![ERROR_COLOR; pixel_count];
+    }
+    let available = below_weights - color_start;
+    let color_levels = match COLOR_LEVELS
+        .iter()
+        .rev()
+        .find(|levels| ise_size(value_count, **levels) <= available)
+    {
+        Some(l) if *l >= 6 => *l,
+        _ => return vec![ERROR_COLOR; pixel_count],
+    };
+
+    let values: Vec<u32> = decode_ise(bits, color_start, value_count, color_levels)
+        .into_iter()
+        .map(|v| unquantize_color(v, color_levels))
+        .collect();
+
+    let mut endpoints = Vec::new();
+    let mut offset = 0;
+    for endpoint_mode in endpoint_modes.iter() {
+        let count = (2 * ((endpoint_mode >> 2) + 1)) as usize;
+        match decode_endpoints(*endpoint_mode, &values[offset..offset + count]) {
+            Some(e) => endpoints.push(e),
+            None => return vec![ERROR_COLOR; pixel_count],
+        }
+        offset += count;
+    }
+
+    // Weights are stored from the top of the block, with their bits reversed.
+    let weights: Vec<u32> = decode_ise(bits.reverse_bits(), 0, weight_count, mode.weight_levels)
+        .into_iter()
+        .map(|w| unquantize_weight(w, mode.weight_levels))
+        .collect();
+
+    let partition_seed = get_bits(bits, 13, 10);
+    let small_block = pixel_count < 31;
+
+    // Scale factors used to map texels on to the weight grid.
+    let ds = (1024 + block_width / 2) / (block_width - 1);
+    let dt = (1024 + block_height / 2) / (block_height - 1);
+
+    let mut pixels = Vec::with_capacity(pixel_count);
+    for y in 0..block_height {
+        for x in 0..block_width {
+            let gs = (ds * x * (mode.weight_width - 1) + 32) >> 6;
+            let gt = (dt * y * (mode.weight_height - 1) + 32) >> 6;
+            let (js, fs) = (gs >> 4, gs & 0xF);
+            let (jt, ft) = (gt >> 4, gt & 0xF);
+
+            let w11 = (fs * ft + 8) >> 4;
+            let factors = [16 + w11 - fs - ft, fs - w11, ft - w11, w11];
+            let base = js + jt * mode.weight_width;
+            let indices = [
+                base,
+                base + 1,
+                base + mode.weight_width,
+                base + mode.weight_width + 1,
+            ];
+
+            // Interpolates the weight of a plane from the four nearest grid points.
+            let weight = |plane: u32| {
+                let mut sum = 0;
+                for (index, factor) in indices.iter().zip(factors.iter()) {
+                    if *factor != 0 {
+                        sum += weights[(index * planes + plane) as usize] * factor;
+                    }
+                }
+                (sum + 8) >> 4
+            };
+
+            let partition = if partition_count > 1 {
+                select_partition(partition_seed, x, y, partition_count, small_block)
+            } else {
+                0
+            };
+            let [first, second] = endpoints[partition];
+
+            let mut pixel = [0; 4];
+            for c in 0..4 {
+                let w = if component == Some(c) {
+                    weight(1)
+                } else {
+                    weight(0)
+                };
+                let (c0, c1) = (first[c] as u32 * 257, second[c] as u32 * 257);
+                pixel[c] = (((c0 * (64 - w) + c1 * w + 32) / 64) >> 8) as u8;
+            }
+
+            pixels.push(pixel);
+        }
+    }
+
+    pixels
+}
@@ -0,0 +1,88 @@
+//! Combining the sprites of a `_tex.sc` file into a single atlas image.
+//!
+//! The sprites are packed into rows (shelves), tallest first, in an atlas
+//! about as wide as it is tall.
+
+use image::{imageops, RgbaImage};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Where a sprite was placed in the atlas saved with
+/// [`TexOptions::combine`].
+///
+/// [`TexOptions::combine`]: ./struct.TexOptions.html#structfield.combine
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub struct AtlasSprite {
+    /// Index of the sprite in the file.
+    pub index: usize,
+    /// Horizontal offset of the sprite in the atlas.
+    pub x: u32,
+    /// Vertical offset of the sprite in the atlas.
+    pub y: u32,
+    /// Width of the sprite.
+    pub width: u32,
+    /// Height of the sprite.
+    pub height: u32,
+}
+
+/// The atlas saved with [`TexOptions::combine`].
+///
+/// [`TexOptions::combine`]: ./struct.TexOptions.html#structfield.combine
+#[derive(Clone, Debug, PartialEq)]
+pub struct TexAtlas {
+    /// Path of the saved image.
+    pub path: PathBuf,
+    /// Path of the saved JSON manifest, an array of the [`sprites`].
+    ///
+    /// [`sprites`]: #structfield.sprites
+    pub manifest_path: PathBuf,
+    /// Where each sprite was placed, in the order they are stored in the
+    /// file.
+    pub sprites: Vec<AtlasSprite>,
+}
+
+/// Packs `images`, each with the index of its sprite, into one atlas.
+///
+/// Returns the atlas and where each sprite was placed, in the order of
+/// `images`. `images` must not be empty.
+pub(super) fn build_atlas(images: &[(usize, RgbaImage)]) -> (RgbaImage, Vec<AtlasSprite>) {
+    let widest = images.iter().map(|(_, image)| image.width()).max();
+    let area: u64 = images
+        .iter()
+        .map(|(_, image)| image.width() as u64 * image.height() as u64)
+        .sum();
+    let width = widest.unwrap_or(1).max((area as f64).sqrt().ceil() as u32);
+
+    // Tall sprites first, so that the rows waste less space.
+    let mut order: Vec<_> = (0..images.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(images[i].1.height()));
+
+    let mut sprites = vec![None; images.len()];
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+    for i in order {
+        let (index, image) = &images[i];
+        if x + image.width() > width {
+            x = 0;
+            y += row_height;
+            row_height = 0;
+        }
+
+        sprites[i] = Some(AtlasSprite {
+            index: *index,
+            x,
+            y,
+            width: image.width(),
+            height: image.height(),
+        });
+        x += image.width();
+        row_height = row_height.max(image.height());
+    }
+
+    let mut atlas = RgbaImage::new(width, (y + row_height).max(1));
+    let sprites: Vec<_> = sprites.into_iter().flatten().collect();
+    for (sprite, (_, image)) in sprites.iter().zip(images) {
+        imageops::replace(&mut atlas, image, sprite.x, sprite.y);
+    }
+
+    (atlas, sprites)
+}
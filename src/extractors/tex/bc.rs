@@ -0,0 +1,111 @@
+//! Decoding of BC1 (DXT1) and BC3 (DXT5) block-compressed pixel data.
+//!
+//! A BC1 block is 8 bytes long and holds 4x4 pixels: two RGB565 endpoint
+//! colors followed by 2-bit color indices. A BC3 block is 16 bytes long: an
+//! 8-byte alpha block (two 8-bit endpoints and 3-bit indices) followed by a
+//! BC1-style color block.
+
+use super::unpremultiply;
+
+/// Returns `sum / max` scaled to `0..=255`, rounded to the nearest integer.
+fn scale(sum: u32, max: u32) -> u8 {
+    ((sum * 510 + max) / (2 * max)) as u8
+}
+
+/// Splits an RGB565 color into its 5-, 6- and 5-bit channels.
+fn split_565(color: u16) -> [u32; 3] {
+    [
+        (color >> 11) as u32 & 0x1F,
+        (color >> 5) as u32 & 0x3F,
+        color as u32 & 0x1F,
+    ]
+}
+
+/// Returns the color of each pixel of a BC1-style color block.
+///
+/// If `allow_alpha` is `true` and the first endpoint is not greater than the
+/// second one, the block uses three colors and 1-bit alpha: index `3` is
+/// transparent black. Otherwise, the block uses four opaque colors.
+///
+/// The endpoints and the colors between them are rounded from the 565
+/// channels directly, like bcdec does, rather
+/// than interpolated between the expanded 8-bit endpoints.
+fn decode_color_block(block: &[u8], allow_alpha: bool) -> Vec<[u8; 4]> {
+    let first = u16::from_le_bytes([block[0], block[1]]);
+    let second = u16::from_le_bytes([block[2], block[3]]);
+    let (c0, c1) = (split_565(first), split_565(second));
+    let max = [31, 63, 31];
+
+    let mut colors = [[0, 0, 0, 255]; 4];
+    for i in 0..3 {
+        colors[0][i] = scale(c0[i], max[i]);
+        colors[1][i] = scale(c1[i], max[i]);
+        if first > second || !allow_alpha {
+            colors[2][i] = scale(2 * c0[i] + c1[i], 3 * max[i]);
+            colors[3][i] = scale(c0[i] + 2 * c1[i], 3 * max[i]);
+        } else {
+            colors[2][i] = scale(c0[i] + c1[i], 2 * max[i]);
+        }
+    }
+    if first <= second && allow_alpha {
+        colors[3] = [0; 4];
+    }
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    (0..16)
+        .map(|i| colors[((indices >> (2 * i)) & 0x3) as usize])
+        .collect()
+}
+
+/// Returns the alpha of each pixel of a BC3 alpha block.
+///
+/// The interpolated alphas are rounded the way bcdec rounds them.
+fn decode_alpha_block(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+
+    let mut alphas = [a0, a1, 0, 0, 0, 0, 0, 255];
+    if a0 > a1 {
+        for (i, alpha) in alphas.iter_mut().enumerate().skip(2) {
+            *alpha = ((8 - i as u32) * a0 + (i as u32 - 1) * a1 + 1) / 7;
+        }
+    } else {
+        for (i, alpha) in alphas.iter_mut().enumerate().take(6).skip(2) {
+            *alpha = ((6 - i as u32) * a0 + (i as u32 - 1) * a1 + 1) / 5;
+        }
+    }
+
+    let mut indices = [0; 8];
+    indices[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(indices);
+
+    let mut result = [0; 16];
+    for (i, alpha) in result.iter_mut().enumerate() {
+        *alpha = alphas[((indices >> (3 * i)) & 0x7) as usize] as u8;
+    }
+    result
+}
+
+/// Decodes a BC1 block and returns its pixels in row-major order.
+pub(crate) fn decode_bc1_block(block: &[u8]) -> Vec<[u8; 4]> {
+    decode_color_block(block, true)
+}
+
+/// Decodes a BC3 block with straight alpha and returns its pixels in
+/// row-major order.
+pub(crate) fn decode_bc3_block(block: &[u8]) -> Vec<[u8; 4]> {
+    let alphas = decode_alpha_block(&block[..8]);
+
+    let mut pixels = decode_color_block(&block[8..], false);
+    for (pixel, alpha) in pixels.iter_mut().zip(alphas.iter()) {
+        pixel[3] = *alpha;
+    }
+    pixels
+}
+
+/// Decodes a BC3 block with premultiplied alpha (DXT4) and returns its pixels
+/// in row-major order, with straight alpha.
+pub(crate) fn decode_bc3_premultiplied_block(block: &[u8]) -> Vec<[u8; 4]> {
+    let mut pixels = decode_bc3_block(block);
+    pixels.iter_mut().for_each(unpremultiply);
+    pixels
+}
@@ -0,0 +1,306 @@
+//! Decoding of ETC1 and ETC2 block-compressed pixel data.
+//!
+//! An ETC1 or ETC2 RGB block is 8 bytes long and holds 4x4 pixels. ETC1
+//! splits the block into two sub-blocks of 2x4 or 4x2 pixels, each with its
+//! own base color and intensity modifier table. ETC2 reuses the invalid
+//! differential encodings of ETC1 for three extra modes (T, H and planar).
+//!
+//! An ETC2 RGBA block is 16 bytes long: an 8-byte EAC alpha block followed by
+//! an 8-byte ETC2 RGB block.
+
+use super::{extend_4, extend_5, extend_6};
+
+/// Intensity modifier tables, indexed by a sub-block's table codeword.
+///
+/// Each table is ordered by the 2-bit pixel index (`msb << 1 | lsb`).
+const MODIFIERS: [[i32; 4]; 8] = [
+    [2, 8, -2, -8],
+    [5, 17, -5, -17],
+    [9, 29, -9, -29],
+    [13, 42, -13, -42],
+    [18, 60, -18, -60],
+    [24, 80, -24, -80],
+    [33, 106, -33, -106],
+    [47, 183, -47, -183],
+];
+
+/// Distances used by the T and H modes of ETC2.
+const DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+/// Alpha modifier tables used by EAC, indexed by the table index of a block.
+const ALPHA_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+/// Extends a 7-bit color channel to 8 bits.
+fn extend_7(value: u8) -> u8 {
+    (value << 1) | (value >> 6)
+}
+
+/// Adds `modifier` to each channel of `color`, clamping the result.
+fn modify(color: [u8; 3], modifier: i32) -> [u8; 4] {
+    [
+        (color[0] as i32 + modifier).clamp(0, 255) as u8,
+        (color[1] as i32 + modifier).clamp(0, 255) as u8,
+        (color[2] as i32 + modifier).clamp(0, 255) as u8,
+        255,
+    ]
+}
+
+/// Returns the 2-bit index of the pixel at (`x`, `y`) in a color block.
+fn pixel_index(block: &[u8], x: usize, y: usize) -> usize {
+    let msb = u16::from_be_bytes([block[4], block[5]]);
+    let lsb = u16::from_be_bytes([block[6], block[7]]);
+
+    // Pixel indices are stored column by column.
+    let i = x * 4 + y;
+    ((((msb >> i) & 1) << 1) | ((lsb >> i) & 1)) as usize
+}
+
+/// Decodes a block in the individual or differential mode, which is shared
+/// by ETC1 and ETC2.
+fn decode_sub_blocks(block: &[u8], first: [u8; 3], second: [u8; 3]) -> Vec<[u8; 4]> {
+    let flip = block[3] & 0x1 == 1;
+    let tables = [
+        MODIFIERS[(block[3] >> 5) as usize],
+        MODIFIERS[((block[3] >> 2) & 0x7) as usize],
+    ];
+
+    let mut pixels = vec![[0; 4]; 16];
+    for x in 0..4 {
+        for y in 0..4 {
+            let sub_block = if flip { y >= 2 } else { x >= 2 } as usize;
+            let base = if sub_block == 0 { first } else { second };
+
+            pixels[y * 4 + x] = modify(base, tables[sub_block][pixel_index(block, x, y)]);
+        }
+    }
+
+    pixels
+}
+
+/// Decodes a block in the T or H mode of ETC2, where every pixel index picks
+/// one of four paint colors.
+fn decode_paint_colors(block: &[u8], paint_colors: [[u8; 4]; 4]) -> Vec<[u8; 4]> {
+    let mut pixels = vec![[0; 4]; 16];
+    for x in 0..4 {
+        for y in 0..4 {
+            pixels[y * 4 + x] = paint_colors[pixel_index(block, x, y)];
+        }
+    }
+
+    pixels
+}
+
+/// Decodes a block in the T mode of ETC2.
+fn decode_t_mode(block: &[u8]) -> Vec<[u8; 4]> {
+    let first = [
+        extend_4((((block[0] >> 3) & 0x3) << 2) | (block[0] & 0x3)),
+        extend_4(block[1] >> 4),
+        extend_4(block[1] & 0xF),
+    ];
+    let second = [
+        extend_4(block[2] >> 4),
+        extend_4(block[2] & 0xF),
+        extend_4(block[3] >> 4),
+    ];
+    let distance = DISTANCES[((((block[3] >> 2) & 0x3) << 1) | (block[3] & 0x1)) as usize];
+
+    decode_paint_colors(
+        block,
+        [
+            modify(first, 0),
+            modify(second, distance),
+            modify(second, 0),
+            modify(second, -distance),
+        ],
+    )
+}
+
+/// Decodes a block in the H mode of ETC2.
+fn decode_h_mode(block: &[u8]) -> Vec<[u8; 4]> {
+    let first = [
+        (block[0] >> 3) & 0xF,
+        ((block[0] & 0x7) << 1) | ((block[1] >> 4) & 0x1),
+        (block[1] & 0x8) | ((block[1] & 0x3) << 1) | (block[2] >> 7),
+    ];
+    let second = [
+        (block[2] >> 3) & 0xF,
+        ((block[2] & 0x7) << 1) | (block[3] >> 7),
+        (block[3] >> 3) & 0xF,
+    ];
+
+    // The lowest bit of the distance index is implied by the order of the colors.
+    let order = |c: [u8; 3]| ((c[0] as u32) << 8) | ((c[1] as u32) << 4) | c[2] as u32;
+    let index = (((block[3] >> 2) & 0x1) << 2)
+        | ((block[3] & 0x1) << 1)
+        | (order(first) >= order(second)) as u8;
+    let distance = DISTANCES[index as usize];
+
+    let first = [extend_4(first[0]), extend_4(first[1]), extend_4(first[2])];
+    let second = [
+        extend_4(second[0]),
+        extend_4(second[1]),
+        extend_4(second[2]),
+    ];
+
+    decode_paint_colors(
+        block,
+        [
+            modify(first, distance),
+            modify(first, -distance),
+            modify(second, distance),
+            modify(second, -distance),
+        ],
+    )
+}
+
+/// Decodes a block in the planar mode of ETC2.
+fn decode_planar_mode(block: &[u8]) -> Vec<[u8; 4]> {
+    let bits = u64::from_be_bytes([
+        block[0], block[1], block[2], block[3], block[4], block[5], block[6], block[7],
+    ]);
+    let get = |shift: u32, length: u32| ((bits >> shift) & ((1 << length) - 1)) as u8;
+
+    let origin = [
+        extend_6(get(57, 6)),
+        extend_7((get(56, 1) << 6) | get(49, 6)),
+        extend_6((get(48, 1) << 5) | (get(43, 2) << 3) | get(39, 3)),
+    ];
+    let horizontal = [
+        extend_6((get(34, 5) << 1) | get(32, 1)),
+        extend_7(get(25, 7)),
+        extend_6(get(19, 6)),
+    ];
+    let vertical = [
+        extend_6(get(13, 6)),
+        extend_7(get(6, 7)),
+        extend_6(get(0, 6)),
+    ];
+
+    let mut pixels = vec![[0; 4]; 16];
+    for x in 0..4 {
+        for y in 0..4 {
+            let mut pixel = [255; 4];
+            for c in 0..3 {
+                let (o, h, v) = (origin[c] as i32, horizontal[c] as i32, vertical[c] as i32);
+                pixel[c] = ((x as i32 * (h - o) + y as i32 * (v - o) + 4 * o + 2) >> 2)
+                    .clamp(0, 255) as u8;
+            }
+
+            pixels[y * 4 + x] = pixel;
+        }
+    }
+
+    pixels
+}
+
+/// Decodes a single 8-byte ETC1 block.
+///
+/// The 16 pixels of the block are returned in row-major order.
+pub(crate) fn decode_etc1_block(block: &[u8]) -> Vec<[u8; 4]> {
+    let mut first = [0; 3];
+    let mut second = [0; 3];
+
+    if block[3] & 0x2 == 0 {
+        // Individual mode: two 4-bit colors.
+        for i in 0..3 {
+            first[i] = extend_4(block[i] >> 4);
+            second[i] = extend_4(block[i] & 0xF);
+        }
+    } else {
+        // Differential mode: a 5-bit color and a signed 3-bit delta.
+        for i in 0..3 {
+            let base = block[i] >> 3;
+            let delta = (block[i] & 0x7) as i8;
+            let delta = if delta >= 4 { delta - 8 } else { delta };
+
+            first[i] = extend_5(base);
+            second[i] = extend_5((base as i8 + delta) as u8 & 0x1F);
+        }
+    }
+
+    decode_sub_blocks(block, first, second)
+}
+
+/// Decodes a single 8-byte ETC2 RGB block.
+///
+/// The 16 pixels of the block are returned in row-major order.
+pub(crate) fn decode_etc2_block(block: &[u8]) -> Vec<[u8; 4]> {
+    if block[3] & 0x2 == 0 {
+        return decode_etc1_block(block);
+    }
+
+    // A differential channel overflowing its 5-bit range selects an ETC2 mode.
+    let overflows = |i: usize| {
+        let delta = (block[i] & 0x7) as i8;
+        let delta = if delta >= 4 { delta - 8 } else { delta };
+        let value = (block[i] >> 3) as i8 + delta;
+
+        !(0..32).contains(&value)
+    };
+
+    if overflows(0) {
+        decode_t_mode(block)
+    } else if overflows(1) {
+        decode_h_mode(block)
+    } else if overflows(2) {
+        decode_planar_mode(block)
+    } else {
+        decode_etc1_block(block)
+    }
+}
+
+/// Decodes a single 8-byte EAC alpha block.
+///
+/// The 16 alpha values of the block are returned in row-major order.
+fn decode_eac_alpha_block(block: &[u8]) -> [u8; 16] {
+    let base = block[0] as i32;
+    let multiplier = (block[1] >> 4) as i32;
+    let table = ALPHA_MODIFIERS[(block[1] & 0xF) as usize];
+    let indices = u64::from_be_bytes([
+        0, 0, block[2], block[3], block[4], block[5], block[6], block[7],
+    ]);
+
+    let mut alphas = [0; 16];
+    for x in 0..4 {
+        for y in 0..4 {
+            // 3-bit indices are stored column by column, starting at the top bits.
+            let i = x * 4 + y;
+            let index = ((indices >> (45 - 3 * i)) & 0x7) as usize;
+
+            alphas[y * 4 + x] = (base + table[index] * multiplier).clamp(0, 255) as u8;
+        }
+    }
+
+    alphas
+}
+
+/// Decodes a single 16-byte ETC2 RGBA block.
+///
+/// The 16 pixels of the block are returned in row-major order.
+pub(crate) fn decode_etc2_rgba_block(block: &[u8]) -> Vec<[u8; 4]> {
+    let alphas = decode_eac_alpha_block(&block[..8]);
+    let mut pixels = decode_etc2_block(&block[8..]);
+
+    for (pixel, alpha) in pixels.iter_mut().zip(alphas.iter()) {
+        pixel[3] = *alpha;
+    }
+
+    pixels
+}
@@ -0,0 +1,134 @@
+//! Parsing of KTX containers, which newer `_tex.sc` files (file types `45` and
+//! `47`) wrap their texture data in.
+//!
+//! A KTX 1 file starts with a 12-byte identifier and thirteen 32-bit header
+//! fields, followed by key-value data and the mipmap levels. Every level is
+//! prefixed with its size in bytes. Only the first (largest) level is decoded.
+
+use super::astc;
+use crate::{error::Error, utils::Reader};
+use std::io::Cursor;
+
+/// Identifier at the start of a KTX 1 file.
+const IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// Identifier at the start of a KTX 2 file.
+const IDENTIFIER_2: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// Value of the endianness field when the file is little-endian.
+const LITTLE_ENDIAN: u32 = 0x0403_0201;
+
+/// How the pixels of a KTX texture are stored.
+pub(crate) enum Encoding {
+    /// Stored like the `_tex.sc` pixel (or block-compressed) type.
+    SubType(u8),
+    /// ASTC blocks with the given block width and height.
+    Astc(u32, u32),
+}
+
+/// The first mipmap level of a KTX texture, borrowed from the container.
+pub(crate) struct Texture<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub encoding: Encoding,
+    pub data: &'a [u8],
+}
+
+/// Returns the encoding of a texture with the given OpenGL internal format,
+/// format and type, or `None` if it can't be decoded.
+fn encoding(internal_format: u32, format: u32, data_type: u32) -> Option<Encoding> {
+    let encoding = match (internal_format, format, data_type) {
+        // GL_RGBA8 or GL_RGBA, GL_UNSIGNED_BYTE
+        (0x8058, _, _) | (0x1908, 0x1908, 0x1401) => Encoding::SubType(0),
+        // GL_RGBA4 or GL_RGBA, GL_UNSIGNED_SHORT_4_4_4_4
+        (0x8056, _, _) | (0x1908, 0x1908, 0x8033) => Encoding::SubType(2),
+        // GL_RGB5_A1 or GL_RGBA, GL_UNSIGNED_SHORT_5_5_5_1
+        (0x8057, _, _) | (0x1908, 0x1908, 0x8034) => Encoding::SubType(3),
+        // GL_RGB565 or GL_RGB, GL_UNSIGNED_SHORT_5_6_5
+        (0x8D62, _, _) | (0x1907, 0x1907, 0x8363) => Encoding::SubType(4),
+        // GL_LUMINANCE, GL_UNSIGNED_BYTE
+        (0x1909, 0x1909, 0x1401) => Encoding::SubType(10),
+        // GL_ETC1_RGB8_OES
+        (0x8D64, _, _) => Encoding::SubType(11),
+        // GL_COMPRESSED_RGB8_ETC2
+        (0x9274, _, _) => Encoding::SubType(12),
+        // GL_COMPRESSED_RGBA8_ETC2_EAC
+        (0x9278, _, _) => Encoding::SubType(13),
+        // GL_COMPRESSED_RGB_PVRTC_4BPPV1_IMG and GL_COMPRESSED_RGBA_PVRTC_4BPPV1_IMG
+        (0x8C00, _, _) | (0x8C02, _, _) => Encoding::SubType(15),
+        // GL_COMPRESSED_RGB_S3TC_DXT1_EXT and GL_COMPRESSED_RGBA_S3TC_DXT1_EXT
+        (0x83F0, _, _) | (0x83F1, _, _) => Encoding::SubType(16),
+        // GL_COMPRESSED_RGBA_S3TC_DXT5_EXT
+        (0x83F3, _, _) => Encoding::SubType(17),
+        // GL_COMPRESSED_RGBA_ASTC_4x4_KHR to GL_COMPRESSED_RGBA_ASTC_12x12_KHR
+        (0x93B0..=0x93BD, _, _) => {
+            let (width, height) = astc::FOOTPRINTS[(internal_format - 0x93B0) as usize];
+            Encoding::Astc(width, height)
+        }
+        _ => return None,
+    };
+
+    Some(encoding)
+}
+
+/// Parses KTX `data` and returns its first mipmap level.
+///
+/// If the texture is stored in a format that can't be decoded, an
+/// [`Error::UnknownPixel`] naming the OpenGL internal format (or the Vulkan
+/// format for KTX 2 textures) is returned.
+///
+/// [`Error::UnknownPixel`]: ./error/enum.Error.html#variant.UnknownPixel
+pub(crate) fn parse(data: &[u8]) -> Result<Texture<'_>, Error> {
+    let mut reader = Reader::new(Cursor::new(data));
+
+    let identifier = reader.try_read(12)?;
+    if identifier == IDENTIFIER_2 {
+        let vk_format = reader.try_read_uint32()?;
+        return Err(Error::UnknownPixel(format!(
+            "Unsupported KTX 2 texture (vkFormat {}).",
+            vk_format
+        )));
+    } else if identifier != IDENTIFIER {
+        return Err(Error::Other("Invalid KTX header.".to_string()));
+    }
+
+    if reader.try_read_uint32()? != LITTLE_ENDIAN {
+        return Err(Error::Other(
+            "Big-endian KTX textures are not supported.".to_string(),
+        ));
+    }
+
+    let data_type = reader.try_read_uint32()?;
+    let _type_size = reader.try_read_uint32()?;
+    let format = reader.try_read_uint32()?;
+    let internal_format = reader.try_read_uint32()?;
+    let _base_internal_format = reader.try_read_uint32()?;
+    let width = reader.try_read_uint32()?;
+    let height = reader.try_read_uint32()?;
+    // Depth, array elements, faces and mipmap levels. Only the first face of
+    // the first level is used.
+    reader.try_skip(16)?;
+    let key_value_size = reader.try_read_uint32()?;
+    reader.try_skip(key_value_size as usize)?;
+
+    let encoding = encoding(internal_format, format, data_type).ok_or_else(|| {
+        Error::UnknownPixel(format!(
+            "Unsupported KTX texture format (glInternalFormat 0x{:04X}).",
+            internal_format
+        ))
+    })?;
+
+    let level_size = reader.try_read_uint32()?;
+    let data = reader.try_slice(level_size as usize)?;
+
+    Ok(Texture {
+        width,
+        height,
+        encoding,
+        data,
+    })
+}
@@ -0,0 +1,271 @@
+//! Packing of images into `_tex.sc` files, the inverse of decoding them.
+//!
+//! Each image is encoded as a chunk of the given file type and pixel type,
+//! followed by the empty chunk of type `0` that marks the end of the file. The
+//! chunks are compressed with LZMA and prefixed with a version 1 header.
+//!
+//! [`read_tex_sprites`] reads back the images that were extracted from a
+//! file, to pack them after they are edited.
+
+use super::{bytes_per_pixel, tile_size, tiled_position};
+use crate::{error::Error, utils};
+use image::{imageops, Pixel, Rgba, RgbaImage};
+use md5::{Digest, Md5};
+use std::{convert::TryFrom, fs, path::Path};
+
+/// Reduces an 8-bit color channel to `bits` bits, rounding to the nearest
+/// value.
+fn reduce(value: u8, bits: u32) -> u16 {
+    let max = (1 << bits) - 1;
+    ((value as u32 * max + 127) / 255) as u16
+}
+
+/// Encodes a pixel as `pixel_type` and appends it to `data`.
+///
+/// This is the inverse of `convert_pixel`. Luminance types use the luminance
+/// of the pixel's color.
+fn encode_pixel(data: &mut Vec<u8>, pixel: Rgba<u8>, pixel_type: u8) -> Result<(), Error> {
+    let [r, g, b, a] = pixel.0;
+    let luminance = pixel.to_luma().0[0];
+
+    match pixel_type {
+        // RGB8888
+        0 | 1 => data.extend_from_slice(&pixel.0),
+        // RGB4444
+        2 => {
+            let pixel = reduce(r, 4) << 12 | reduce(g, 4) << 8 | reduce(b, 4) << 4 | reduce(a, 4);
+            data.extend_from_slice(&pixel.to_le_bytes());
+        }
+        // RGBA5551
+        3 => {
+            let pixel = reduce(r, 5) << 11
+                | reduce(g, 5) << 6
+                | reduce(b, 5) << 1
+                | if a >= 128 { 1 } else { 0 };
+            data.extend_from_slice(&pixel.to_le_bytes());
+        }
+        // RGB565
+        4 => {
+            let pixel = reduce(r, 5) << 11 | reduce(g, 6) << 5 | reduce(b, 5);
+            data.extend_from_slice(&pixel.to_le_bytes());
+        }
+        // LA44
+        5 => data.push((reduce(luminance, 4) << 4 | reduce(a, 4)) as u8),
+        // LA88
+        6 => data.extend_from_slice(&(u16::from(luminance) << 8 | u16::from(a)).to_le_bytes()),
+        10 => data.push(luminance),
+        _ => {
+            return Err(Error::UnknownPixel(format!(
+                "Pixel type {} can't be packed.",
+                pixel_type
+            )))
+        }
+    }
+
+    Ok(())
+}
+
+/// A sprite to be packed into a `_tex.sc` file by [`pack_tex`].
+///
+/// [`pack_tex`]: ./fn.pack_tex.html
+#[derive(Clone, Debug, PartialEq)]
+pub struct TexSprite {
+    /// The image of the sprite.
+    pub image: RgbaImage,
+    /// The file type of the sprite's chunk.
+    ///
+    /// `1` and `24` store the pixels in scanlines, `27` and `28` in 32x32
+    /// tiles.
+    pub file_type: u8,
+    /// The type of the sprite's pixels.
+    ///
+    /// One of `0, 1, 2, 3, 4, 5, 6, 10`. Block-compressed types can't be
+    /// packed.
+    pub sub_type: u8,
+}
+
+/// Packs `sprites` into compressed, raw `_tex.sc` file data.
+///
+/// This is the inverse of [`extract_tex`]: the sprites are stored in the given
+/// order, their pixels encoded as their `sub_type`, and the data is compressed
+/// with LZMA. The header holds the MD5 hash of the uncompressed data, like
+/// Supercell's files.
+///
+/// Pixel types with fewer bits per channel than the images lose precision.
+/// Packing the images returned by [`extract_tex`] with their original types
+/// gives back the same pixels.
+///
+/// ## Errors
+///
+/// If a sprite's `sub_type` can't be packed, [`Error::UnknownPixel`] is
+/// returned. If its `file_type` isn't one of `1, 24, 27, 28`, or its image is
+/// larger than 65535 pixels per side, [`Error::Other`] is returned.
+///
+/// [`extract_tex`]: ./fn.extract_tex.html
+/// [`Error::UnknownPixel`]: ./error/enum.Error.html#variant.UnknownPixel
+/// [`Error::Other`]: ./error/enum.Error.html#variant.Other
+pub fn pack_tex(sprites: &[TexSprite]) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::new();
+
+    for (index, sprite) in sprites.iter().enumerate() {
+        if ![1, 24, 27, 28].contains(&sprite.file_type) {
+            return Err(Error::Other(format!(
+                "Sprite {} has file type {}, which can't be packed.",
+                index, sprite.file_type
+            )));
+        }
+
+        let (width, height) = sprite.image.dimensions();
+        if width > u16::MAX as u32 || height > u16::MAX as u32 {
+            return Err(Error::Other(format!(
+                "Sprite {} ({}x{}) is larger than 65535 pixels per side.",
+                index, width, height
+            )));
+        }
+
+        // Unknown pixel types fail on the first pixel.
+        let pixel_size = bytes_per_pixel(sprite.sub_type).unwrap_or(0);
+        let mut pixels = Vec::with_capacity((width * height) as usize * pixel_size);
+        for i in 0..width * height {
+            let (x, y) = match tile_size(sprite.file_type) {
+                Some(size) => tiled_position(i, width, height, size.min(width.max(height))),
+                None => (i % width, i / width),
+            };
+            encode_pixel(&mut pixels, *sprite.image.get_pixel(x, y), sprite.sub_type)?;
+        }
+
+        let size = u32::try_from(pixels.len() + 5)
+            .map_err(|_| Error::Other(format!("Sprite {} is too large to be packed.", index)))?;
+
+        data.push(sprite.file_type);
+        data.extend_from_slice(&size.to_le_bytes());
+        data.push(sprite.sub_type);
+        data.extend_from_slice(&(width as u16).to_le_bytes());
+        data.extend_from_slice(&(height as u16).to_le_bytes());
+        data.extend(pixels);
+    }
+
+    // The end of the file is marked by an empty chunk of type `0`.
+    data.extend_from_slice(&[0; 5]);
+
+    let hash = Md5::digest(&data);
+
+    let mut output = b"SC".to_vec();
+    output.extend_from_slice(&1u32.to_be_bytes());
+    output.extend_from_slice(&(hash.len() as u32).to_be_bytes());
+    output.extend_from_slice(&hash);
+    output.extend(utils::compress(&data)?);
+
+    Ok(output)
+}
+
+/// Returns the name of the `_tex.sc` file whose first sprite was saved as
+/// `stem`, and the width of the sprites' indices if they are numbered.
+///
+/// `name_tex` is the first sprite of a file with a single sprite, or with
+/// sprites named with underscores, and `name_tex_0` (or `name_tex_00` and so
+/// on) that of a file with numbered sprites.
+fn first_sprite(stem: &str) -> Option<(&str, Option<usize>)> {
+    if stem.ends_with("_tex") {
+        return Some((stem, None));
+    }
+
+    let (name, index) = stem.rsplit_once('_')?;
+    if name.ends_with("_tex") && !index.is_empty() && index.bytes().all(|b| b == b'0') {
+        Some((name, Some(index.len())))
+    } else {
+        None
+    }
+}
+
+/// Reads the PNG images of the sprites of a `_tex.sc` file, as saved by
+/// [`process_tex_with`], to be packed with [`pack_tex`].
+///
+/// `path` must be the image of the file's first sprite, like `ui_tex.png` or
+/// `ui_tex_0.png`. The images of the other sprites are found next to it,
+/// named like [`SpriteNaming`] names them, until one is missing. The name of
+/// the `_tex.sc` file, like `ui_tex.sc`, is returned with the sprites, or
+/// `None` if `path` isn't the image of a first sprite.
+///
+/// The file and pixel types of each sprite are read from its metadata JSON
+/// file, saved with [`TexOptions::metadata`], and cropped sprites are placed
+/// back in their original images. Sprites without metadata use `file_type`
+/// and `sub_type`.
+///
+/// ## Errors
+///
+/// [`Error::IoError`] is returned if an image can't be read, and
+/// [`Error::Other`] if a metadata file isn't valid.
+///
+/// [`process_tex_with`]: ./fn.process_tex_with.html
+/// [`pack_tex`]: ./fn.pack_tex.html
+/// [`SpriteNaming`]: ./enum.SpriteNaming.html
+/// [`TexOptions::metadata`]: ./struct.TexOptions.html#structfield.metadata
+/// [`Error::IoError`]: ./error/enum.Error.html#variant.IoError
+/// [`Error::Other`]: ./error/enum.Error.html#variant.Other
+pub fn read_tex_sprites(
+    path: &Path,
+    file_type: u8,
+    sub_type: u8,
+) -> Result<Option<(String, Vec<TexSprite>)>, Error> {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let (name, width) = match first_sprite(stem) {
+        Some(first) if path.extension().is_some_and(|e| e == "png") => first,
+        _ => return Ok(None),
+    };
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut sprites = Vec::new();
+    for index in 0.. {
+        let sprite_name = match width {
+            Some(width) => format!("{}_{:0width$}", name, index, width = width),
+            None => format!("{}{}", name, "_".repeat(index)),
+        };
+        let image_path = dir.join(format!("{}.png", sprite_name));
+        if !image_path.exists() {
+            break;
+        }
+
+        let mut image = image::open(&image_path)
+            .map_err(|_| Error::IoError(format!("Failed to read image {}!", image_path.display())))?
+            .to_rgba8();
+        let mut sprite_types = (file_type, sub_type);
+
+        let metadata_path = dir.join(format!("{}.json", sprite_name));
+        if let Ok(json) = fs::read_to_string(&metadata_path) {
+            let invalid = || Error::Other(format!("{} is not valid.", metadata_path.display()));
+            let metadata: serde_json::Value = serde_json::from_str(&json).map_err(|_| invalid())?;
+            let field = |key: &str| metadata[key].as_u64().ok_or_else(invalid);
+
+            sprite_types = (
+                u8::try_from(field("file_type")?).map_err(|_| invalid())?,
+                u8::try_from(field("sub_type")?).map_err(|_| invalid())?,
+            );
+
+            let crop = &metadata["crop"];
+            if !crop.is_null() {
+                let field = |key: &str| {
+                    crop[key]
+                        .as_u64()
+                        .and_then(|v| u32::try_from(v).ok())
+                        .ok_or_else(invalid)
+                };
+                let mut original =
+                    RgbaImage::new(field("original_width")?, field("original_height")?);
+                imageops::overlay(&mut original, &image, field("x")?, field("y")?);
+                image = original;
+            }
+        }
+
+        sprites.push(TexSprite {
+            image,
+            file_type: sprite_types.0,
+            sub_type: sprite_types.1,
+        });
+    }
+
+    Ok(Some((format!("{}.sc", name), sprites)))
+}
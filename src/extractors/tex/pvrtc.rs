@@ -0,0 +1,215 @@
+//! Decoding of PVRTC 4bpp compressed pixel data.
+//!
+//! A PVRTC 4bpp block is 8 bytes long and covers 4x4 pixels: 32 bits of
+//! 2-bit modulation values followed by a 32-bit color word holding two low
+//! resolution colors. Unlike ETC or ASTC, a pixel's colors are interpolated
+//! from the four blocks nearest to it, so the image has to be decoded as a
+//! whole.
+//!
+//! Blocks are stored in Morton (twiddled) order instead of scanlines, and the
+//! texture is padded to at least 8x8 pixels. The image wraps around at the
+//! edges when interpolating.
+
+/// Modulation weights (out of 8) of the standard modulation mode.
+const MODULATION_WEIGHTS: [i32; 4] = [0, 3, 5, 8];
+
+/// A single PVRTC block, with both of its colors expanded to 5-bit RGB and
+/// 4-bit alpha.
+struct Block {
+    modulation: u32,
+    punch_through: bool,
+    color_a: [i32; 4],
+    color_b: [i32; 4],
+}
+
+/// Returns the number of blocks in each dimension for a `width` by `height`
+/// texture.
+fn block_counts(width: u32, height: u32) -> (u32, u32) {
+    (width.max(8).div_ceil(4), height.max(8).div_ceil(4))
+}
+
+/// Returns the number of bytes of PVRTC 4bpp data in a `width` by `height`
+/// texture.
+pub(crate) fn data_size(width: u32, height: u32) -> usize {
+    let (blocks_x, blocks_y) = block_counts(width, height);
+    (blocks_x * blocks_y * 8) as usize
+}
+
+/// Returns the position of block (`x`, `y`) in the twiddled block order.
+///
+/// The bits of `x` and `y` are interleaved, `y` first (in the lower bit), as
+/// long as both dimensions have bits left. The remaining bits of the longer
+/// dimension are appended as they are.
+fn twiddle(x: u32, y: u32, blocks_x: u32, blocks_y: u32) -> usize {
+    let min_dimension = blocks_x.min(blocks_y);
+
+    let mut twiddled = 0;
+    let mut bit = 1;
+    let mut shift = 0;
+    while bit < min_dimension {
+        if y & bit != 0 {
+            twiddled |= bit << shift;
+        }
+        if x & bit != 0 {
+            twiddled |= bit << (shift + 1);
+        }
+        bit <<= 1;
+        shift += 1;
+    }
+
+    let rest = if blocks_x < blocks_y { y } else { x };
+    (twiddled | ((rest >> shift) << (2 * shift))) as usize
+}
+
+/// Expands color A (the low half of the color word).
+///
+/// Opaque colors are RGB554, translucent colors are ARGB3443.
+fn color_a(word: u32) -> [i32; 4] {
+    let word = word as i32;
+    if word & 0x8000 != 0 {
+        [
+            (word >> 10) & 0x1F,
+            (word >> 5) & 0x1F,
+            (word & 0x1E) | ((word >> 4) & 0x1),
+            0xF,
+        ]
+    } else {
+        [
+            ((word >> 7) & 0x1E) | ((word >> 11) & 0x1),
+            ((word >> 3) & 0x1E) | ((word >> 7) & 0x1),
+            ((word << 1) & 0x1C) | ((word >> 2) & 0x3),
+            (word >> 11) & 0xE,
+        ]
+    }
+}
+
+/// Expands color B (the high half of the color word).
+///
+/// Opaque colors are RGB555, translucent colors are ARGB3444.
+fn color_b(word: u32) -> [i32; 4] {
+    let word = (word >> 16) as i32;
+    if word & 0x8000 != 0 {
+        [(word >> 10) & 0x1F, (word >> 5) & 0x1F, word & 0x1F, 0xF]
+    } else {
+        [
+            ((word >> 7) & 0x1E) | ((word >> 11) & 0x1),
+            ((word >> 3) & 0x1E) | ((word >> 7) & 0x1),
+            ((word << 1) & 0x1E) | ((word >> 3) & 0x1),
+            (word >> 11) & 0xE,
+        ]
+    }
+}
+
+/// Bilinearly interpolates a color between the centers of four neighboring
+/// blocks and expands it to 8 bits per channel.
+///
+/// `x` and `y` are the offsets (`0..4`) from the center of the top-left block.
+fn interpolate(colors: [[i32; 4]; 4], x: i32, y: i32) -> [i32; 4] {
+    let mut result = [0; 4];
+    for (i, channel) in result.iter_mut().enumerate() {
+        // Scaled by 16.
+        let value = colors[0][i] * (4 - x) * (4 - y)
+            + colors[1][i] * x * (4 - y)
+            + colors[2][i] * (4 - x) * y
+            + colors[3][i] * x * y;
+
+        *channel = if i == 3 {
+            (value >> 4) + value
+        } else {
+            (value >> 6) + (value >> 1)
+        };
+    }
+    result
+}
+
+/// Decodes PVRTC 4bpp `data` and returns the pixels of the `width` by
+/// `height` image in row-major order.
+///
+/// `data` must be [`data_size`] bytes long.
+///
+/// [`data_size`]: ./fn.data_size.html
+pub(crate) fn decode_4bpp(data: &[u8], width: u32, height: u32) -> Vec<[u8; 4]> {
+    let (blocks_x, blocks_y) = block_counts(width, height);
+
+    let mut blocks = Vec::with_capacity((blocks_x * blocks_y) as usize);
+    for y in 0..blocks_y {
+        for x in 0..blocks_x {
+            let offset = twiddle(x, y, blocks_x, blocks_y) * 8;
+            let word =
+                |i: usize| u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+            let color = word(offset + 4);
+
+            blocks.push(Block {
+                modulation: word(offset),
+                punch_through: color & 0x1 == 1,
+                color_a: color_a(color),
+                color_b: color_b(color),
+            });
+        }
+    }
+
+    let (padded_width, padded_height) = (blocks_x * 4, blocks_y * 4);
+
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            // Block colors sit at the centers of their blocks.
+            let sample_x = (x + padded_width - 2) % padded_width;
+            let sample_y = (y + padded_height - 2) % padded_height;
+            let (left, top) = (sample_x / 4, sample_y / 4);
+            let (right, bottom) = ((left + 1) % blocks_x, (top + 1) % blocks_y);
+
+            let neighbors = [
+                &blocks[(top * blocks_x + left) as usize],
+                &blocks[(top * blocks_x + right) as usize],
+                &blocks[(bottom * blocks_x + left) as usize],
+                &blocks[(bottom * blocks_x + right) as usize],
+            ];
+            let (offset_x, offset_y) = ((sample_x % 4) as i32, (sample_y % 4) as i32);
+            let color_a = interpolate(
+                [
+                    neighbors[0].color_a,
+                    neighbors[1].color_a,
+                    neighbors[2].color_a,
+                    neighbors[3].color_a,
+                ],
+                offset_x,
+                offset_y,
+            );
+            let color_b = interpolate(
+                [
+                    neighbors[0].color_b,
+                    neighbors[1].color_b,
+                    neighbors[2].color_b,
+                    neighbors[3].color_b,
+                ],
+                offset_x,
+                offset_y,
+            );
+
+            let block = &blocks[((y / 4) * blocks_x + x / 4) as usize];
+            let index = (y % 4) * 4 + x % 4;
+            let value = ((block.modulation >> (2 * index)) & 0x3) as usize;
+
+            let (weight, punch_through) = match (block.punch_through, value) {
+                (false, _) => (MODULATION_WEIGHTS[value], false),
+                (true, 0) => (0, false),
+                (true, 1) => (4, false),
+                (true, 2) => (4, true),
+                (true, _) => (8, false),
+            };
+
+            let mut pixel = [0; 4];
+            for (i, channel) in pixel.iter_mut().enumerate() {
+                *channel = ((color_a[i] * (8 - weight) + color_b[i] * weight) / 8) as u8;
+            }
+            if punch_through {
+                pixel[3] = 0;
+            }
+
+            pixels.push(pixel);
+        }
+    }
+
+    pixels
+}
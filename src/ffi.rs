@@ -0,0 +1,129 @@
+//! C-compatible functions for using `sc_extract` from other languages.
+//!
+//! This module is only built with the `ffi` feature. The library can be built
+//! as a shared library with
+//! `cargo rustc --release --lib --features ffi --crate-type cdylib`.
+//!
+//! ## Memory ownership
+//!
+//! The caller owns all memory passed to these functions. It is only read
+//! during the call, and no pointer to it is kept afterwards.
+//!
+//! The message returned by [`sce_last_error`] is owned by `sc_extract`. It
+//! must not be freed, and is valid until the next call to a function of this
+//! module on the same thread. Copy it to keep it longer.
+//!
+//! [`sce_last_error`]: ./fn.sce_last_error.html
+
+use crate::process_tex;
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    ptr, slice,
+};
+
+/// The call was successful.
+pub const SCE_OK: c_int = 0;
+/// A pointer was null, or a string wasn't valid UTF-8.
+pub const SCE_INVALID_ARGUMENT: c_int = 1;
+/// The file couldn't be processed. See [`sce_last_error`] for why.
+///
+/// [`sce_last_error`]: ./fn.sce_last_error.html
+pub const SCE_ERROR: c_int = 2;
+/// `sc_extract` panicked while processing the file. This is a bug.
+pub const SCE_PANIC: c_int = 3;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Records `message` as the last error of this thread and returns `status`.
+fn fail(status: c_int, message: String) -> c_int {
+    // C strings can't hold null bytes.
+    let message = CString::new(message.replace('\0', " "))
+        .expect("Expected the message to have no null bytes.");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+
+    status
+}
+
+/// Reads a UTF-8 C string, or returns `None` if it is null or isn't UTF-8.
+///
+/// ## Safety
+///
+/// `string` must be null or point to a null-terminated string.
+unsafe fn read_str<'a>(string: *const c_char) -> Option<&'a str> {
+    if string.is_null() {
+        None
+    } else {
+        CStr::from_ptr(string).to_str().ok()
+    }
+}
+
+/// Processes the compressed, raw `_tex.sc` file data of `length` bytes at
+/// `data` and saves its images in `out_dir`, like [`process_tex`].
+///
+/// `file_name` is the name of the file, like `ui_tex.sc`, which the images
+/// are named after. The status is returned as one of the `SCE_*` codes. If
+/// it isn't [`SCE_OK`], [`sce_last_error`] returns why.
+///
+/// ## Safety
+///
+/// `data` must point to `length` readable bytes. `file_name` and `out_dir`
+/// must point to null-terminated strings. All three are only read during the
+/// call.
+///
+/// [`process_tex`]: ../fn.process_tex.html
+/// [`SCE_OK`]: ./constant.SCE_OK.html
+/// [`sce_last_error`]: ./fn.sce_last_error.html
+#[no_mangle]
+pub unsafe extern "C" fn sce_process_tex(
+    data: *const u8,
+    length: usize,
+    file_name: *const c_char,
+    out_dir: *const c_char,
+) -> c_int {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+
+    if data.is_null() {
+        return fail(SCE_INVALID_ARGUMENT, "`data` is null.".to_string());
+    }
+    let (file_name, out_dir) = match (read_str(file_name), read_str(out_dir)) {
+        (Some(file_name), Some(out_dir)) => (file_name, out_dir),
+        _ => {
+            return fail(
+                SCE_INVALID_ARGUMENT,
+                "`file_name` and `out_dir` must be UTF-8 strings.".to_string(),
+            )
+        }
+    };
+    let data = slice::from_raw_parts(data, length);
+
+    // Panics must not unwind into the caller.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        process_tex(data, file_name, Path::new(out_dir), true)
+    }));
+
+    match result {
+        Ok(Ok(_)) => SCE_OK,
+        Ok(Err(e)) => fail(SCE_ERROR, e.to_string()),
+        Err(_) => fail(SCE_PANIC, format!("Processing {} panicked.", file_name)),
+    }
+}
+
+/// Returns the message of the last error on this thread, or null if the last
+/// call was successful.
+///
+/// The message is owned by `sc_extract` and must not be freed. It is valid
+/// until the next call to a function of this module on the same thread.
+#[no_mangle]
+pub extern "C" fn sce_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
@@ -0,0 +1,326 @@
+use crate::error::Error;
+use image::{
+    codecs::jpeg::JpegEncoder, ColorType, DynamicImage, GenericImageView, ImageFormat, Rgb,
+    RgbImage, RgbaImage,
+};
+use std::{
+    borrow::Cow,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+/// Image format in which the extracted images are saved.
+///
+/// The default format is PNG.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    /// Lossless PNG.
+    #[default]
+    Png,
+    /// WebP, encoded using `libwebp`.
+    ///
+    /// `quality` ranges from `0.0` to `100.0` and is only used for lossy
+    /// encoding.
+    WebP { lossless: bool, quality: f32 },
+    /// Lossy JPEG.
+    ///
+    /// `quality` ranges from `1` to `100`. JPEG has no alpha channel, so
+    /// transparent pixels are blended onto the `background` color. Opaque
+    /// pixels are not changed.
+    Jpeg { quality: u8, background: [u8; 3] },
+    /// Uncompressed TIFF, with an alpha channel.
+    Tiff,
+    /// DDS with uncompressed, 32-bit BGRA pixels.
+    Dds,
+    /// Raw, uncompressed RGBA pixels, saved with the `.rgba` extension.
+    ///
+    /// The file starts with the width and height of the image, as
+    /// little-endian `u32`s, followed by 4 bytes for each pixel, in rows from
+    /// top to bottom.
+    Raw,
+    /// Uncompressed BMP.
+    ///
+    /// Grayscale-alpha images are saved as RGBA, since BMP files can't hold
+    /// them.
+    Bmp,
+    /// Uncompressed TGA.
+    Tga,
+}
+
+/// Blends `img` onto a `background` color, which removes its alpha channel.
+///
+/// Opaque pixels are not changed.
+pub(crate) fn blend(img: &RgbaImage, background: [u8; 3]) -> RgbImage {
+    RgbImage::from_fn(img.width(), img.height(), |x, y| {
+        let pixel = img.get_pixel(x, y);
+        let alpha = pixel[3] as u32;
+        let mut blended = [0; 3];
+        for (i, channel) in blended.iter_mut().enumerate() {
+            *channel = ((pixel[i] as u32 * alpha + background[i] as u32 * (255 - alpha) + 127)
+                / 255) as u8;
+        }
+        Rgb(blended)
+    })
+}
+
+/// How hard PNG images are compressed.
+///
+/// Better compression makes smaller files, but takes longer.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PngCompression {
+    /// The fastest compression, without filtering. The files are a lot
+    /// larger.
+    Fast,
+    /// Fast compression with the `Sub` filter.
+    #[default]
+    Default,
+    /// The best compression, with the `Paeth` filter.
+    Best,
+}
+
+impl PngCompression {
+    /// Returns the compression and filter types of the PNG encoder.
+    fn encoder_settings(self) -> (png::Compression, png::FilterType) {
+        match self {
+            Self::Fast => (png::Compression::Fast, png::FilterType::NoFilter),
+            Self::Default => (png::Compression::Fast, png::FilterType::Sub),
+            Self::Best => (png::Compression::Best, png::FilterType::Paeth),
+        }
+    }
+}
+
+/// `DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PITCH | DDSD_PIXELFORMAT`.
+const DDS_FLAGS: u32 = 0x1 | 0x2 | 0x4 | 0x8 | 0x1000;
+
+/// `DDPF_ALPHAPIXELS | DDPF_RGB`.
+const DDS_PIXEL_FLAGS: u32 = 0x1 | 0x40;
+
+/// `DDSCAPS_TEXTURE`.
+const DDS_CAPS: u32 = 0x1000;
+
+/// Encodes `img` as a DDS file with uncompressed BGRA pixels.
+///
+/// The file is the `DDS ` magic, followed by a 124-byte header and the pixels
+/// in rows from top to bottom.
+fn encode_dds(img: &RgbaImage) -> Vec<u8> {
+    let (width, height) = img.dimensions();
+
+    let mut header = vec![
+        124,
+        DDS_FLAGS,
+        height,
+        width,
+        // The pitch, i.e. the number of bytes in a row.
+        width * 4,
+        // Depth and mipmap count.
+        0,
+        0,
+    ];
+    // Reserved.
+    header.extend_from_slice(&[0; 11]);
+    // The pixel format: its size, flags, FourCC, bits per pixel and the
+    // red, green, blue and alpha masks.
+    header.extend_from_slice(&[
+        32,
+        DDS_PIXEL_FLAGS,
+        0,
+        32,
+        0x00FF_0000,
+        0x0000_FF00,
+        0x0000_00FF,
+        0xFF00_0000,
+    ]);
+    // Caps, caps 2 to 4 and a reserved field.
+    header.extend_from_slice(&[DDS_CAPS, 0, 0, 0, 0]);
+
+    let mut data = b"DDS ".to_vec();
+    for field in header {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    for pixel in img.pixels() {
+        let [r, g, b, a] = pixel.0;
+        data.extend_from_slice(&[b, g, r, a]);
+    }
+
+    data
+}
+
+/// Encodes `img` as a PNG image, holding `text` as pairs of keywords and
+/// values, and writes it to `writer`.
+///
+/// Images are written with their own color type if PNG supports it.
+/// Otherwise, they are converted to RGBA first. ASCII values are written in
+/// `tEXt` chunks, and others in UTF-8 `iTXt` chunks.
+pub(crate) fn write_png<W: Write>(
+    img: &DynamicImage,
+    writer: W,
+    png_compression: PngCompression,
+    text: &[(&str, String)],
+) -> Result<(), Error> {
+    let img = match img {
+        DynamicImage::ImageLuma8(_)
+        | DynamicImage::ImageLumaA8(_)
+        | DynamicImage::ImageRgb8(_)
+        | DynamicImage::ImageRgba8(_) => Cow::Borrowed(img),
+        _ => Cow::Owned(DynamicImage::ImageRgba8(img.to_rgba8())),
+    };
+    let color = match img.color() {
+        ColorType::L8 => png::ColorType::Grayscale,
+        ColorType::La8 => png::ColorType::GrayscaleAlpha,
+        ColorType::Rgb8 => png::ColorType::Rgb,
+        _ => png::ColorType::Rgba,
+    };
+    let png_error = |e: png::EncodingError| Error::IoError(e.to_string());
+
+    let mut encoder = png::Encoder::new(writer, img.width(), img.height());
+    encoder.set_color(color);
+    encoder.set_depth(png::BitDepth::Eight);
+    let (compression, filter) = png_compression.encoder_settings();
+    encoder.set_compression(compression);
+    encoder.set_filter(filter);
+
+    for (keyword, value) in text {
+        if value.is_ascii() {
+            encoder.add_text_chunk(keyword.to_string(), value.clone())
+        } else {
+            encoder.add_itxt_chunk(keyword.to_string(), value.clone())
+        }
+        .map_err(png_error)?;
+    }
+
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    writer.write_image_data(img.as_bytes()).map_err(png_error)?;
+    writer.finish().map_err(png_error)
+}
+
+impl OutputFormat {
+    /// Returns the file extension of the format, without the leading dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::WebP { .. } => "webp",
+            Self::Jpeg { .. } => "jpg",
+            Self::Tiff => "tiff",
+            Self::Dds => "dds",
+            Self::Raw => "rgba",
+            Self::Bmp => "bmp",
+            Self::Tga => "tga",
+        }
+    }
+
+    /// Encodes `img` in the format and saves it at `path`.
+    ///
+    /// Images are saved with their own color type if the format supports it.
+    /// Otherwise, they are converted to RGBA first. `png_compression` is only
+    /// used for PNG images.
+    #[cfg(feature = "fs")]
+    pub(crate) fn save(
+        &self,
+        img: &DynamicImage,
+        path: &Path,
+        png_compression: PngCompression,
+    ) -> Result<(), Error> {
+        self.save_with_text(img, path, png_compression, &[])
+    }
+
+    /// Like [`save`], but PNG images also hold `text` as pairs of keywords and
+    /// values, see [`write_png`]. Other formats ignore it.
+    ///
+    /// [`write_png`]: ./fn.write_png.html
+    /// [`save`]: #method.save
+    #[cfg(feature = "fs")]
+    pub(crate) fn save_with_text(
+        &self,
+        img: &DynamicImage,
+        path: &Path,
+        png_compression: PngCompression,
+        text: &[(&str, String)],
+    ) -> Result<(), Error> {
+        match *self {
+            Self::Png => write_png(
+                img,
+                BufWriter::new(File::create(path)?),
+                png_compression,
+                text,
+            ),
+            #[cfg(feature = "webp")]
+            Self::WebP { lossless, quality } => {
+                let img = match img {
+                    DynamicImage::ImageRgba8(img) => Cow::Borrowed(img),
+                    _ => Cow::Owned(img.to_rgba8()),
+                };
+                let encoder = webp::Encoder::from_rgba(img.as_raw(), img.width(), img.height());
+                let data = if lossless {
+                    encoder.encode_lossless()
+                } else {
+                    encoder.encode(quality)
+                };
+
+                Ok(fs::write(path, &*data)?)
+            }
+            #[cfg(not(feature = "webp"))]
+            Self::WebP { .. } => Err(Error::Other(
+                "WebP images can't be saved; enable the `webp` feature of sc_extract.".to_string(),
+            )),
+            Self::Jpeg {
+                quality,
+                background,
+            } => {
+                let img = blend(&img.to_rgba8(), background);
+
+                let mut file = BufWriter::new(File::create(path)?);
+                JpegEncoder::new_with_quality(&mut file, quality.clamp(1, 100))
+                    .encode(img.as_raw(), img.width(), img.height(), ColorType::Rgb8)
+                    .map_err(|e| Error::IoError(e.to_string()))
+            }
+            Self::Tiff => {
+                // TIFF images can't hold luminance with alpha.
+                let img = match img {
+                    DynamicImage::ImageLuma8(_)
+                    | DynamicImage::ImageRgb8(_)
+                    | DynamicImage::ImageRgba8(_) => Cow::Borrowed(img),
+                    _ => Cow::Owned(DynamicImage::ImageRgba8(img.to_rgba8())),
+                };
+
+                img.save_with_format(path, ImageFormat::Tiff)
+                    .map_err(|e| Error::IoError(e.to_string()))
+            }
+            Self::Dds => {
+                let img = match img {
+                    DynamicImage::ImageRgba8(img) => Cow::Borrowed(img),
+                    _ => Cow::Owned(img.to_rgba8()),
+                };
+
+                Ok(fs::write(path, encode_dds(&img))?)
+            }
+            Self::Raw => {
+                let img = match img {
+                    DynamicImage::ImageRgba8(img) => Cow::Borrowed(img),
+                    _ => Cow::Owned(img.to_rgba8()),
+                };
+
+                let mut file = BufWriter::new(File::create(path)?);
+                file.write_all(&img.width().to_le_bytes())?;
+                file.write_all(&img.height().to_le_bytes())?;
+                file.write_all(img.as_raw())?;
+
+                Ok(file.flush()?)
+            }
+            Self::Bmp => {
+                let img = match img {
+                    DynamicImage::ImageLumaA8(_) => {
+                        Cow::Owned(DynamicImage::ImageRgba8(img.to_rgba8()))
+                    }
+                    _ => Cow::Borrowed(img),
+                };
+
+                img.save_with_format(path, ImageFormat::Bmp)
+                    .map_err(|e| Error::IoError(e.to_string()))
+            }
+            Self::Tga => img
+                .save_with_format(path, ImageFormat::Tga)
+                .map_err(|e| Error::IoError(e.to_string())),
+        }
+    }
+}
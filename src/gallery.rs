@@ -0,0 +1,139 @@
+//! A self-contained HTML page that shows extracted sprites.
+
+use crate::{error::Error, extractors::tex::TexExtraction};
+use std::{fmt::Write, fs, path::Path};
+
+/// Returns the name of a `_tex.sc` pixel type (sub-type).
+fn pixel_type_name(sub_type: u8) -> &'static str {
+    match sub_type {
+        0 | 1 => "RGBA8888",
+        2 => "RGBA4444",
+        3 => "RGBA5551",
+        4 => "RGB565",
+        5 => "LA44",
+        6 => "LA88",
+        10 => "L8",
+        11 => "ETC1",
+        12 => "ETC2 RGB",
+        13 => "ETC2 RGBA",
+        14 => "ASTC",
+        15 => "PVRTC",
+        16 => "BC1",
+        17 => "BC3",
+        18 => "BC3 premultiplied",
+        _ => "unknown",
+    }
+}
+
+/// Escapes `text` to be placed in HTML text or a quoted attribute.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Returns the URL of `path`, relative to `dir` if it is inside it.
+///
+/// Bytes other than letters, digits and `-_.~/` are percent-encoded.
+fn url(path: &Path, dir: &Path) -> String {
+    let relative = path.strip_prefix(dir).unwrap_or(path);
+    let path = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let mut url = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~/".contains(&byte) {
+            url.push(byte as char);
+        } else {
+            let _ = write!(url, "%{:02X}", byte);
+        }
+    }
+
+    url
+}
+
+const STYLE: &str = "body{font-family:sans-serif;margin:0;padding:1em;background:#222;color:#eee}\
+h2{font-size:1.1em;margin:1.5em 0 .5em}\
+.grid{display:grid;grid-template-columns:repeat(auto-fill,minmax(160px,1fr));gap:8px}\
+figure{margin:0;padding:8px;background:#333;border-radius:4px;text-align:center}\
+img{max-width:100%;max-height:160px;height:auto;object-fit:contain;\
+background:repeating-conic-gradient(#555 0 25%,#444 0 50%) 0 0/16px 16px}\
+figcaption{font-size:.8em;margin-top:4px;word-break:break-all}\
+.details{color:#aaa}";
+
+/// Saves an HTML page at `path` that shows the sprites of every file in
+/// `files`, grouped by the file.
+///
+/// `files` holds the name of each `_tex.sc` file and what was extracted from
+/// it, in the order they are shown. Each sprite is shown with its name,
+/// dimensions and pixel type. The page has no external assets: the images are
+/// linked relative to the page's directory and loaded lazily, as they are
+/// scrolled into view.
+///
+/// ## Errors
+///
+/// [`Error::IoError`] is returned if the page can't be saved.
+///
+/// [`Error::IoError`]: ./error/enum.Error.html#variant.IoError
+pub fn save_html_index(files: &[(String, TexExtraction)], path: &Path) -> Result<(), Error> {
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>Extracted sprites</title>\n");
+    let _ = writeln!(html, "<style>{}</style>", STYLE);
+    html.push_str("</head>\n<body>\n<h1>Extracted sprites</h1>\n");
+
+    for (file_name, extraction) in files {
+        let _ = writeln!(
+            html,
+            "<section>\n<h2>{} ({} sprite(s))</h2>\n<div class=\"grid\">",
+            escape(file_name),
+            extraction.sprites.len()
+        );
+
+        for sprite in &extraction.sprites {
+            let metadata = &sprite.metadata;
+            let name = sprite
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            let _ = writeln!(
+                html,
+                "<figure><img src=\"{}\" alt=\"{}\" width=\"{}\" height=\"{}\" loading=\"lazy\">\
+                 <figcaption>{}<br><span class=\"details\">{}x{}, {} ({})</span></figcaption></figure>",
+                escape(&url(&sprite.path, dir)),
+                escape(&name),
+                metadata.width,
+                metadata.height,
+                escape(&name),
+                metadata.width,
+                metadata.height,
+                pixel_type_name(metadata.sub_type),
+                metadata.sub_type
+            );
+        }
+
+        html.push_str("</div>\n</section>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+
+    fs::write(path, html)?;
+
+    Ok(())
+}
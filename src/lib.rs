@@ -1,8 +1,45 @@
 //! Library to extract graphics and decode csv files from Supercell's game files.
 //!
-//! The library exposes three high-level functions, [`process_sc`],
-//! [`process_tex`] and [`process_csv`], to process extracted `sc`, `_tex.sc`
-//! and `.csv` files respectively.
+//! The library exposes four high-level functions, [`process_sc`],
+//! [`process_tex`], [`process_sctx`] and [`process_csv`], to process extracted
+//! `sc`, `_tex.sc`, `.sctx` and `.csv` files respectively. Chunked `_tex.sc2`
+//! and `.csv.sc2` files are processed like `_tex.sc` and `.csv` files.
+//!
+//! [`extract_tex`] can be used to get the decoded `_tex.sc` images in memory
+//! instead of saving them, [`extract_tex_to_pngs`] to get them encoded as PNG
+//! files, and [`process_tex_with`] to save them
+//! differently, e.g. in an [`OutputFormat`] other than PNG, with
+//! [`TexOptions`] built like `TexOptions::new().crop(true)`. [`decompress`] decompresses raw LZMA,
+//! LZHAM, zstd, xz or LZ4 data on its own, and [`detect_codec`] tells which of them
+//! data is compressed with. [`decompress_with_limit`] caps the size of the
+//! output. [`decompress_sc`] strips the header of `_tex.sc`
+//! data before decompressing it, and [`read_sc_header`] only reads the header.
+//! [`process_sc_with_options`] can name the
+//! sprites cut from extracted `sc` files after their exports, and
+//! [`process_csv_with_options`] can save `.csv` files as JSON.
+//!
+//! [`decode_pixel`] decodes a single `_tex.sc` pixel from bytes, for other
+//! containers with the same pixel types, and [`Reader`] reads the fields of
+//! such containers from memory or from a file.
+//!
+//! [`pack_tex`] packs images back into a `_tex.sc` file, reading extracted
+//! images with [`read_tex_sprites`], [`pack_csv`] packs edited `.csv` files,
+//! and [`compress`] compresses data the way [`decompress`] expects it.
+//! [`save_contact_sheets`] tiles thumbnails of extracted images into a few
+//! large images, and [`save_html_index`] shows them on an HTML page.
+//! [`process_dir`] processes every file in a directory and returns a
+//! [`Summary`] of what succeeded and what failed, and
+//! [`process_dir_with_options`] can leave out lower resolution variants.
+//!
+//! Progress, skipped sprites and warnings are reported through the [`log`]
+//! crate, so nothing is printed unless the application sets up a logger.
+//!
+//! The functions that write files need the default `fs` feature. With the
+//! `wasm` feature, and without the default features, the library builds for
+//! WebAssembly, like `wasm32-unknown-unknown`, where [`extract_tex`] and
+//! [`extract_tex_to_pngs`] return the sprites instead. zstd data and WebP
+//! images need the C libraries of the default `zstd` and `webp` features,
+//! which aren't built then.
 //!
 //! This library is simply intended to get high quality graphics and data from
 //! the files. It is in no way an attempt to:
@@ -13,13 +50,96 @@
 //!
 //! [`process_sc`]: ./fn.process_sc.html
 //! [`process_tex`]: ./fn.process_tex.html
+//! [`process_sctx`]: ./fn.process_sctx.html
 //! [`process_csv`]: ./fn.process_csv.html
+//! [`extract_tex`]: ./fn.extract_tex.html
+//! [`extract_tex_to_pngs`]: ./fn.extract_tex_to_pngs.html
+//! [`process_tex_with`]: ./fn.process_tex_with.html
+//! [`TexOptions`]: ./struct.TexOptions.html
+//! [`OutputFormat`]: ./enum.OutputFormat.html
+//! [`decompress`]: ./fn.decompress.html
+//! [`detect_codec`]: ./fn.detect_codec.html
+//! [`decompress_with_limit`]: ./fn.decompress_with_limit.html
+//! [`decompress_sc`]: ./fn.decompress_sc.html
+//! [`read_sc_header`]: ./fn.read_sc_header.html
+//! [`process_sc_with_options`]: ./fn.process_sc_with_options.html
+//! [`process_csv_with_options`]: ./fn.process_csv_with_options.html
+//! [`decode_pixel`]: ./fn.decode_pixel.html
+//! [`Reader`]: ./struct.Reader.html
+//! [`pack_tex`]: ./fn.pack_tex.html
+//! [`read_tex_sprites`]: ./fn.read_tex_sprites.html
+//! [`pack_csv`]: ./fn.pack_csv.html
+//! [`compress`]: ./fn.compress.html
+//! [`save_contact_sheets`]: ./fn.save_contact_sheets.html
+//! [`save_html_index`]: ./fn.save_html_index.html
+//! [`process_dir`]: ./fn.process_dir.html
+//! [`process_dir_with_options`]: ./fn.process_dir_with_options.html
+//! [`Summary`]: ./struct.Summary.html
+//! [`log`]: https://docs.rs/log
+
+// The helpers of the functions that write files are unused without them.
+#![cfg_attr(not(feature = "fs"), allow(dead_code, unused_imports))]
 
+#[cfg(feature = "fs")]
+mod batch;
+mod dedupe;
 mod error;
 mod extractors;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod format;
+#[cfg(feature = "fs")]
+mod gallery;
+#[cfg(all(
+    feature = "lzham-rust",
+    not(all(
+        feature = "lzham-native",
+        any(target_os = "macos", target_os = "linux")
+    ))
+))]
+mod lzham_decoder;
+#[cfg(feature = "fs")]
+mod sheet;
 mod utils;
 
+#[doc(inline)]
+#[cfg(feature = "fs")]
+pub use batch::{prefer_highres, process_dir, process_dir_with_options, DirOptions, Summary};
+#[doc(inline)]
+pub use dedupe::{DedupeMode, Deduplicator, Duplicate};
 #[doc(inline)]
 pub use error::Error;
 #[doc(inline)]
-pub use extractors::{csv::process_csv, sc::process_sc, tex::process_tex};
+pub use extractors::{
+    csv::{is_decompressed_csv, pack_csv, CsvOptions},
+    sctx::SctxOptions,
+    tex::{
+        decode_pixel, decompress_sc, extract_tex, extract_tex_to_pngs, is_decompressed_tex,
+        pack_tex, read_sc_header, read_tex_sprites, split_alpha, AtlasSprite, HashCheck, Resize,
+        ScHeader, SpriteCrop, SpriteInfo, SpriteNaming, TexAtlas, TexExtraction, TexMetadata,
+        TexOptions, TexSprite,
+    },
+};
+// These write files, so they need the `fs` feature.
+#[doc(inline)]
+#[cfg(feature = "fs")]
+#[allow(deprecated)]
+pub use extractors::tex::process_tex_with_options;
+#[doc(inline)]
+#[cfg(feature = "fs")]
+pub use extractors::{
+    csv::{process_csv, process_csv_with_options},
+    sc::{process_sc, process_sc_with_options, ScOptions},
+    sctx::{process_sctx, process_sctx_with_options},
+    tex::{process_tex, process_tex_with},
+};
+#[doc(inline)]
+pub use format::{OutputFormat, PngCompression};
+#[doc(inline)]
+#[cfg(feature = "fs")]
+pub use gallery::save_html_index;
+#[doc(inline)]
+#[cfg(feature = "fs")]
+pub use sheet::{save_contact_sheets, ContactSheetOptions};
+#[doc(inline)]
+pub use utils::{compress, decompress, decompress_with_limit, detect_codec, Codec, Reader};
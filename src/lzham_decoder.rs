@@ -0,0 +1,929 @@
+//! Decoder of LZHAM streams, the payload of `SCLZ` data, written in Rust.
+//!
+//! It follows the decompressor of the LZHAM codec 1.0 (public domain), for
+//! streams compressed with the default table update rate and without a seed
+//! dictionary or zlib header, which is how Supercell compresses its files.
+//! Decoded bytes are written out as they are decoded, keeping only the last
+//! dictionary's worth of them for matches.
+
+use std::{convert::TryFrom, io::Write};
+
+const MIN_DICT_SIZE_LOG2: u8 = 15;
+const MAX_DICT_SIZE_LOG2: u8 = 29;
+
+const MIN_MATCH_LEN: usize = 2;
+const MAX_MATCH_LEN: usize = 257;
+const NUM_LIT_STATES: usize = 7;
+const NUM_STATES: usize = 12;
+
+/// Number of special symbols at the start of the main table: the end of a
+/// block and a reset of the match history.
+const NUM_SPECIAL_SYMBOLS: usize = 2;
+const NUM_SECONDARY_LENGTHS: usize = 249;
+
+/// Number of match distance slots, by dictionary size.
+const NUM_POSITION_SLOTS: [usize; 15] =
+    [30, 32, 34, 36, 38, 40, 42, 44, 46, 48, 50, 52, 54, 58, 66];
+
+const LITERAL_NEXT_STATE: [usize; NUM_STATES] = [0, 0, 0, 0, 1, 2, 3, 4, 5, 6, 4, 5];
+
+const HUGE_MATCH_BASE_LEN: [usize; 4] = [
+    MAX_MATCH_LEN + 1,
+    MAX_MATCH_LEN + 1 + 256,
+    MAX_MATCH_LEN + 1 + 256 + 1024,
+    MAX_MATCH_LEN + 1 + 256 + 1024 + 4096,
+];
+const HUGE_MATCH_CODE_LEN: [u32; 4] = [8, 10, 12, 16];
+
+/// Interval and slowing rate of Huffman table updates, for the default table
+/// update rate. They must match those used by the compressor.
+const MAX_UPDATE_INTERVAL: u32 = 64;
+const UPDATE_INTERVAL_SLOW_RATE: u32 = 64;
+
+const MAX_CODE_SIZE: usize = 16;
+
+const ARITH_MIN_LEN: u32 = 0x0100_0000;
+const ARITH_PROB_BITS: u32 = 11;
+const ARITH_PROB_SCALE: u16 = 1 << ARITH_PROB_BITS;
+const ARITH_PROB_MOVE_BITS: u32 = 5;
+
+/// Bytes read past the end of the data before a stream counts as truncated.
+/// The decoder reads a few bytes ahead while decoding the last symbols.
+const MAX_OVERREAD: usize = 8;
+
+/// Decoded bytes collected before they are written out.
+const WRITE_SIZE: usize = 64 * 1024;
+
+/// Why an LZHAM stream couldn't be decoded, and where.
+pub(crate) struct DecodeError {
+    /// Number of bytes of the stream read when decoding stopped.
+    pub offset: usize,
+    pub reason: String,
+}
+
+/// Reads bits from the most significant bit of each byte. Bytes past the end
+/// of the data are read as zeros.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u64,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            buf: 0,
+            count: 0,
+        }
+    }
+
+    fn refill(&mut self, num_bits: u32) {
+        while self.count < num_bits {
+            let byte = self.data.get(self.pos).copied().unwrap_or(0);
+            self.pos += 1;
+            self.count += 8;
+            self.buf |= u64::from(byte) << (64 - self.count);
+        }
+    }
+
+    fn bits(&mut self, num_bits: u32) -> u32 {
+        if num_bits == 0 {
+            return 0;
+        }
+
+        self.refill(num_bits);
+        let value = (self.buf >> (64 - num_bits)) as u32;
+        self.buf <<= num_bits;
+        self.count -= num_bits;
+        value
+    }
+
+    fn align_to_byte(&mut self) {
+        self.bits(self.count & 7);
+    }
+
+    /// Takes `len` bytes of the data after the bits read so far, which must
+    /// end at a byte boundary.
+    fn take_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let start = self.offset();
+        let bytes = self.data.get(start..start.checked_add(len)?)?;
+        self.pos = start + len;
+        self.buf = 0;
+        self.count = 0;
+        Some(bytes)
+    }
+
+    /// Number of bytes read, not counting those still in the bit buffer.
+    fn offset(&self) -> usize {
+        self.pos - (self.count / 8) as usize
+    }
+
+    fn is_past_end(&self) -> bool {
+        self.pos > self.data.len() + MAX_OVERREAD
+    }
+}
+
+/// Binary arithmetic decoder, reading from the same bits as the Huffman codes.
+struct ArithDecoder {
+    value: u32,
+    length: u32,
+}
+
+impl ArithDecoder {
+    fn start(bits: &mut BitReader) -> Self {
+        let mut value = 0;
+        for _ in 0..4 {
+            value = (value << 8) | bits.bits(8);
+        }
+
+        Self {
+            value,
+            length: u32::MAX,
+        }
+    }
+
+    /// Decodes a bit whose chance of being `0` is `prob_0` out of 2048, and
+    /// adapts the chance to the bit.
+    fn bit(&mut self, bits: &mut BitReader, prob_0: &mut u16) -> bool {
+        while self.length < ARITH_MIN_LEN {
+            self.value = (self.value << 8) | bits.bits(8);
+            self.length <<= 8;
+        }
+
+        let x = u32::from(*prob_0) * (self.length >> ARITH_PROB_BITS);
+        if self.value >= x {
+            *prob_0 -= *prob_0 >> ARITH_PROB_MOVE_BITS;
+            self.value -= x;
+            self.length -= x;
+            true
+        } else {
+            *prob_0 += (ARITH_PROB_SCALE - *prob_0) >> ARITH_PROB_MOVE_BITS;
+            self.length = x;
+            false
+        }
+    }
+}
+
+/// Chances of the bits decoded in each state.
+#[derive(Clone)]
+struct BitModels {
+    is_match: [u16; NUM_STATES],
+    is_rep: [u16; NUM_STATES],
+    is_rep0: [u16; NUM_STATES],
+    is_rep0_single_byte: [u16; NUM_STATES],
+    is_rep1: [u16; NUM_STATES],
+    is_rep2: [u16; NUM_STATES],
+}
+
+impl Default for BitModels {
+    fn default() -> Self {
+        let half = [ARITH_PROB_SCALE / 2; NUM_STATES];
+        Self {
+            is_match: half,
+            is_rep: half,
+            is_rep0: half,
+            is_rep0_single_byte: half,
+            is_rep1: half,
+            is_rep2: half,
+        }
+    }
+}
+
+/// Huffman code whose code sizes are rebuilt from the frequencies of the
+/// decoded symbols, at intervals that grow as more symbols are decoded.
+#[derive(Clone)]
+struct HuffmanModel {
+    sym_freq: Vec<u16>,
+    code_sizes: Vec<u8>,
+    max_cycle: u32,
+    update_cycle: u32,
+    symbols_until_update: u32,
+    total_count: u32,
+    min_code_size: usize,
+    /// One more than the largest code of each size, left-aligned to 16 bits,
+    /// or `0` if there are no codes of the size. The last one is a sentinel.
+    max_codes: [u32; MAX_CODE_SIZE + 1],
+    /// Index in `sorted_symbols` of the symbol of code `0` of each size.
+    val_ptrs: [i32; MAX_CODE_SIZE + 1],
+    /// Symbols ordered by their code sizes, then by their values.
+    sorted_symbols: Vec<u16>,
+}
+
+impl HuffmanModel {
+    fn new(total_syms: usize) -> Self {
+        let max_cycle = ((total_syms.max(24) as u32 + 6) * MAX_UPDATE_INTERVAL).min(32767);
+        let mut model = Self {
+            sym_freq: vec![1; total_syms],
+            code_sizes: vec![0; total_syms],
+            max_cycle,
+            update_cycle: 0,
+            symbols_until_update: 0,
+            total_count: 0,
+            min_code_size: 0,
+            max_codes: [0; MAX_CODE_SIZE + 1],
+            val_ptrs: [0; MAX_CODE_SIZE + 1],
+            sorted_symbols: vec![0; total_syms],
+        };
+        model.reset();
+        model
+    }
+
+    fn total_syms(&self) -> usize {
+        self.sym_freq.len()
+    }
+
+    fn reset(&mut self) {
+        for freq in &mut self.sym_freq {
+            *freq = 1;
+        }
+        self.update_cycle = self.total_syms() as u32;
+        self.total_count = 0;
+        self.symbols_until_update = 0;
+
+        self.update_tables(Some(self.max_cycle.min(16)), true);
+    }
+
+    fn rescale(&mut self) {
+        let mut total = 0;
+        for freq in &mut self.sym_freq {
+            *freq = (*freq + 1) >> 1;
+            total += u32::from(*freq);
+        }
+        self.total_count = total;
+    }
+
+    fn reset_update_rate(&mut self) {
+        self.total_count += self.update_cycle - self.symbols_until_update;
+        if self.total_count > self.total_syms() as u32 {
+            self.rescale();
+        }
+
+        self.update_cycle = self.update_cycle.min(8);
+        self.symbols_until_update = self.update_cycle;
+    }
+
+    fn update_tables(&mut self, force_update_cycle: Option<u32>, sym_freq_all_ones: bool) {
+        self.total_count += self.update_cycle;
+        while self.total_count >= 32768 {
+            self.rescale();
+        }
+
+        let total_syms = self.total_syms();
+        if sym_freq_all_ones && total_syms >= 2 {
+            // All codes have almost the same size.
+            let base_code_size = floor_log2(total_syms as u32) as u8;
+            let num_left = ((total_syms - (1 << base_code_size)) * 2).min(total_syms);
+            for (i, size) in self.code_sizes.iter_mut().enumerate() {
+                *size = if i < num_left {
+                    base_code_size + 1
+                } else {
+                    base_code_size
+                };
+            }
+        } else {
+            let max_code_size = huffman_code_sizes(&self.sym_freq, &mut self.code_sizes);
+            if max_code_size > MAX_CODE_SIZE {
+                limit_max_code_size(&mut self.code_sizes, MAX_CODE_SIZE);
+            }
+        }
+
+        match force_update_cycle {
+            Some(cycle) => self.update_cycle = cycle,
+            None => {
+                self.update_cycle = ((31 + self.update_cycle * UPDATE_INTERVAL_SLOW_RATE.max(32))
+                    >> 5)
+                    .min(self.max_cycle)
+            }
+        }
+        self.symbols_until_update = self.update_cycle;
+
+        self.build_decoder_tables();
+    }
+
+    fn build_decoder_tables(&mut self) {
+        let mut num_codes = [0usize; MAX_CODE_SIZE + 1];
+        for &size in &self.code_sizes {
+            num_codes[size as usize] += 1;
+        }
+
+        let mut sorted_positions = [0usize; MAX_CODE_SIZE + 1];
+        let mut next_code = 0u32;
+        let mut total_used_syms = 0;
+        self.min_code_size = MAX_CODE_SIZE;
+        for size in 1..=MAX_CODE_SIZE {
+            let n = num_codes[size];
+            if n == 0 {
+                self.max_codes[size - 1] = 0;
+            } else {
+                self.min_code_size = self.min_code_size.min(size);
+
+                let max_code = next_code + n as u32 - 1;
+                let shift = MAX_CODE_SIZE - size;
+                self.max_codes[size - 1] = 1 + ((max_code << shift) | ((1 << shift) - 1));
+                self.val_ptrs[size - 1] = total_used_syms as i32 - next_code as i32;
+                sorted_positions[size] = total_used_syms;
+
+                next_code += n as u32;
+                total_used_syms += n;
+            }
+            next_code <<= 1;
+        }
+
+        for (symbol, &size) in self.code_sizes.iter().enumerate() {
+            if size != 0 {
+                let position = &mut sorted_positions[size as usize];
+                self.sorted_symbols[*position] = symbol as u16;
+                *position += 1;
+            }
+        }
+
+        // Sentinels, which end the search for the size of a code.
+        self.max_codes[MAX_CODE_SIZE] = u32::MAX;
+        self.val_ptrs[MAX_CODE_SIZE] = 0xFFFFF;
+    }
+
+    fn decode(&mut self, bits: &mut BitReader) -> usize {
+        bits.refill(24);
+
+        let k = (bits.buf >> 48) as u32 + 1;
+        let mut len = self.min_code_size;
+        while k > self.max_codes[len - 1] {
+            len += 1;
+        }
+
+        let val_ptr = self.val_ptrs[len - 1] + (bits.buf >> (64 - len)) as i32;
+        let symbol = usize::try_from(val_ptr)
+            .ok()
+            .and_then(|i| self.sorted_symbols.get(i))
+            .copied()
+            .unwrap_or(self.sorted_symbols[0]) as usize;
+
+        bits.buf <<= len;
+        bits.count -= len as u32;
+
+        self.sym_freq[symbol] += 1;
+        self.symbols_until_update -= 1;
+        if self.symbols_until_update == 0 {
+            self.update_tables(None, false);
+        }
+
+        symbol
+    }
+}
+
+fn floor_log2(value: u32) -> u32 {
+    31 - value.leading_zeros()
+}
+
+/// Sets the sizes of the Huffman codes of symbols with the frequencies in
+/// `freqs`, and returns the largest size.
+fn huffman_code_sizes(freqs: &[u16], code_sizes: &mut [u8]) -> usize {
+    let mut symbols: Vec<(u16, usize)> = Vec::with_capacity(freqs.len());
+    for (symbol, &freq) in freqs.iter().enumerate() {
+        if freq == 0 {
+            code_sizes[symbol] = 0;
+        } else {
+            symbols.push((freq, symbol));
+        }
+    }
+
+    if symbols.len() == 1 {
+        code_sizes[symbols[0].1] = 1;
+        return 1;
+    }
+
+    // The sort must be stable, so that equal frequencies keep the order of
+    // their symbols, like the radix sort of the compressor.
+    symbols.sort_by_key(|&(freq, _)| freq);
+
+    let mut lengths: Vec<i32> = symbols.iter().map(|&(freq, _)| i32::from(freq)).collect();
+    minimum_redundancy(&mut lengths);
+
+    let mut max_code_size = 0;
+    for (&(_, symbol), &len) in symbols.iter().zip(&lengths) {
+        code_sizes[symbol] = len as u8;
+        max_code_size = max_code_size.max(len as usize);
+    }
+    max_code_size
+}
+
+/// Turns the frequencies in `a`, sorted in increasing order, into the code
+/// sizes of a Huffman code, in place.
+///
+/// This is the algorithm of Moffat and Katajainen, "In-Place Calculation of
+/// Minimum-Redundancy Codes" (1995).
+fn minimum_redundancy(a: &mut [i32]) {
+    let n = a.len() as isize;
+    if n == 0 {
+        return;
+    }
+    if n == 1 {
+        a[0] = 0;
+        return;
+    }
+
+    let at = |i: isize| i as usize;
+
+    // First pass, left to right, setting parent pointers.
+    a[0] += a[1];
+    let mut root: isize = 0;
+    let mut leaf: isize = 2;
+    for next in 1..n - 1 {
+        // Select the first item for a pairing.
+        if leaf >= n || a[at(root)] < a[at(leaf)] {
+            a[at(next)] = a[at(root)];
+            a[at(root)] = next as i32;
+            root += 1;
+        } else {
+            a[at(next)] = a[at(leaf)];
+            leaf += 1;
+        }
+
+        // Add on the second item.
+        if leaf >= n || (root < next && a[at(root)] < a[at(leaf)]) {
+            a[at(next)] += a[at(root)];
+            a[at(root)] = next as i32;
+            root += 1;
+        } else {
+            a[at(next)] += a[at(leaf)];
+            leaf += 1;
+        }
+    }
+
+    // Second pass, right to left, setting internal depths.
+    a[at(n - 2)] = 0;
+    let mut next = n - 3;
+    while next >= 0 {
+        a[at(next)] = a[a[at(next)] as usize] + 1;
+        next -= 1;
+    }
+
+    // Third pass, right to left, setting leaf depths.
+    let mut available = 1;
+    let mut used = 0;
+    let mut depth = 0;
+    let mut root = n - 2;
+    let mut next = n - 1;
+    while available > 0 {
+        while root >= 0 && a[at(root)] == depth {
+            used += 1;
+            root -= 1;
+        }
+        while available > used {
+            a[at(next)] = depth;
+            next -= 1;
+            available -= 1;
+        }
+        available = 2 * used;
+        depth += 1;
+        used = 0;
+    }
+}
+
+/// Shortens the codes longer than `max_code_size`, lengthening shorter ones
+/// to keep the code complete. The symbols keep the order of their sizes.
+fn limit_max_code_size(code_sizes: &mut [u8], max_code_size: usize) {
+    const MAX_EVER_CODE_SIZE: usize = 34;
+
+    let mut num_codes = [0u32; MAX_EVER_CODE_SIZE + 1];
+    for &size in code_sizes.iter() {
+        num_codes[size as usize] += 1;
+    }
+
+    let mut next_sorted_ofs = [0usize; MAX_EVER_CODE_SIZE + 1];
+    let mut ofs = 0;
+    for size in 1..=MAX_EVER_CODE_SIZE {
+        next_sorted_ofs[size] = ofs;
+        ofs += num_codes[size] as usize;
+    }
+
+    if ofs < 2 || ofs > 1 << max_code_size {
+        return;
+    }
+
+    for size in max_code_size + 1..=MAX_EVER_CODE_SIZE {
+        num_codes[max_code_size] += num_codes[size];
+    }
+
+    let mut total: u32 = (1..=max_code_size)
+        .map(|size| num_codes[size] << (max_code_size - size))
+        .sum();
+
+    while total != 1 << max_code_size {
+        num_codes[max_code_size] -= 1;
+
+        match (1..max_code_size).rev().find(|&size| num_codes[size] != 0) {
+            Some(size) => {
+                num_codes[size] -= 1;
+                num_codes[size + 1] += 2;
+            }
+            None => return,
+        }
+
+        total -= 1;
+    }
+
+    let mut new_sizes = Vec::with_capacity(ofs);
+    for (size, &count) in num_codes.iter().enumerate().take(max_code_size + 1).skip(1) {
+        new_sizes.resize(new_sizes.len() + count as usize, size as u8);
+    }
+
+    for size in code_sizes.iter_mut().filter(|size| **size != 0) {
+        let ofs = &mut next_sorted_ofs[*size as usize];
+        *size = new_sizes[*ofs];
+        *ofs += 1;
+    }
+}
+
+/// Huffman codes of the symbols of the stream.
+#[derive(Clone)]
+struct HuffmanModels {
+    literal: HuffmanModel,
+    delta_literal: HuffmanModel,
+    main: HuffmanModel,
+    /// Lengths of repeated matches, after a literal and after a match.
+    rep_len: [HuffmanModel; 2],
+    /// Lengths of matches of 9 bytes or more, after a literal and after a
+    /// match.
+    large_len: [HuffmanModel; 2],
+    dist_lsb: HuffmanModel,
+}
+
+impl HuffmanModels {
+    fn new(num_position_slots: usize) -> Self {
+        let literal = HuffmanModel::new(256);
+        let rep_len = HuffmanModel::new(1 + MAX_MATCH_LEN - MIN_MATCH_LEN + 1);
+        let large_len = HuffmanModel::new(1 + NUM_SECONDARY_LENGTHS);
+
+        Self {
+            delta_literal: literal.clone(),
+            literal,
+            main: HuffmanModel::new(NUM_SPECIAL_SYMBOLS + (num_position_slots - 1) * 8),
+            rep_len: [rep_len.clone(), rep_len],
+            large_len: [large_len.clone(), large_len],
+            dist_lsb: HuffmanModel::new(16),
+        }
+    }
+
+    fn all_mut(&mut self) -> [&mut HuffmanModel; 8] {
+        let [rep_len_0, rep_len_1] = &mut self.rep_len;
+        let [large_len_0, large_len_1] = &mut self.large_len;
+        [
+            &mut self.literal,
+            &mut self.delta_literal,
+            &mut self.main,
+            rep_len_0,
+            rep_len_1,
+            large_len_0,
+            large_len_1,
+            &mut self.dist_lsb,
+        ]
+    }
+
+    fn reset(&mut self) {
+        for model in self.all_mut().iter_mut() {
+            model.reset();
+        }
+    }
+
+    fn reset_update_rate(&mut self) {
+        for model in self.all_mut().iter_mut() {
+            model.reset_update_rate();
+        }
+    }
+}
+
+/// Decoded bytes, of which the last `dict_size` are kept for matches.
+struct Window<'w> {
+    bytes: Vec<u8>,
+    /// Number of bytes at the start of `bytes` already written out.
+    written: usize,
+    dict_size: usize,
+    total: usize,
+    adler: (u32, u32),
+    output: &'w mut dyn Write,
+}
+
+impl Window<'_> {
+    fn byte_at_distance(&self, distance: usize) -> Option<u8> {
+        let index = self.bytes.len().checked_sub(distance)?;
+        if distance == 0 {
+            return None;
+        }
+        Some(self.bytes[index])
+    }
+
+    fn copy_match(&mut self, distance: usize, len: usize) -> bool {
+        let start = match self.bytes.len().checked_sub(distance) {
+            Some(start) if distance != 0 => start,
+            _ => return false,
+        };
+
+        if distance >= len {
+            self.bytes.extend_from_within(start..start + len);
+        } else {
+            for i in start..start + len {
+                let byte = self.bytes[i];
+                self.bytes.push(byte);
+            }
+        }
+        self.total += len;
+        true
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.bytes.push(byte);
+        self.total += 1;
+    }
+
+    fn extend(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+        self.total += bytes.len();
+    }
+
+    /// Writes the bytes decoded so far if there are enough of them, or if
+    /// `all` is `true`, and drops those too far back for matches.
+    fn flush(&mut self, all: bool) -> Result<(), String> {
+        let pending = &self.bytes[self.written..];
+        if !all && pending.len() < WRITE_SIZE {
+            return Ok(());
+        }
+
+        self.adler = adler32(self.adler, pending);
+        self.output.write_all(pending).map_err(|e| e.to_string())?;
+        self.written = self.bytes.len();
+
+        if self.bytes.len() >= 2 * self.dict_size {
+            let drop = self.bytes.len() - self.dict_size;
+            self.bytes.drain(..drop);
+            self.written -= drop;
+        }
+        Ok(())
+    }
+}
+
+fn adler32((mut a, mut b): (u32, u32), data: &[u8]) -> (u32, u32) {
+    const MOD: u32 = 65521;
+    // The largest number of bytes whose sums can't overflow before `% MOD`.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (a, b)
+}
+
+/// Decodes the LZHAM stream `data`, compressed with a dictionary of
+/// `2^dict_size_log2` bytes, into `output`.
+///
+/// Decoding fails if the stream decodes to more than `max_size` bytes, or if
+/// the checksum at its end doesn't match the output.
+pub(crate) fn decompress(
+    data: &[u8],
+    dict_size_log2: u8,
+    max_size: usize,
+    output: &mut dyn Write,
+) -> Result<(), DecodeError> {
+    let mut bits = BitReader::new(data);
+    let result = decode(&mut bits, dict_size_log2, max_size, output);
+    result.map_err(|reason| DecodeError {
+        offset: bits.offset().min(data.len()),
+        reason,
+    })
+}
+
+fn decode(
+    bits: &mut BitReader,
+    dict_size_log2: u8,
+    max_size: usize,
+    output: &mut dyn Write,
+) -> Result<(), String> {
+    const SYNC_BLOCK: u32 = 0;
+    const COMP_BLOCK: u32 = 1;
+    const RAW_BLOCK: u32 = 2;
+
+    if !(MIN_DICT_SIZE_LOG2..=MAX_DICT_SIZE_LOG2).contains(&dict_size_log2) {
+        return Err(format!(
+            "the dictionary size (2^{}) is not between 2^{} and 2^{}",
+            dict_size_log2, MIN_DICT_SIZE_LOG2, MAX_DICT_SIZE_LOG2
+        ));
+    }
+
+    let num_position_slots = NUM_POSITION_SLOTS[(dict_size_log2 - MIN_DICT_SIZE_LOG2) as usize];
+    let mut position_base = Vec::with_capacity(num_position_slots);
+    let mut position_extra_bits = Vec::with_capacity(num_position_slots);
+    let mut base = 0usize;
+    for slot in 0..num_position_slots {
+        let extra_bits = (slot.saturating_sub(2) / 2).min(25) as u32;
+        position_base.push(base);
+        position_extra_bits.push(extra_bits);
+        base += 1 << extra_bits;
+    }
+
+    let mut huffman = HuffmanModels::new(num_position_slots);
+    let mut models = BitModels::default();
+    let mut window = Window {
+        bytes: Vec::new(),
+        written: 0,
+        dict_size: 1 << dict_size_log2,
+        total: 0,
+        adler: (1, 0),
+        output,
+    };
+
+    loop {
+        let block_type = bits.bits(2);
+
+        if block_type == SYNC_BLOCK {
+            match bits.bits(2) {
+                1 => huffman.reset_update_rate(),
+                2 => {
+                    huffman.reset();
+                    models = BitModels::default();
+                }
+                _ => {}
+            }
+
+            bits.align_to_byte();
+            if bits.bits(16) != 0 || bits.bits(16) != 0xFFFF {
+                return Err("bad sync block".to_string());
+            }
+        } else if block_type == RAW_BLOCK {
+            let len = bits.bits(24);
+            let check = bits.bits(8);
+            if check != (len ^ (len >> 8) ^ (len >> 16)) & 0xFF {
+                return Err("bad raw block length".to_string());
+            }
+
+            bits.align_to_byte();
+            let bytes = bits
+                .take_bytes(len as usize + 1)
+                .ok_or_else(|| "raw block ends after the data".to_string())?;
+            if window.total + bytes.len() > max_size {
+                return Err("more output than the declared size".to_string());
+            }
+            window.extend(bytes);
+            window.flush(false)?;
+        } else if block_type == COMP_BLOCK {
+            let mut arith = ArithDecoder::start(bits);
+
+            let mut match_hist = [1usize; 4];
+            let mut state = 0;
+
+            match bits.bits(2) {
+                1 => huffman.reset_update_rate(),
+                2 => {
+                    huffman.reset();
+                    models = BitModels::default();
+                }
+                _ => {}
+            }
+
+            loop {
+                if bits.is_past_end() {
+                    return Err("unexpected end of data".to_string());
+                }
+                if !arith.bit(bits, &mut models.is_match[state]) {
+                    // Literal.
+                    if window.total >= max_size {
+                        return Err("more output than the declared size".to_string());
+                    }
+                    let byte = if state < NUM_LIT_STATES {
+                        huffman.literal.decode(bits) as u8
+                    } else {
+                        // The byte is coded relative to the one of the last
+                        // match.
+                        let rep_lit0 = window
+                            .byte_at_distance(match_hist[0])
+                            .ok_or_else(|| "bad match distance".to_string())?;
+                        huffman.delta_literal.decode(bits) as u8 ^ rep_lit0
+                    };
+                    window.push(byte);
+                    state = LITERAL_NEXT_STATE[state];
+                } else {
+                    let after_match = (state >= NUM_LIT_STATES) as usize;
+                    let mut match_len = 1;
+
+                    if arith.bit(bits, &mut models.is_rep[state]) {
+                        if arith.bit(bits, &mut models.is_rep0[state]) {
+                            if arith.bit(bits, &mut models.is_rep0_single_byte[state]) {
+                                state = if after_match == 0 { 9 } else { 11 };
+                            } else {
+                                match_len = rep_match_len(bits, &mut huffman.rep_len[after_match]);
+                                state = if after_match == 0 { 8 } else { 11 };
+                            }
+                        } else {
+                            match_len = rep_match_len(bits, &mut huffman.rep_len[after_match]);
+
+                            if arith.bit(bits, &mut models.is_rep1[state]) {
+                                match_hist.swap(0, 1);
+                            } else if arith.bit(bits, &mut models.is_rep2[state]) {
+                                match_hist[..3].rotate_right(1);
+                            } else {
+                                match_hist.rotate_right(1);
+                            }
+                            state = if after_match == 0 { 8 } else { 11 };
+                        }
+                    } else {
+                        let symbol = huffman.main.decode(bits);
+                        if symbol < NUM_SPECIAL_SYMBOLS {
+                            if symbol == 0 {
+                                // End of the block.
+                                break;
+                            }
+
+                            // Reset of the match history.
+                            match_hist = [1; 4];
+                            state = 0;
+                            continue;
+                        }
+
+                        let symbol = symbol - NUM_SPECIAL_SYMBOLS;
+                        match_len = (symbol & 7) + MIN_MATCH_LEN;
+                        let slot = (symbol >> 3) + 1;
+
+                        if match_len == 9 {
+                            match_len += huffman.large_len[after_match].decode(bits);
+                            if match_len == MAX_MATCH_LEN + 1 {
+                                match_len = huge_match_len(bits);
+                            }
+                        }
+
+                        let num_extra_bits = position_extra_bits[slot];
+                        let extra = if num_extra_bits < 3 {
+                            bits.bits(num_extra_bits) as usize
+                        } else {
+                            let high = if num_extra_bits > 4 {
+                                (bits.bits(num_extra_bits - 4) as usize) << 4
+                            } else {
+                                0
+                            };
+                            high + huffman.dist_lsb.decode(bits)
+                        };
+
+                        match_hist.rotate_right(1);
+                        match_hist[0] = position_base[slot] + extra;
+                        state = if after_match == 0 {
+                            NUM_LIT_STATES
+                        } else {
+                            NUM_LIT_STATES + 3
+                        };
+                    }
+
+                    if window.total + match_len > max_size {
+                        return Err("more output than the declared size".to_string());
+                    }
+                    if !window.copy_match(match_hist[0], match_len) {
+                        return Err("bad match distance".to_string());
+                    }
+                }
+
+                window.flush(false)?;
+            }
+
+            bits.align_to_byte();
+        } else {
+            // End of the stream.
+            break;
+        }
+    }
+
+    window.flush(true)?;
+
+    bits.align_to_byte();
+    let checksum = (bits.bits(16) << 16) | bits.bits(16);
+    if bits.is_past_end() {
+        return Err("unexpected end of data".to_string());
+    }
+    let (a, b) = window.adler;
+    if checksum != (b << 16) | a {
+        return Err("the checksum of the output doesn't match".to_string());
+    }
+
+    Ok(())
+}
+
+/// Decodes the length of a repeated match.
+fn rep_match_len(bits: &mut BitReader, model: &mut HuffmanModel) -> usize {
+    let len = model.decode(bits) + MIN_MATCH_LEN;
+    if len == MAX_MATCH_LEN + 1 {
+        huge_match_len(bits)
+    } else {
+        len
+    }
+}
+
+/// Decodes the length of a match longer than 257 bytes.
+fn huge_match_len(bits: &mut BitReader) -> usize {
+    let mut index = 0;
+    while index < 3 && bits.bits(1) == 1 {
+        index += 1;
+    }
+    HUGE_MATCH_BASE_LEN[index] + bits.bits(HUGE_MATCH_CODE_LEN[index]) as usize
+}
@@ -1,17 +1,32 @@
 use colored::Colorize;
+use env_logger::Target;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{Level, LevelFilter};
 use rayon::prelude::*;
-use sc_extract::{process_csv, process_sc, process_tex};
+use sc_extract::{
+    decompress_sc, is_decompressed_csv, is_decompressed_tex, pack_csv, pack_tex, prefer_highres,
+    process_csv_with_options, process_sc_with_options, process_sctx_with_options, process_tex_with,
+    read_tex_sprites, save_contact_sheets, save_html_index, ContactSheetOptions, CsvOptions,
+    DedupeMode, Deduplicator, HashCheck, OutputFormat, PngCompression, Resize, ScOptions,
+    SctxOptions, SpriteInfo, SpriteNaming, TexExtraction, TexOptions,
+};
 use std::{
+    borrow::Cow,
     fs,
+    io::{self, Read, Write},
+    ops::RangeInclusive,
     path::{Path, PathBuf},
     str::FromStr,
-    sync::atomic::{AtomicBool, Ordering},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
 };
 use structopt::StructOpt;
 
 /// A very fast tool to extract graphics and data from Supercell's game files.
 ///
-/// sce supports extraction of the following files: `_tex.sc`, extracted `.sc` and `.csv`.
+/// sce supports extraction of the following files: `_tex.sc`, `.sctx`, extracted `.sc` and `.csv`.
 #[derive(StructOpt)]
 #[structopt(name = "sce")]
 struct Options {
@@ -21,13 +36,26 @@ struct Options {
     #[structopt(parse(from_os_str))]
     path: Option<PathBuf>,
 
+    /// Reads the data of a single file from stdin instead of `path`.
+    ///
+    /// The file's name must be given with `--name`, as it determines the type
+    /// of the file and the names of the extracted files.
+    #[structopt(short = "s", long = "stdin", requires = "name")]
+    stdin: bool,
+
+    /// The name of the file read from stdin, like `characters_tex.sc`.
+    #[structopt(short = "n", long = "name")]
+    name: Option<String>,
+
     /// The path to directory where an extracts folder is created to save output.
     ///
     /// If not specified, `extracts` directory is created inside `path`.
     #[structopt(parse(from_os_str), short = "o", long = "out")]
     out_dir: Option<PathBuf>,
 
-    /// If this flag is supplied, the source `_tex.sc` or `.csv` files are deleted after extracting.
+    /// If this flag is supplied, the source `_tex.sc`, `.sctx` or `.csv` files are deleted after extracting.
+    ///
+    /// Compressed `.sc` files are kept.
     #[structopt(short = "d", long = "delete")]
     delete: bool,
 
@@ -35,6 +63,37 @@ struct Options {
     #[structopt(short = "p", long = "parallelize")]
     parallelize: bool,
 
+    /// The number of threads used for parallel work.
+    ///
+    /// It bounds the files extracted at once with `--parallelize`, and the
+    /// images cut from extracted `sc` files at once. If not specified, all
+    /// cores are used.
+    #[structopt(short = "T", long = "threads")]
+    threads: Option<usize>,
+
+    /// Shows a progress bar of the files extracted from a directory.
+    ///
+    /// The details of every file and sprite aren't printed with it, only
+    /// errors.
+    #[structopt(long = "progress")]
+    progress: bool,
+
+    /// Extracts files in subdirectories of `path` as well.
+    ///
+    /// The directory structure is preserved inside the output directory.
+    #[structopt(short = "r", long = "recursive")]
+    recursive: bool,
+
+    /// Extracts only the highest resolution variant of each `_tex.sc` file in
+    /// a directory.
+    ///
+    /// `ui_highres_tex.sc` is preferred over `ui_tex.sc`, which is preferred
+    /// over `ui_lowres_tex.sc`. Downloadable variants, like
+    /// `ui_lowres_dl_tex.sc`, are compared with each other. The skipped
+    /// variants are listed after extracting.
+    #[structopt(long = "prefer-highres")]
+    prefer_highres: bool,
+
     /// The path to directory where a `_tex.sc` file's extracted images are stored.
     ///
     /// It is required for cutting images using extracted `.sc` files. If the
@@ -43,9 +102,24 @@ struct Options {
     #[structopt(parse(from_os_str), short = "P", long = "png")]
     png_dir: Option<PathBuf>,
 
+    /// Names sprites cut from extracted `.sc` files after their exports.
+    ///
+    /// By default, the sprites are numbered. With this flag, a sprite used by
+    /// an export (like a character or an icon) gets the export's name.
+    #[structopt(short = "e", long = "export-names")]
+    export_names: bool,
+
+    /// Also extracts compressed `.sc` files, after the `_tex.sc` files whose
+    /// images their sprites are cut from.
+    ///
+    /// Only `.sc` files laid out like those QuickBMS extracts are supported,
+    /// so this is opt-in. `.sc` files are never deleted with `--delete`.
+    #[structopt(long = "compressed-sc")]
+    compressed_sc: bool,
+
     /// Specifies the type of files you want to extract.
     ///
-    /// Possible values are "csv", "sc" and "tex". By default, all types are considered.
+    /// Possible values are "csv", "sc", "sctx" and "tex". By default, all types are considered.
     #[structopt(short = "t", long = "type")]
     kind: Option<FileType>,
 
@@ -56,6 +130,318 @@ struct Options {
     /// flag.
     #[structopt(short = "F", long = "disable-filter")]
     disable_filter: bool,
+
+    /// Saves luminance images as grayscale PNGs.
+    ///
+    /// `_tex.sc` sprites with LA88 (6) or L8 (10) pixels are saved as
+    /// grayscale-alpha or grayscale images instead of RGBA images, making the
+    /// files smaller. Other sprites are not affected.
+    #[structopt(short = "g", long = "grayscale")]
+    grayscale: bool,
+
+    /// Saves a JSON file with the details of each `_tex.sc` sprite next to its image.
+    ///
+    /// The file holds the sprite's index, file type, sub-type, width and height.
+    #[structopt(short = "m", long = "metadata")]
+    metadata: bool,
+
+    /// Fills `_tex.sc` pixels that can't be decoded with magenta.
+    ///
+    /// By default, sprites with unknown or truncated pixel data are skipped.
+    /// With this flag, they are saved with the invalid pixels filled instead.
+    #[structopt(short = "f", long = "fill-invalid")]
+    fill_invalid: bool,
+
+    /// Saves decoded `.csv` files as JSON instead of csv.
+    ///
+    /// Each row becomes an object keyed by the column names. Integers and
+    /// booleans are saved as JSON numbers and booleans, as marked by the type
+    /// row of the file.
+    #[structopt(short = "j", long = "csv-json")]
+    csv_json: bool,
+
+    /// Saves chunks of unknown types found in `_tex.sc` files as `.bin` files.
+    ///
+    /// A JSON file listing the offset, type and size of each chunk is saved
+    /// along with them. It is meant for figuring out new chunk types.
+    #[structopt(short = "u", long = "dump-unknown")]
+    dump_unknown: bool,
+
+    /// Names the images of `_tex.sc` sprites with `_`s instead of numbers.
+    ///
+    /// By default, the sprites of a file are saved as `name_0.png`,
+    /// `name_1.png` and so on. With this flag, they are saved as `name.png`,
+    /// `name_.png` and so on, like older versions did.
+    #[structopt(short = "U", long = "underscore-names")]
+    underscore_names: bool,
+
+    /// Extracts only the sprite with the given index from each `_tex.sc` file.
+    ///
+    /// Sprites are counted from 0, in the order they are stored in the file.
+    /// The other sprites are skipped without being decoded.
+    #[structopt(short = "i", long = "index")]
+    index: Option<usize>,
+
+    /// Extracts only the sprites with the given indices from each `_tex.sc` file.
+    ///
+    /// Indices and ranges of indices are separated by commas, like `0,3-5`.
+    /// Sprites are counted from 0 and the other sprites are skipped without
+    /// being decoded.
+    #[structopt(short = "S", long = "sprites", conflicts_with = "index")]
+    sprites: Option<SpriteRanges>,
+
+    /// The size of the square tiles in which tiled `_tex.sc` sprites are stored.
+    ///
+    /// Sprites of file types 27 and 28 are stored in 32x32 pixel tiles by
+    /// default. Some games use other sizes, like 64. The size isn't stored in
+    /// the file, so it must be specified if the images come out scrambled.
+    #[structopt(short = "b", long = "block-size")]
+    block_size: Option<u32>,
+
+    /// The largest width or height of a `_tex.sc` sprite that is extracted.
+    ///
+    /// Corrupt headers can claim huge sizes. Such sprites are skipped instead
+    /// of running out of memory. The default is 16384.
+    #[structopt(short = "M", long = "max-size")]
+    max_size: Option<u32>,
+
+    /// The largest size, in bytes, of the decompressed data of a file.
+    ///
+    /// Corrupt files can decompress to huge sizes. Such files fail once they
+    /// reach the limit instead of running out of memory. The default is 512
+    /// MiB.
+    #[structopt(long = "max-decompressed-size")]
+    max_decompressed_size: Option<usize>,
+
+    /// The path of a zstd dictionary that newer `_tex.sc` and `.sctx` files
+    /// were compressed with.
+    ///
+    /// Such files fail to decompress without it. The dictionary is usually
+    /// shipped with the game.
+    #[structopt(long = "zstd-dict", parse(from_os_str))]
+    zstd_dict: Option<PathBuf>,
+
+    /// Treats `_tex.sc` and `.csv` files as data that was already
+    /// decompressed.
+    ///
+    /// Files without an `SC` header or compression signature are already
+    /// treated this way if they look like decompressed data. This forces it
+    /// for files that don't.
+    #[structopt(long = "assume-decompressed")]
+    assume_decompressed: bool,
+
+    /// The format in which `_tex.sc` sprites and `.sctx` textures are saved.
+    ///
+    /// Possible values are "png", "webp", "jpeg", "tiff", "dds", "raw", "bmp"
+    /// and "tga". The default is "png". DDS images hold uncompressed BGRA
+    /// pixels.
+    ///
+    /// "raw" saves `.rgba` files: the width and height as little-endian
+    /// 32-bit integers, followed by 4 bytes of RGBA for each pixel, row by
+    /// row. It skips encoding entirely.
+    /// WebP images are lossless unless `--lossy` is given. JPEG images have
+    /// no transparency, so transparent pixels are blended onto `--background`.
+    #[structopt(short = "O", long = "format")]
+    format: Option<ImageKind>,
+
+    /// Saves WebP images with lossy compression, making them much smaller.
+    #[structopt(short = "l", long = "lossy", requires = "format")]
+    lossy: bool,
+
+    /// The quality of lossy WebP and JPEG images, from 0 to 100.
+    ///
+    /// The default is 75 for WebP and 85 for JPEG.
+    #[structopt(short = "q", long = "quality", requires = "format")]
+    quality: Option<f32>,
+
+    /// The color transparent pixels are blended onto in JPEG images and with
+    /// `--flatten`.
+    ///
+    /// The color is given in hex, like `ffffff`, which is the default.
+    #[structopt(short = "B", long = "background")]
+    background: Option<Color>,
+
+    /// Saves `_tex.sc` sprites without an alpha channel.
+    ///
+    /// Opaque sprites lose nothing. Sprites with transparent pixels keep their
+    /// alpha channel, with a warning, unless `--flatten` is given.
+    #[structopt(short = "N", long = "no-alpha")]
+    no_alpha: bool,
+
+    /// Converts `_tex.sc` sprites from premultiplied alpha to straight alpha,
+    /// for games that premultiply their textures.
+    ///
+    /// Use it if semi-transparent edges of sprites look too dark.
+    #[structopt(long = "unmultiply-alpha")]
+    unmultiply_alpha: bool,
+
+    /// Blends transparent sprites onto `--background` with `--no-alpha`,
+    /// instead of keeping their alpha channel.
+    #[structopt(short = "L", long = "flatten", requires = "no-alpha")]
+    flatten: bool,
+
+    /// Crops the fully transparent borders off `_tex.sc` sprites.
+    ///
+    /// The offset and size of each sprite in its original image are saved in
+    /// a JSON file next to it, like with `--metadata`, and printed. Fully
+    /// transparent sprites are saved as a single transparent pixel, unless
+    /// `--skip-transparent` is given.
+    #[structopt(short = "x", long = "trim")]
+    trim: bool,
+
+    /// Skips fully transparent `_tex.sc` sprites with `--trim`, instead of
+    /// saving them as a single pixel.
+    #[structopt(long = "skip-transparent", requires = "trim")]
+    skip_transparent: bool,
+
+    /// How hard PNG images are compressed.
+    ///
+    /// Possible values are "fast", "default" and "best". "fast" saves time at
+    /// the cost of much larger files. "best" makes the smallest files, but is
+    /// the slowest. The default is "default".
+    #[structopt(short = "C", long = "png-compression")]
+    png_compression: Option<Compression>,
+
+    /// Saves the alpha channel of each `_tex.sc` sprite as a separate image.
+    ///
+    /// The sprite is saved without its alpha channel, and its alpha channel as
+    /// a grayscale `<name>_alpha` image next to it.
+    #[structopt(short = "A", long = "split-alpha")]
+    split_alpha: bool,
+
+    /// Lists the sprites of `_tex.sc` files and the paths they would be saved
+    /// at, without decoding or saving anything.
+    ///
+    /// Only the headers of the sprites are read. Other files are listed by
+    /// name, and no file is deleted with `--delete`.
+    #[structopt(short = "D", long = "dry-run")]
+    dry_run: bool,
+
+    /// Scales `_tex.sc` images by the given factor before saving them, like
+    /// `0.5` for half the size.
+    ///
+    /// The aspect ratio is preserved. Sprites are never made smaller than a
+    /// pixel.
+    #[structopt(long = "scale", conflicts_with = "max-dimension")]
+    scale: Option<f32>,
+
+    /// Scales `_tex.sc` images whose width or height is larger than the given
+    /// number of pixels down to fit it before saving them.
+    ///
+    /// The aspect ratio is preserved. Smaller images are saved as they are.
+    #[structopt(long = "max-dimension")]
+    max_dimension: Option<u32>,
+
+    /// Flips `_tex.sc` sprites upside down, for engines whose texture
+    /// coordinates start at the bottom.
+    #[structopt(long = "flip-v")]
+    flip_v: bool,
+
+    /// Mirrors `_tex.sc` sprites left to right.
+    #[structopt(long = "flip-h")]
+    flip_h: bool,
+
+    /// Saves thumbnails of all saved `_tex.sc` images, captioned with their
+    /// names, on a contact sheet at the given path.
+    ///
+    /// If they don't fit on one sheet, the sheets are numbered, like
+    /// `sheet_0.png`, `sheet_1.png` and so on.
+    #[structopt(long = "contact-sheet", parse(from_os_str))]
+    contact_sheet: Option<PathBuf>,
+
+    /// Saves an `index.html` page in the output directory that shows all
+    /// saved `_tex.sc` images, grouped by file, with their dimensions and
+    /// pixel types.
+    ///
+    /// The page is self-contained and loads the images as they are scrolled
+    /// into view.
+    #[structopt(long = "html-index")]
+    html_index: bool,
+
+    /// Skips `_tex.sc` sprites whose pixels are identical to those of a
+    /// sprite saved before, in any file.
+    ///
+    /// The duplicates are listed in `duplicates.json` in the output directory.
+    /// `--dedupe-mode` can save them differently.
+    #[structopt(long = "dedupe")]
+    dedupe: bool,
+
+    /// What is done with duplicate sprites with `--dedupe`.
+    ///
+    /// Possible values are "skip", "hardlink", "symlink" and "report".
+    /// "hardlink" and "symlink" save duplicates as links to the first image,
+    /// and "report" saves them like other sprites. The default is "skip".
+    #[structopt(long = "dedupe-mode", requires = "dedupe")]
+    dedupe_mode: Option<Dedupe>,
+
+    /// Skips `_tex.sc` files whose images all exist already and are newer
+    /// than the files, without decoding them.
+    ///
+    /// Files are always extracted with `--skip-transparent` or when
+    /// duplicates are skipped, as the images to expect aren't known then.
+    #[structopt(long = "incremental")]
+    incremental: bool,
+
+    /// Also saves a PNG thumbnail of every `_tex.sc` sprite, as
+    /// `<name>.thumb.png`, scaled down to fit in the given number of pixels.
+    ///
+    /// Smaller sprites are saved at their size.
+    #[structopt(long = "thumbnails")]
+    thumbnails: Option<u32>,
+
+    /// Doesn't save the source file, index, pixel type and size of `_tex.sc`
+    /// sprites, or the version of sc_extract, in text chunks of their PNG
+    /// images.
+    #[structopt(long = "no-png-text")]
+    no_png_text: bool,
+
+    /// Only warns when the hash in the header of a `_tex.sc` or `.sctx` file
+    /// doesn't match its decompressed data, instead of failing the file.
+    ///
+    /// MD5, SHA-1 and SHA-256 hashes are verified. Hashes of other lengths
+    /// are skipped with a warning.
+    #[structopt(long = "no-verify")]
+    no_verify: bool,
+
+    /// Combines the sprites of each `_tex.sc` file into one atlas image,
+    /// `<name>_atlas.png`, instead of saving them separately.
+    ///
+    /// The rectangle of each sprite in the atlas is saved in
+    /// `<name>_atlas.json`.
+    #[structopt(long = "combine")]
+    combine: bool,
+
+    /// Packs plain `.csv` files and extracted `_tex.sc` images back into the
+    /// files the games read, instead of extracting files.
+    ///
+    /// The packed files are saved in the output directory. `.csv` files keep
+    /// their names. The images of a `_tex.sc` file are found from that of its
+    /// first sprite, like `ui_tex.png` or `ui_tex_0.png`, and packed into
+    /// `ui_tex.sc`. Other files are skipped.
+    #[structopt(long = "pack")]
+    pack: bool,
+
+    /// The pixel type of packed sprites without metadata JSON files, like
+    /// `0` for RGBA8888 or `2` for RGBA4444.
+    ///
+    /// Sprites saved with `--metadata` keep their original types.
+    #[structopt(long = "pack-pixel-type", default_value = "0")]
+    pack_pixel_type: u8,
+
+    /// Packs sprites without metadata JSON files in 32x32 tiles, as file
+    /// type 28, instead of in scanlines.
+    #[structopt(long = "pack-tiled")]
+    pack_tiled: bool,
+}
+
+/// State shared by all files that are processed.
+struct Shared {
+    /// The names of `_tex.sc` files and what was extracted from them.
+    saved: Mutex<Vec<(String, TexExtraction)>>,
+    /// Finds duplicate sprites across files with `--dedupe`.
+    dedupe: Option<Arc<Deduplicator>>,
+    /// The dictionary read from `--zstd-dict`.
+    zstd_dictionary: Option<Arc<Vec<u8>>>,
 }
 
 /// Represents a single file type.
@@ -67,6 +453,8 @@ enum FileType {
     Sc,
     /// Represents `_tex.sc` files.
     Tex,
+    /// Represents `.sctx` files.
+    Sctx,
 }
 
 impl FromStr for FileType {
@@ -77,14 +465,154 @@ impl FromStr for FileType {
             "csv" => Ok(Self::Csv),
             "sc" => Ok(Self::Sc),
             "tex" => Ok(Self::Tex),
-            _ => Err("File type must be one of `csv`, `sc` and `tex`."),
+            "sctx" => Ok(Self::Sctx),
+            _ => Err("File type must be one of `csv`, `sc`, `sctx` and `tex`."),
+        }
+    }
+}
+
+/// Represents the image format given to `--format`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ImageKind {
+    Png,
+    WebP,
+    Jpeg,
+    Tiff,
+    Dds,
+    Raw,
+    Bmp,
+    Tga,
+}
+
+impl FromStr for ImageKind {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "png" => Ok(Self::Png),
+            "webp" => Ok(Self::WebP),
+            "jpeg" | "jpg" => Ok(Self::Jpeg),
+            "tiff" | "tif" => Ok(Self::Tiff),
+            "dds" => Ok(Self::Dds),
+            "raw" => Ok(Self::Raw),
+            "bmp" => Ok(Self::Bmp),
+            "tga" => Ok(Self::Tga),
+            _ => Err(
+                "Format must be one of `png`, `webp`, `jpeg`, `tiff`, `dds`, `raw`, `bmp` and `tga`.",
+            ),
+        }
+    }
+}
+
+/// Represents a color given in hex to `--background`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Color([u8; 3]);
+
+impl FromStr for Color {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = "Color must be six hex digits, like `ffffff`.";
+
+        let bytes = hex::decode(s.trim().trim_start_matches('#')).map_err(|_| error)?;
+        match bytes.as_slice() {
+            &[r, g, b] => Ok(Self([r, g, b])),
+            _ => Err(error),
         }
     }
 }
 
+/// Represents a `PngCompression` given to `--png-compression`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Compression(PngCompression);
+
+impl FromStr for Compression {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "fast" => Ok(Self(PngCompression::Fast)),
+            "default" => Ok(Self(PngCompression::Default)),
+            "best" => Ok(Self(PngCompression::Best)),
+            _ => Err("Compression must be one of `fast`, `default` and `best`."),
+        }
+    }
+}
+
+/// Represents a `DedupeMode` given to `--dedupe-mode`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Dedupe(DedupeMode);
+
+impl FromStr for Dedupe {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "skip" => Ok(Self(DedupeMode::Skip)),
+            "hardlink" => Ok(Self(DedupeMode::HardLink)),
+            "symlink" => Ok(Self(DedupeMode::SymLink)),
+            "report" => Ok(Self(DedupeMode::Report)),
+            _ => Err("Dedupe mode must be one of `skip`, `hardlink`, `symlink` and `report`."),
+        }
+    }
+}
+
+/// Represents the ranges of sprite indices given to `--sprites`.
+#[derive(Debug)]
+struct SpriteRanges(Vec<RangeInclusive<usize>>);
+
+impl FromStr for SpriteRanges {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error =
+            "Sprites must be indices or ranges of indices separated by commas, like `0,3-5`.";
+
+        let mut ranges = Vec::new();
+        for part in s.split(',') {
+            let (start, end) = match part.split_once('-') {
+                Some((start, end)) => (start, end),
+                None => (part, part),
+            };
+            let start = start.trim().parse().map_err(|_| error)?;
+            let end = end.trim().parse().map_err(|_| error)?;
+            if start > end {
+                return Err(error);
+            }
+
+            ranges.push(start..=end);
+        }
+
+        Ok(Self(ranges))
+    }
+}
+
+/// Prints the details of a sprite extracted from a `_tex.sc` file.
+fn print_sprite(sprite: &SpriteInfo) {
+    let metadata = &sprite.metadata;
+    println!(
+        "file_type: {}, file_size: {}, sub_type: {}, width: {}, height: {}",
+        metadata.file_type.to_string().cyan().bold(),
+        sprite.chunk_size.to_string().cyan().bold(),
+        metadata.sub_type.to_string().cyan().bold(),
+        metadata.width.to_string().cyan().bold(),
+        metadata.height.to_string().cyan().bold()
+    );
+
+    if let Some(crop) = metadata.crop {
+        println!(
+            "cropped at: {}, {} of {}x{}",
+            crop.x.to_string().cyan().bold(),
+            crop.y.to_string().cyan().bold(),
+            crop.original_width.to_string().cyan().bold(),
+            crop.original_height.to_string().cyan().bold()
+        );
+    }
+}
+
 /// Deletes the file with given path. It deletion fails, prints it on stdout.
 fn delete_file(path: &Path) {
-    match fs::remove_file(&path) {
+    match fs::remove_file(path) {
         Ok(_) => (),
         Err(_) => println!(
             "{}: {}",
@@ -94,17 +622,56 @@ fn delete_file(path: &Path) {
     };
 }
 
+/// Appends the paths of all files inside `dir` and its subdirectories to
+/// `files`.
+///
+/// `skip` is not walked, so that the output directory isn't processed again.
+fn collect_files(dir: &Path, skip: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            if fs::canonicalize(&path)? != skip {
+                collect_files(&path, skip, files)?;
+            }
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if `path` may be a compressed `sc` file, with an `.sc`
+/// extension, extracted with `--compressed-sc`.
+fn is_compressed_sc(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+
+    name.ends_with(".sc") && !name.ends_with("_tex.sc")
+}
+
+/// Returns `true` if `path` may be an `sc` file, compressed with an `.sc`
+/// extension or extracted without one, whose sprites are cut from the images
+/// of its `_tex.sc` file.
+fn is_sc_file(path: &Path) -> bool {
+    path.extension().is_none() || is_compressed_sc(path)
+}
+
 /// Returns correct file type depending on the file extension and/or data.
 ///
 /// If the extension and/or data don't match any expected file type,
 /// `None` is returned.
 ///
-/// The data passed here must be compressed/raw.
-fn get_file_type(data: &[u8], path: &Path, filter: bool) -> Option<FileType> {
+/// The data passed here must be compressed/raw, unless it looks like
+/// decompressed data or `--assume-decompressed` is used.
+fn get_file_type(data: &[u8], path: &Path, opts: &Options) -> Option<FileType> {
     // Some common mistakenly used file types are filtered here.
     let path_str = path.file_name().unwrap().to_str().unwrap();
 
-    if filter && [".DS_Store", "quickbms"].contains(&path_str) {
+    if !opts.disable_filter && [".DS_Store", "quickbms"].contains(&path_str) {
         return None;
     }
 
@@ -112,9 +679,21 @@ fn get_file_type(data: &[u8], path: &Path, filter: bool) -> Option<FileType> {
         None
     } else if path.extension().is_none() {
         Some(FileType::Sc)
-    } else if data[0] == 83 && path_str.ends_with("_tex.sc") {
+    } else if (data[0] == 83 || opts.assume_decompressed || is_decompressed_tex(data))
+        && (path_str.ends_with("_tex.sc") || path_str.ends_with("_tex.sc2"))
+    {
         Some(FileType::Tex)
-    } else if data.len() >= 2 && data[..2] == [93, 0] && path_str.ends_with(".csv") {
+    } else if data[0] == 83 && path_str.ends_with(".sctx") {
+        Some(FileType::Sctx)
+    } else if opts.compressed_sc && data.starts_with(b"SC") && is_compressed_sc(path) {
+        Some(FileType::Sc)
+    } else if (data.starts_with(&[93, 0]) && path_str.ends_with(".csv"))
+        || (data.starts_with(b"SC") && path_str.ends_with(".csv.sc2"))
+        || ((opts.assume_decompressed || is_decompressed_csv(data))
+            && (path_str.ends_with(".csv") || path_str.ends_with(".csv.sc2")))
+    {
+        // Chunked `.sc2` files have an `SC` header. Already decompressed files
+        // are saved as they are.
         Some(FileType::Csv)
     } else {
         None
@@ -130,16 +709,110 @@ fn get_file_type(data: &[u8], path: &Path, filter: bool) -> Option<FileType> {
 /// ## Panic
 ///
 /// The process may panic in case of lack of permissions to read/write files.
-fn process_file(path: &Path, out_dir: &Path, parallelize: bool, opts: &Options) -> Result<(), ()> {
-    let data = match fs::read(&path) {
+fn process_file(
+    path: &Path,
+    out_dir: &Path,
+    parallelize: bool,
+    opts: &Options,
+    shared: &Shared,
+) -> Result<(), ()> {
+    let data = match fs::read(path) {
         Ok(d) => d,
         Err(_) => return Err(()),
     };
 
-    let res = if let Some(file_type) = get_file_type(data.as_slice(), path, !opts.disable_filter) {
+    // `.sc` files also hold the animations, which aren't extracted.
+    if process_data(&data, path, out_dir, parallelize, opts, shared)?
+        && opts.delete
+        && !is_compressed_sc(path)
+    {
+        delete_file(path);
+    }
+
+    Ok(())
+}
+
+/// Packs the given plain `.csv` file data, or the `_tex.sc` sprites whose
+/// first image is at `path`, with `--pack`.
+///
+/// Returns `Ok(true)` if the data was packed, `Ok(false)` if packing it
+/// failed, and `Err(())` if it isn't a plain `.csv` file or the image of a
+/// first sprite.
+fn pack_data(data: &[u8], path: &Path, out_dir: &Path, opts: &Options) -> Result<bool, ()> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).ok_or(())?;
+
+    let res = if file_name.ends_with(".csv") {
+        // Compressed files are packed already.
+        if data.starts_with(&[93, 0]) {
+            return Err(());
+        }
+
+        create_out_dir(out_dir);
+        println!("\nPacking {} file...", file_name.green().bold());
+
+        pack_csv(data).and_then(|packed| Ok(fs::write(out_dir.join(file_name), packed)?))
+    } else {
+        let file_type = if opts.pack_tiled { 28 } else { 1 };
+        match read_tex_sprites(path, file_type, opts.pack_pixel_type) {
+            Ok(Some((name, sprites))) => {
+                create_out_dir(out_dir);
+                println!(
+                    "\nPacking {} image(s) into {}...",
+                    sprites.len(),
+                    name.green().bold()
+                );
+
+                pack_tex(&sprites).and_then(|packed| Ok(fs::write(out_dir.join(name), packed)?))
+            }
+            Ok(None) => return Err(()),
+            Err(e) => Err(e),
+        }
+    };
+    if let Err(e) = res {
+        println!("\n{}: {}", e.inner().red(), path.to_str().unwrap().red());
+
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+/// Creates `out_dir` if it doesn't exist.
+fn create_out_dir(out_dir: &Path) {
+    if !out_dir.exists() {
+        fs::create_dir_all(out_dir).expect("Expected to be able to create a directory.");
+    }
+}
+
+/// Processes the given file data.
+///
+/// `path` is used to detect the file type and to name the output, so it
+/// doesn't have to exist. Returns `Ok(true)` if the data was extracted,
+/// `Ok(false)` if it was skipped or extracting it failed, and `Err(())` if it
+/// isn't a valid file.
+///
+/// The names of `_tex.sc` files and what was extracted from them are added to
+/// `shared.saved` if a contact sheet or an HTML index is made.
+///
+/// ## Panic
+///
+/// The process may panic in case of lack of permissions to read/write files.
+fn process_data(
+    data: &[u8],
+    path: &Path,
+    out_dir: &Path,
+    parallelize: bool,
+    opts: &Options,
+    shared: &Shared,
+) -> Result<bool, ()> {
+    if opts.pack {
+        return pack_data(data, path, out_dir, opts);
+    }
+
+    let res = if let Some(file_type) = get_file_type(data, path, opts) {
         if let Some(ft) = opts.kind {
             if ft != file_type {
-                return Ok(());
+                return Ok(false);
             }
         }
         let file_name = path
@@ -148,20 +821,185 @@ fn process_file(path: &Path, out_dir: &Path, parallelize: bool, opts: &Options)
             .to_str()
             .expect("Expected file to have a valid UTF-8 name.");
 
+        if opts.dry_run && file_type != FileType::Tex {
+            println!("{} would be extracted.", file_name);
+
+            return Ok(false);
+        }
+
+        if !out_dir.exists() && !opts.dry_run {
+            // Nested output directories are created only when there is a file
+            // to extract in them.
+            fs::create_dir_all(out_dir).expect("Expected to be able to create a directory.");
+        }
+
+        // `.sctx` textures are saved like `_tex.sc` sprites.
+        let format = match opts.format {
+            Some(ImageKind::WebP) => OutputFormat::WebP {
+                lossless: !opts.lossy,
+                quality: opts.quality.unwrap_or(75.0).clamp(0.0, 100.0),
+            },
+            Some(ImageKind::Jpeg) => OutputFormat::Jpeg {
+                quality: opts.quality.unwrap_or(85.0).clamp(1.0, 100.0) as u8,
+                background: opts.background.unwrap_or(Color([255; 3])).0,
+            },
+            Some(ImageKind::Tiff) => OutputFormat::Tiff,
+            Some(ImageKind::Dds) => OutputFormat::Dds,
+            Some(ImageKind::Raw) => OutputFormat::Raw,
+            Some(ImageKind::Bmp) => OutputFormat::Bmp,
+            Some(ImageKind::Tga) => OutputFormat::Tga,
+            _ => OutputFormat::Png,
+        };
+        let png_compression = opts.png_compression.map(|c| c.0).unwrap_or_default();
+
         match file_type {
-            FileType::Tex => process_tex(&data, file_name, &out_dir, parallelize),
-            FileType::Csv => process_csv(&data, file_name, &out_dir),
+            FileType::Tex => {
+                let options = TexOptions {
+                    format,
+                    grayscale: opts.grayscale,
+                    block_size: opts.block_size,
+                    // The crop of every sprite is saved with its metadata.
+                    metadata: opts.metadata || opts.trim,
+                    only_index: opts.index,
+                    sprites: opts.sprites.as_ref().map(|ranges| ranges.0.clone()),
+                    dump_unknown: opts.dump_unknown,
+                    fill_invalid: opts.fill_invalid,
+                    max_size: opts.max_size,
+                    max_decompressed_size: opts.max_decompressed_size,
+                    zstd_dictionary: shared.zstd_dictionary.clone(),
+                    naming: if opts.underscore_names {
+                        SpriteNaming::Underscores
+                    } else {
+                        SpriteNaming::Numbered
+                    },
+                    png_compression,
+                    split_alpha: opts.split_alpha,
+                    no_alpha: opts.no_alpha,
+                    unmultiply_alpha: opts.unmultiply_alpha,
+                    alpha_background: if opts.flatten {
+                        Some(opts.background.unwrap_or(Color([255; 3])).0)
+                    } else {
+                        None
+                    },
+                    crop: opts.trim,
+                    skip_transparent: opts.skip_transparent,
+                    dry_run: opts.dry_run,
+                    flip_vertical: opts.flip_v,
+                    flip_horizontal: opts.flip_h,
+                    dedupe: shared.dedupe.clone(),
+                    resize: match (opts.scale, opts.max_dimension) {
+                        (Some(scale), _) => Some(Resize::Scale(scale)),
+                        (_, Some(max)) => Some(Resize::MaxDimension(max)),
+                        _ => None,
+                    },
+                    thumbnail_size: opts.thumbnails,
+                    no_png_text: opts.no_png_text,
+                    hash_check: if opts.no_verify {
+                        HashCheck::Warn
+                    } else {
+                        HashCheck::Fail
+                    },
+                    combine: opts.combine,
+                    assume_decompressed: opts.assume_decompressed,
+                    // Data read from the standard input has no time to compare.
+                    skip_existing: if opts.incremental {
+                        fs::metadata(path)
+                            .and_then(|metadata| metadata.modified())
+                            .ok()
+                    } else {
+                        None
+                    },
+                    parallelize,
+                };
+
+                let res = process_tex_with(data, file_name, out_dir, &options).map(|extraction| {
+                    if !opts.progress {
+                        for sprite in &extraction.sprites {
+                            print_sprite(sprite);
+                            if opts.dry_run {
+                                println!("would be saved at: {}", sprite.path.display());
+                            }
+                        }
+
+                        if let Some(atlas) = &extraction.atlas {
+                            println!(
+                                "combined {} sprite(s) into: {}",
+                                atlas.sprites.len().to_string().cyan().bold(),
+                                atlas.path.display()
+                            );
+                        }
+                    }
+
+                    if (opts.contact_sheet.is_some() || opts.html_index) && !opts.dry_run {
+                        shared
+                            .saved
+                            .lock()
+                            .expect("Expected the list of saved images to be usable.")
+                            .push((file_name.to_string(), extraction));
+                    }
+                });
+
+                if opts.dry_run && res.is_ok() {
+                    // Nothing was extracted, so the file must not be deleted.
+                    return Ok(false);
+                }
+
+                res
+            }
+            FileType::Sctx => {
+                let options = SctxOptions {
+                    format,
+                    png_compression,
+                    max_decompressed_size: opts.max_decompressed_size,
+                    zstd_dictionary: shared.zstd_dictionary.clone(),
+                    hash_check: if opts.no_verify {
+                        HashCheck::Warn
+                    } else {
+                        HashCheck::Fail
+                    },
+                };
+
+                process_sctx_with_options(data, file_name, out_dir, parallelize, &options)
+            }
+            FileType::Csv => {
+                let options = CsvOptions {
+                    json: opts.csv_json,
+                    max_decompressed_size: opts.max_decompressed_size,
+                    assume_decompressed: opts.assume_decompressed,
+                };
+
+                process_csv_with_options(data, file_name, out_dir, &options)
+            }
             FileType::Sc => {
+                // The images of `.sc` files are extracted from their `_tex.sc`
+                // files to the output directory, while extracted `sc` files
+                // are usually next to their images.
+                let compressed = file_name.ends_with(".sc");
                 let png_dir = match opts.png_dir.as_ref() {
                     Some(p) => p,
+                    None if compressed => out_dir,
                     None => match path.parent() {
                         Some(p) => p,
                         None => {
                             println!("{}", "Could not determine the path for png files.".red());
 
-                            return Ok(());
+                            return Ok(false);
+                        }
+                    },
+                };
+
+                // `.sc` files are compressed like `_tex.sc` files, and
+                // QuickBMS saves them decompressed without an extension.
+                let (data, file_name) = match file_name.strip_suffix(".sc") {
+                    Some(name) => match decompress_sc(data) {
+                        Ok(data) => (Cow::Owned(data), name),
+                        Err(e) => {
+                            println!("\n{}: {}", e.inner().red(), path.to_str().unwrap().red());
+
+                            return Ok(false);
                         }
                     },
+                    None => (Cow::Borrowed(data), file_name),
                 };
 
                 let out_dir = out_dir.join(format!("{}_out", file_name));
@@ -170,7 +1008,11 @@ fn process_file(path: &Path, out_dir: &Path, parallelize: bool, opts: &Options)
                     fs::create_dir(&out_dir).unwrap();
                 }
 
-                process_sc(&data, file_name, &out_dir, png_dir, parallelize)
+                let options = ScOptions {
+                    export_names: opts.export_names,
+                };
+
+                process_sc_with_options(&data, file_name, &out_dir, png_dir, parallelize, &options)
             }
         }
     } else {
@@ -181,18 +1023,51 @@ fn process_file(path: &Path, out_dir: &Path, parallelize: bool, opts: &Options)
         println!("\n{}: {}", e.inner().red(), path.to_str().unwrap().red());
 
         // Don't delete file if there was an error.
-        return Ok(());
+        return Ok(false);
     }
 
-    if opts.delete {
-        delete_file(&path);
-    }
+    Ok(true)
+}
 
-    Ok(())
+/// Prints the messages the library logs, colored by their level, like the
+/// rest of the output.
+///
+/// The level is `info` by default, and can be changed with the `RUST_LOG`
+/// environment variable.
+fn init_logger() {
+    env_logger::Builder::new()
+        .filter_level(LevelFilter::Info)
+        .parse_env("RUST_LOG")
+        .target(Target::Stdout)
+        .format(|buf, record| match record.level() {
+            Level::Error => writeln!(buf, "{}", record.args().to_string().red()),
+            Level::Warn => writeln!(buf, "{}", record.args().to_string().yellow()),
+            // Each file starts with an `info` message.
+            Level::Info => writeln!(buf, "\n{}", record.args()),
+            _ => writeln!(buf, "{}", record.args()),
+        })
+        .init();
 }
 
 fn main() {
     let opts: Options = Options::from_args();
+    init_logger();
+
+    if let Some(threads) = opts.threads {
+        if threads == 0 {
+            println!("{}", "Number of threads must be positive.".red());
+            std::process::exit(1);
+        }
+
+        if rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .is_err()
+        {
+            println!("{}", "Failed to create the thread pool.".red());
+            std::process::exit(1);
+        }
+    }
 
     let path = if let Some(ref p) = opts.path {
         p.clone()
@@ -232,67 +1107,222 @@ fn main() {
         false
     };
 
-    if path.is_dir() {
-        let found_one = AtomicBool::new(false);
-        let dir_entries = match fs::read_dir(&path) {
-            Ok(e) => e,
+    let shared = Shared {
+        saved: Mutex::new(Vec::new()),
+        dedupe: if opts.dedupe {
+            let mode = opts.dedupe_mode.map(|m| m.0).unwrap_or_default();
+            Some(Arc::new(Deduplicator::new(mode)))
+        } else {
+            None
+        },
+        zstd_dictionary: opts.zstd_dict.as_ref().map(|path| match fs::read(path) {
+            Ok(dictionary) => Arc::new(dictionary),
             Err(_) => {
                 println!(
                     "{}",
-                    format!(
-                        "Failed to read contents of {} directory/folder.",
-                        path.to_str()
-                            .expect("Expected path to be valid UTF-8.")
-                            .red()
-                    )
-                    .red()
+                    format!("Failed to read zstd dictionary {}.", path.display()).red()
                 );
                 std::process::exit(1);
             }
-        };
+        }),
+    };
+    // Lower resolution variants skipped with `--prefer-highres`.
+    let mut skipped_variants = Vec::new();
 
-        let mut entries = Vec::new();
-        for entry in dir_entries {
-            entries.push(entry);
+    if opts.stdin {
+        // `--name` is required with `--stdin`.
+        let name = Path::new(opts.name.as_deref().unwrap_or_default());
+        let mut data = Vec::new();
+        if io::stdin().read_to_end(&mut data).is_err() {
+            println!("{}", "Failed to read data from stdin.".red());
+            std::process::exit(1);
         }
 
-        if opts.parallelize {
-            entries.into_par_iter().for_each(|entry| {
-                let file_path = entry.unwrap().path();
-                if process_file(&file_path, &out_dir, true, &opts).is_ok() {
-                    let _ = found_one.compare_exchange_weak(
-                        false,
-                        true,
-                        Ordering::AcqRel,
-                        Ordering::Acquire,
-                    );
+        if process_data(&data, name, &out_dir, false, &opts, &shared).is_err() {
+            println!(
+                "{}",
+                "Data from stdin isn't a valid `_tex.sc`, `.sctx`, `.csv` or `.sc` file!"
+                    .red()
+                    .bold()
+            );
+            std::process::exit(1);
+        }
+    } else if path.is_dir() {
+        let found_one = AtomicBool::new(false);
+        let mut entries = Vec::new();
+        let read = if opts.recursive {
+            // The output directory exists at this point, so it can be canonicalized.
+            let skip =
+                fs::canonicalize(&out_dir).expect("Expected to access the output directory.");
+            collect_files(&path, &skip, &mut entries)
+        } else {
+            fs::read_dir(&path).and_then(|dir_entries| {
+                for entry in dir_entries {
+                    entries.push(entry?.path());
                 }
+                Ok(())
             })
+        };
+
+        if read.is_err() {
+            println!(
+                "{}",
+                format!(
+                    "Failed to read contents of {} directory/folder.",
+                    path.to_str()
+                        .expect("Expected path to be valid UTF-8.")
+                        .red()
+                )
+                .red()
+            );
+            std::process::exit(1);
+        }
+
+        // Files in subdirectories are extracted to the same subdirectories of
+        // the output directory.
+        let file_out_dir =
+            |file_path: &Path| match file_path.parent().and_then(|p| p.strip_prefix(&path).ok()) {
+                Some(relative) => out_dir.join(relative),
+                None => out_dir.clone(),
+            };
+
+        if opts.prefer_highres {
+            skipped_variants = prefer_highres(&mut entries);
+        }
+
+        let bar = if opts.progress {
+            let bar = ProgressBar::new(entries.len() as u64);
+            if let Ok(style) =
+                ProgressStyle::default_bar().template("{bar:40} {pos}/{len} files ({eta})")
+            {
+                bar.set_style(style);
+            }
+            bar
         } else {
-            for entry in entries {
-                let file_path = entry.unwrap().path();
-                if process_file(&file_path, &out_dir, false, &opts).is_ok() {
-                    let _ = found_one.compare_exchange_weak(
-                        false,
-                        true,
-                        Ordering::AcqRel,
-                        Ordering::Acquire,
-                    );
+            ProgressBar::hidden()
+        };
+
+        let process = |file_path: &Path, parallelize: bool| {
+            if process_file(
+                file_path,
+                &file_out_dir(file_path),
+                parallelize,
+                &opts,
+                &shared,
+            )
+            .is_ok()
+            {
+                let _ = found_one.compare_exchange_weak(
+                    false,
+                    true,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                );
+            }
+            bar.inc(1);
+        };
+
+        // Sprites are cut from the images of `_tex.sc` files, so `sc` files
+        // are processed after them.
+        let (sc_entries, entries): (Vec<_>, Vec<_>) = entries
+            .into_iter()
+            .partition(|file_path| is_sc_file(file_path));
+        for entries in [entries, sc_entries] {
+            if opts.parallelize {
+                entries
+                    .into_par_iter()
+                    .for_each(|file_path| process(&file_path, true));
+            } else {
+                for file_path in entries {
+                    // The library doesn't print the name of every file when
+                    // it's told they are processed in parallel.
+                    process(&file_path, opts.progress);
                 }
             }
         }
+        bar.finish();
 
         if !found_one.into_inner() {
             println!(
                 "{}",
-                "No valid `_tex.sc` or `.csv` file in the given directory!"
+                "No valid `_tex.sc`, `.sctx` or `.csv` file in the given directory!"
                     .red()
                     .bold()
             );
             std::process::exit(1);
         }
     } else if path.is_file() {
-        let _ = process_file(&path, &out_dir, false, &opts);
+        let _ = process_file(&path, &out_dir, false, &opts, &shared);
+    }
+
+    // Files extracted in parallel finish in any order.
+    let mut saved = shared
+        .saved
+        .into_inner()
+        .expect("Expected the list of saved images to be usable.");
+    saved.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    if let Some(sheet_path) = &opts.contact_sheet {
+        let images: Vec<_> = saved
+            .iter()
+            .flat_map(|(_, extraction)| extraction.sprites.iter().map(|s| s.path.clone()))
+            .collect();
+
+        if images.is_empty() {
+            println!("{}", "No images were saved for a contact sheet.".yellow());
+        } else {
+            match save_contact_sheets(&images, sheet_path, &ContactSheetOptions::default()) {
+                Ok(sheets) => println!(
+                    "\nSaved {} image(s) on {} contact sheet(s).",
+                    images.len().to_string().cyan().bold(),
+                    sheets.len().to_string().cyan().bold()
+                ),
+                Err(e) => println!("\n{}", e.inner().red()),
+            }
+        }
+    }
+
+    if opts.html_index {
+        if saved.is_empty() {
+            println!("{}", "No images were saved for an HTML index.".yellow());
+        } else {
+            let index_path = out_dir.join("index.html");
+            match save_html_index(&saved, &index_path) {
+                Ok(()) => println!(
+                    "\nSaved the HTML index at {}.",
+                    index_path.display().to_string().cyan().bold()
+                ),
+                Err(e) => println!("\n{}", e.inner().red()),
+            }
+        }
+    }
+
+    if let Some(dedupe) = &shared.dedupe {
+        let duplicates = dedupe.duplicates();
+        if !duplicates.is_empty() {
+            let report_path = out_dir.join("duplicates.json");
+            let saved = serde_json::to_string_pretty(&duplicates)
+                .map_err(|e| e.to_string())
+                .and_then(|json| fs::write(&report_path, json).map_err(|e| e.to_string()));
+            match saved {
+                Ok(()) => println!(
+                    "\nFound {} duplicate sprite(s), listed in {}.",
+                    duplicates.len().to_string().cyan().bold(),
+                    report_path.display().to_string().cyan().bold()
+                ),
+                Err(e) => println!("\n{}", e.red()),
+            }
+        }
+    }
+
+    if !skipped_variants.is_empty() {
+        println!(
+            "\nSkipped {} lower resolution variant(s):",
+            skipped_variants.len().to_string().cyan().bold()
+        );
+        for (path, used) in &skipped_variants {
+            println!("{} (extracted {})", path.display(), used.display());
+        }
     }
 
     if created_out {
@@ -0,0 +1,228 @@
+//! Contact sheets: thumbnails of many images, tiled into a few large images.
+
+use crate::error::Error;
+use image::{imageops, ImageFormat, Rgba, RgbaImage};
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Glyphs of the caption font, 3 pixels wide and 5 tall.
+///
+/// Each row is 3 bits, the top row first and the leftmost pixel in the
+/// highest bit. Letters are uppercase.
+fn glyph(c: char) -> u16 {
+    match c.to_ascii_uppercase() {
+        '0' => 0b111_101_101_101_111,
+        '1' => 0b010_110_010_010_111,
+        '2' => 0b111_001_111_100_111,
+        '3' => 0b111_001_111_001_111,
+        '4' => 0b101_101_111_001_001,
+        '5' => 0b111_100_111_001_111,
+        '6' => 0b111_100_111_101_111,
+        '7' => 0b111_001_001_001_001,
+        '8' => 0b111_101_111_101_111,
+        '9' => 0b111_101_111_001_111,
+        'A' => 0b010_101_111_101_101,
+        'B' => 0b110_101_110_101_110,
+        'C' => 0b011_100_100_100_011,
+        'D' => 0b110_101_101_101_110,
+        'E' => 0b111_100_110_100_111,
+        'F' => 0b111_100_110_100_100,
+        'G' => 0b011_100_101_101_011,
+        'H' => 0b101_101_111_101_101,
+        'I' => 0b111_010_010_010_111,
+        'J' => 0b001_001_001_101_010,
+        'K' => 0b101_101_110_101_101,
+        'L' => 0b100_100_100_100_111,
+        'M' => 0b101_111_111_101_101,
+        'N' => 0b110_101_101_101_101,
+        'O' => 0b010_101_101_101_010,
+        'P' => 0b110_101_110_100_100,
+        'Q' => 0b010_101_101_110_011,
+        'R' => 0b110_101_110_101_101,
+        'S' => 0b011_100_010_001_110,
+        'T' => 0b111_010_010_010_010,
+        'U' => 0b101_101_101_101_011,
+        'V' => 0b101_101_101_010_010,
+        'W' => 0b101_101_111_111_101,
+        'X' => 0b101_101_010_101_101,
+        'Y' => 0b101_101_010_010_010,
+        'Z' => 0b111_001_010_100_111,
+        '_' => 0b000_000_000_000_111,
+        '-' => 0b000_000_111_000_000,
+        '.' => 0b000_000_000_000_010,
+        ' ' => 0,
+        _ => 0b111_001_010_000_010,
+    }
+}
+
+/// Width of a caption character, including the space after it.
+const CHAR_WIDTH: u32 = 4;
+
+/// Height of the caption below each thumbnail, including the space around it.
+const CAPTION_HEIGHT: u32 = 9;
+
+/// Space between the thumbnails and around the edges of a sheet.
+const SPACING: u32 = 4;
+
+const BACKGROUND: Rgba<u8> = Rgba([32, 32, 32, 255]);
+const CAPTION_COLOR: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+/// Draws `text` on `sheet` with its top-left corner at `x`, `y`, cutting it
+/// short to fit in `width` pixels.
+///
+/// Names of sprites differ at their ends, so the start of a long text is
+/// replaced with `..` instead.
+fn draw_caption(sheet: &mut RgbaImage, text: &str, x: u32, y: u32, width: u32) {
+    let max_chars = (width / CHAR_WIDTH) as usize;
+    let chars: Vec<_> = text.chars().collect();
+    let text: String = if chars.len() > max_chars {
+        let start = chars.len() - max_chars.saturating_sub(2);
+        "..".chars().chain(chars[start..].iter().copied()).collect()
+    } else {
+        text.to_string()
+    };
+
+    for (i, c) in text.chars().take(max_chars).enumerate() {
+        let glyph = glyph(c);
+        for row in 0..5 {
+            for column in 0..3 {
+                if (glyph >> (14 - row * 3 - column)) & 1 == 1 {
+                    sheet.put_pixel(x + i as u32 * CHAR_WIDTH + column, y + row, CAPTION_COLOR);
+                }
+            }
+        }
+    }
+}
+
+/// Options of [`save_contact_sheets`].
+///
+/// [`save_contact_sheets`]: ./fn.save_contact_sheets.html
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContactSheetOptions {
+    /// Largest width and height of a thumbnail. Smaller images aren't
+    /// enlarged.
+    pub thumbnail_size: u32,
+    /// Number of thumbnails in a row of a sheet.
+    pub columns: u32,
+    /// Number of rows of a sheet. Images that don't fit are placed on more
+    /// sheets.
+    pub rows: u32,
+}
+
+impl Default for ContactSheetOptions {
+    fn default() -> Self {
+        Self {
+            thumbnail_size: 128,
+            columns: 10,
+            rows: 10,
+        }
+    }
+}
+
+/// Returns the path of the `page`th sheet of `count`.
+///
+/// A single sheet is saved at `path`. More sheets are numbered, like
+/// `sheet_0.png`, `sheet_1.png` and so on.
+fn sheet_path(path: &Path, page: usize, count: usize) -> PathBuf {
+    if count <= 1 {
+        return path.to_path_buf();
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(extension) => {
+            path.with_file_name(format!("{}_{}.{}", stem, page, extension.to_string_lossy()))
+        }
+        None => path.with_file_name(format!("{}_{}", stem, page)),
+    }
+}
+
+/// Tiles thumbnails of the images at `images`, captioned with their file
+/// names, into PNG contact sheets saved at `path`.
+///
+/// The images are placed in the given order, `options.columns` by
+/// `options.rows` on each sheet. If they don't fit on one sheet, the sheets
+/// are numbered, like `sheet_0.png`, `sheet_1.png` and so on. Images that
+/// can't be read, like raw `.rgba` images, leave their thumbnails empty.
+///
+/// The paths of the saved sheets are returned.
+///
+/// ## Errors
+///
+/// If a size in `options` is zero, [`Error::Other`] is returned.
+/// [`Error::IoError`] is returned if a sheet can't be saved.
+///
+/// [`Error::Other`]: ./error/enum.Error.html#variant.Other
+/// [`Error::IoError`]: ./error/enum.Error.html#variant.IoError
+pub fn save_contact_sheets(
+    images: &[PathBuf],
+    path: &Path,
+    options: &ContactSheetOptions,
+) -> Result<Vec<PathBuf>, Error> {
+    let ContactSheetOptions {
+        thumbnail_size: size,
+        columns,
+        rows,
+    } = *options;
+    if size == 0 || columns == 0 || rows == 0 {
+        return Err(Error::Other(
+            "Contact sheet sizes must not be zero.".to_string(),
+        ));
+    }
+
+    let per_sheet = (columns * rows) as usize;
+    let count = images.len().div_ceil(per_sheet);
+    let cell_width = size + SPACING;
+    let cell_height = size + CAPTION_HEIGHT + SPACING;
+
+    let mut paths = Vec::new();
+    for (page, images) in images.chunks(per_sheet).enumerate() {
+        // Only the thumbnails of a single sheet are held at a time.
+        let thumbnails: Vec<_> = images
+            .par_iter()
+            .map(|path| {
+                let image = image::open(path).ok()?.to_rgba8();
+                let (width, height) = image.dimensions();
+                let scale = (size as f64 / width.max(height) as f64).min(1.0);
+                let scaled = |side: u32| ((side as f64 * scale).round() as u32).max(1);
+
+                Some(imageops::thumbnail(&image, scaled(width), scaled(height)))
+            })
+            .collect();
+
+        let sheet_rows = (images.len() as u32).div_ceil(columns);
+        let sheet_columns = columns.min(images.len() as u32);
+        let mut sheet = RgbaImage::from_pixel(
+            sheet_columns * cell_width + SPACING,
+            sheet_rows * cell_height + SPACING,
+            BACKGROUND,
+        );
+
+        for (i, (path, thumbnail)) in images.iter().zip(thumbnails).enumerate() {
+            let x = SPACING + (i as u32 % columns) * cell_width;
+            let y = SPACING + (i as u32 / columns) * cell_height;
+
+            // Thumbnails are centered in their cells.
+            if let Some(thumbnail) = thumbnail {
+                let (width, height) = thumbnail.dimensions();
+                imageops::overlay(
+                    &mut sheet,
+                    &thumbnail,
+                    x + (size - width) / 2,
+                    y + (size - height) / 2,
+                );
+            }
+
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            draw_caption(&mut sheet, &name, x, y + size + 2, size);
+        }
+
+        let sheet_path = sheet_path(path, page, count);
+        sheet
+            .save_with_format(&sheet_path, ImageFormat::Png)
+            .map_err(|_| Error::IoError("Failed to save contact sheet!".to_string()))?;
+        paths.push(sheet_path);
+    }
+
+    Ok(paths)
+}
@@ -1,31 +1,106 @@
 use super::error::Error;
-use byteorder::{LittleEndian, ReadBytesExt};
-#[cfg(any(target_os = "macos", target_os = "linux"))]
+#[cfg(all(
+    feature = "lzham-rust",
+    not(all(
+        feature = "lzham-native",
+        any(target_os = "macos", target_os = "linux")
+    ))
+))]
+use super::lzham_decoder;
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
+use lz4_flex::frame::FrameDecoder;
+#[cfg(all(
+    feature = "lzham-native",
+    any(target_os = "macos", target_os = "linux")
+))]
 use lzham::decompress::{decompress_with_options, DecompressionOptions};
-use lzma_rs::lzma_decompress;
-use std::io::{Cursor, Read};
+use lzma_rs::{
+    compress::{Options, UnpackedSize},
+    lzma_compress_with_options, lzma_decompress, xz_decompress,
+};
+use std::{
+    convert::TryFrom,
+    io::{self, Cursor, Read, Seek, SeekFrom, Write},
+};
 
-/// Wrapper for reading data from stream.
-pub(crate) struct Reader<'a> {
-    stream: Cursor<&'a [u8]>,
+/// Wrapper for reading the fields of Supercell's files from a stream.
+///
+/// The stream can be any seekable reader, like a `BufReader<File>`, see
+/// [`from_stream`]. Only the bytes that are read are loaded. In-memory data is
+/// read from a `Cursor<&[u8]>`, see [`new`], which can also lend parts of the
+/// data without copying them, see [`try_slice`].
+///
+/// The reader keeps track of the bytes left in the stream, so every read
+/// returns [`Error::UnexpectedEof`] instead of reading past its end.
+///
+/// [`from_stream`]: #method.from_stream
+/// [`new`]: #method.new
+/// [`try_slice`]: #method.try_slice
+/// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+pub struct Reader<R> {
+    stream: R,
     bytes_left: usize,
 }
 
-impl<'a> Reader<'a> {
-    /// Create new `Reader` instance from a stream.
+impl<'a> Reader<Cursor<&'a [u8]>> {
+    /// Create new `Reader` instance from in-memory data.
     pub fn new(stream: Cursor<&'a [u8]>) -> Self {
-        let bytes_left = stream.get_ref().len();
+        let bytes_left = stream
+            .get_ref()
+            .len()
+            .saturating_sub(stream.position() as usize);
 
         Self { stream, bytes_left }
     }
 
+    /// Borrow exact number of bytes from the stream, without copying them.
+    ///
+    /// Like [`try_read`], [`Error::UnexpectedEof`] is returned if the stream
+    /// ends first.
+    ///
+    /// [`try_read`]: #method.try_read
+    /// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+    pub fn try_slice(&mut self, size: usize) -> Result<&'a [u8], Error> {
+        self.consume(size)?;
+
+        let start = self.stream.position() as usize;
+        self.stream.set_position((start + size) as u64);
+
+        let data: &'a [u8] = self.stream.get_ref();
+        Ok(&data[start..start + size])
+    }
+}
+
+impl<R: Read + Seek> Reader<R> {
+    /// Create new `Reader` instance from a seekable stream.
+    ///
+    /// The bytes from the current position to the end of the stream are
+    /// read. [`Error::IoError`] is returned if seeking fails.
+    ///
+    /// [`Error::IoError`]: ./error/enum.Error.html#variant.IoError
+    pub fn from_stream(mut stream: R) -> Result<Self, Error> {
+        let position = stream.stream_position()?;
+        let end = stream.seek(SeekFrom::End(0))?;
+        stream.seek(SeekFrom::Start(position))?;
+
+        let bytes_left = usize::try_from(end.saturating_sub(position))
+            .map_err(|_| Error::Other("Stream is too large to be read.".to_string()))?;
+
+        Ok(Self { stream, bytes_left })
+    }
+
     /// Bytes left in the data stream.
     pub fn len(&self) -> usize {
         self.bytes_left
     }
 
+    /// Returns `true` if no bytes are left in the data stream.
+    pub fn is_empty(&self) -> bool {
+        self.bytes_left == 0
+    }
+
     /// Read exact number of bytes from the stream.
-    pub fn read(&mut self, size: usize) -> Vec<u8> {
+    pub(crate) fn read(&mut self, size: usize) -> Vec<u8> {
         if size > self.bytes_left {
             self.bytes_left = 0;
         } else {
@@ -45,7 +120,7 @@ impl<'a> Reader<'a> {
     }
 
     /// Read one byte from the stream.
-    pub fn read_byte(&mut self) -> u8 {
+    pub(crate) fn read_byte(&mut self) -> u8 {
         if 1 > self.bytes_left {
             self.bytes_left = 0;
         } else {
@@ -56,7 +131,7 @@ impl<'a> Reader<'a> {
     }
 
     /// Read an unsigned 16-bit little-endian integer from the stream.
-    pub fn read_uint16(&mut self) -> u16 {
+    pub(crate) fn read_uint16(&mut self) -> u16 {
         if 2 > self.bytes_left {
             self.bytes_left = 0;
         } else {
@@ -67,7 +142,7 @@ impl<'a> Reader<'a> {
     }
 
     /// Read an unsigned 32-bit little-endian integer from the stream.
-    pub fn read_uint32(&mut self) -> u32 {
+    pub(crate) fn read_uint32(&mut self) -> u32 {
         if 4 > self.bytes_left {
             self.bytes_left = 0;
         } else {
@@ -78,7 +153,7 @@ impl<'a> Reader<'a> {
     }
 
     /// Read an signed 16-bit little-endian integer from the stream.
-    pub fn read_int16(&mut self) -> i16 {
+    pub(crate) fn read_int16(&mut self) -> i16 {
         if 2 > self.bytes_left {
             self.bytes_left = 0;
         } else {
@@ -89,7 +164,7 @@ impl<'a> Reader<'a> {
     }
 
     /// Read an signed 32-bit little-endian integer from the stream.
-    pub fn read_int32(&mut self) -> i32 {
+    pub(crate) fn read_int32(&mut self) -> i32 {
         if 4 > self.bytes_left {
             self.bytes_left = 0;
         } else {
@@ -100,7 +175,7 @@ impl<'a> Reader<'a> {
     }
 
     /// Read `length` bytes from the stream and return the output as a `String`.
-    pub fn read_string(&mut self, length: usize) -> String {
+    pub(crate) fn read_string(&mut self, length: usize) -> String {
         if length > self.bytes_left {
             self.bytes_left = 0;
         } else {
@@ -109,66 +184,748 @@ impl<'a> Reader<'a> {
 
         String::from_utf8_lossy(self.read(length).as_slice()).to_string()
     }
+
+    /// Marks `size` bytes as read, or returns [`Error::UnexpectedEof`] if fewer
+    /// bytes are left in the stream.
+    ///
+    /// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+    fn consume(&mut self, size: usize) -> Result<(), Error> {
+        if size > self.bytes_left {
+            return Err(Error::UnexpectedEof(format!(
+                "Expected {} more byte(s), but only {} are left.",
+                size, self.bytes_left
+            )));
+        }
+
+        self.bytes_left -= size;
+        Ok(())
+    }
+
+    /// Read exact number of bytes from the stream, or return
+    /// [`Error::UnexpectedEof`] if the stream ends first.
+    ///
+    /// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+    pub fn try_read(&mut self, size: usize) -> Result<Vec<u8>, Error> {
+        self.consume(size)?;
+
+        let mut buf = vec![0; size];
+        self.stream.read_exact(&mut buf)?;
+
+        Ok(buf)
+    }
+
+    /// Skip `size` bytes of the stream by seeking past them, without reading
+    /// them.
+    ///
+    /// Like [`read`], skipping past the end of the stream stops at its end.
+    ///
+    /// [`read`]: #method.read
+    pub(crate) fn skip(&mut self, size: usize) {
+        let size = size.min(self.bytes_left);
+        self.bytes_left -= size;
+
+        self.stream
+            .seek(SeekFrom::Current(size as i64))
+            .unwrap_or_default();
+    }
+
+    /// Skip `size` bytes of the stream by seeking past them, without reading
+    /// them, or return [`Error::UnexpectedEof`] if the stream ends first.
+    ///
+    /// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+    pub fn try_skip(&mut self, size: usize) -> Result<(), Error> {
+        self.consume(size)?;
+        self.stream.seek(SeekFrom::Current(size as i64))?;
+
+        Ok(())
+    }
+
+    /// Read one byte from the stream, or return [`Error::UnexpectedEof`] if
+    /// the stream has ended.
+    ///
+    /// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+    pub fn try_read_byte(&mut self) -> Result<u8, Error> {
+        self.consume(1)?;
+
+        Ok(self.stream.read_u8()?)
+    }
+
+    /// Read an unsigned 16-bit little-endian integer from the stream, or return
+    /// [`Error::UnexpectedEof`] if the stream ends first.
+    ///
+    /// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+    pub fn try_read_uint16(&mut self) -> Result<u16, Error> {
+        self.consume(2)?;
+
+        Ok(self.stream.read_u16::<LittleEndian>()?)
+    }
+
+    /// Read an unsigned 32-bit little-endian integer from the stream, or return
+    /// [`Error::UnexpectedEof`] if the stream ends first.
+    ///
+    /// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+    pub fn try_read_uint32(&mut self) -> Result<u32, Error> {
+        self.consume(4)?;
+
+        Ok(self.stream.read_u32::<LittleEndian>()?)
+    }
+
+    /// Read an unsigned 16-bit big-endian integer from the stream, or return
+    /// [`Error::UnexpectedEof`] if the stream ends first.
+    ///
+    /// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+    pub fn try_read_uint16_be(&mut self) -> Result<u16, Error> {
+        self.consume(2)?;
+
+        Ok(self.stream.read_u16::<BigEndian>()?)
+    }
+
+    /// Read an unsigned 32-bit big-endian integer from the stream, or return
+    /// [`Error::UnexpectedEof`] if the stream ends first.
+    ///
+    /// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+    pub fn try_read_uint32_be(&mut self) -> Result<u32, Error> {
+        self.consume(4)?;
+
+        Ok(self.stream.read_u32::<BigEndian>()?)
+    }
+
+    /// Read a signed 32-bit big-endian integer from the stream, or return
+    /// [`Error::UnexpectedEof`] if the stream ends first.
+    ///
+    /// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+    pub fn try_read_int32_be(&mut self) -> Result<i32, Error> {
+        self.consume(4)?;
+
+        Ok(self.stream.read_i32::<BigEndian>()?)
+    }
+
+    /// Read a 32-bit little-endian IEEE float from the stream, or return
+    /// [`Error::UnexpectedEof`] if the stream ends first.
+    ///
+    /// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+    pub fn try_read_f32(&mut self) -> Result<f32, Error> {
+        self.consume(4)?;
+
+        Ok(self.stream.read_f32::<LittleEndian>()?)
+    }
+
+    /// Read a 64-bit little-endian IEEE float from the stream, or return
+    /// [`Error::UnexpectedEof`] if the stream ends first.
+    ///
+    /// [`Error::UnexpectedEof`]: ./error/enum.Error.html#variant.UnexpectedEof
+    pub fn try_read_f64(&mut self) -> Result<f64, Error> {
+        self.consume(8)?;
+
+        Ok(self.stream.read_f64::<LittleEndian>()?)
+    }
+}
+
+/// Compression codec of `_tex.sc` or `.csv` data, found by [`detect_codec`].
+///
+/// [`detect_codec`]: ./fn.detect_codec.html
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Codec {
+    /// LZHAM, which starts with `SCLZ`.
+    Lzham,
+    /// zstd, which starts with `28 B5 2F FD`.
+    Zstd,
+    /// A standard `.xz` container, which starts with `FD 37 7A 58 5A 00`.
+    Xz,
+    /// An LZ4 frame, which starts with `04 22 4D 18`, the little-endian
+    /// `0x184D2204` magic.
+    Lz4,
+    /// LZMA, which starts with a properties byte below 225 and a dictionary
+    /// size that LZMA encoders write.
+    Lzma,
+    /// Anything else. [`decompress`] still tries to decompress it as LZMA.
+    ///
+    /// [`decompress`]: ./fn.decompress.html
+    Uncompressed,
+}
+
+/// Returns the compression codec of `raw_data` from its magic bytes, without
+/// decompressing it.
+///
+/// This tells apart data that this build can't decompress from corrupt data.
+/// See [`decompress`] for the magic bytes of each codec.
+///
+/// [`decompress`]: ./fn.decompress.html
+pub fn detect_codec(raw_data: &[u8]) -> Codec {
+    if raw_data.starts_with(b"SCLZ") {
+        Codec::Lzham
+    } else if raw_data.starts_with(&[40, 181, 47, 253]) {
+        Codec::Zstd
+    } else if raw_data.starts_with(&[0xFD, b'7', b'z', b'X', b'Z', 0]) {
+        Codec::Xz
+    } else if raw_data.starts_with(&[0x04, 0x22, 0x4D, 0x18]) {
+        Codec::Lz4
+    } else if is_lzma_header(raw_data) {
+        Codec::Lzma
+    } else {
+        Codec::Uncompressed
+    }
+}
+
+/// Returns `true` if `raw_data` starts with an LZMA header: a properties byte
+/// for valid `lc`, `lp` and `pb` values, which most files leave at the default
+/// `0x5D`, and a plausible dictionary size.
+///
+/// Encoders write dictionary sizes of the form `2^n` or `3 * 2^n`, or whole
+/// MiBs from 4 MiB, between 4 KiB and 1.5 GiB. Checking it tells LZMA data
+/// apart from other data that happens to start with a small byte.
+fn is_lzma_header(raw_data: &[u8]) -> bool {
+    let (props, dict_size) = match raw_data.get(..5) {
+        Some(header) => (
+            header[0],
+            u32::from_le_bytes([header[1], header[2], header[3], header[4]]),
+        ),
+        None => return false,
+    };
+
+    // `(pb * 5 + lp) * 9 + lc`, with `lc` < 9, `lp` < 5 and `pb` < 5.
+    props < 9 * 5 * 5
+        && (1 << 12..=3 << 29).contains(&dict_size)
+        && (dict_size.is_power_of_two()
+            || (dict_size % 3 == 0 && (dict_size / 3).is_power_of_two())
+            || dict_size % (1 << 20) == 0)
 }
 
-/// Decompresses `.tex_sc` or `.csv` data.
+/// Decompresses `_tex.sc` or `.csv` data and returns the decompressed bytes.
 ///
-/// Before decompressing the data using LZMA decompression,
-/// four `\x00` bytes are added to `raw_data` after the eigth index.
-/// A `Cursor` containing the transformed raw data is returned.
+/// The codec is picked by the magic bytes at the start of `raw_data`:
 ///
-/// `_tex.sc` files found in Supercell's games require the header
-/// to be removed before decompression.
+/// - `SCLZ`: LZHAM, followed by the dictionary size (log2, one byte) and the
+///   decompressed size (little-endian `u32`). A decoder written in Rust, of
+///   the default `lzham-rust` feature, decodes it. On Linux and macOS, the
+///   opt-in `lzham-native` feature decodes it with the C++ LZHAM library
+///   instead.
+/// - `28 B5 2F FD`: zstd.
+/// - `FD 37 7A 58 5A 00`: a standard `.xz` container, which some tools wrap
+///   the data in.
+/// - `04 22 4D 18`: an LZ4 frame, used by some repacked files.
+/// - Anything else: LZMA.
+///
+/// Supercell's LZMA data has a 9-byte header: the properties byte, the
+/// dictionary size (`u32`) and the decompressed size as a *32-bit* integer.
+/// Standard LZMA headers use a 64-bit size, so four `\x00` bytes are added to
+/// `raw_data` after the eighth index before decompressing. Pass such data
+/// as it is, without padding it yourself.
+///
+/// `_tex.sc` files found in Supercell's games require the header (`SC`,
+/// version and hash) to be removed before decompression. `.csv` files can be
+/// passed as they are.
 ///
 /// If the decompression fails due to any reason,
-/// [`Error::DecompressionError`] is returned.
+/// [`Error::DecompressionError`] is returned. For LZMA and LZHAM data, its
+/// message holds the offset at which decompression stopped and the expected
+/// size of the output.
 ///
 /// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
-pub(crate) fn decompress(raw_data: &[u8], output: &mut Vec<u8>) -> Result<(), Error> {
-    // let mut buf: Vec<u8> = Vec::new();
-
-    if raw_data[..4] == [83, 67, 76, 90] {
-        #[cfg(not(any(target_os = "macos", target_os = "linux")))]
-        {
-            return Err(Error::DecompressionError(
-                "`lzham` compression is not supported for your operating system yet".to_string(),
-            ));
-        }
-
-        #[cfg(any(target_os = "macos", target_os = "linux"))]
-        {
-            // We need to do LZHAM decompression.
-            let dict_size = (&raw_data[4..5]).read_u8().unwrap_or(0);
-            let uncompressed_size =
-                (&raw_data[5..9]).read_u32::<LittleEndian>().unwrap_or(0) as usize;
-
-            let options = DecompressionOptions {
-                dict_size_log2: dict_size as u32,
-                ..Default::default()
-            };
+pub fn decompress(raw_data: &[u8]) -> Result<Vec<u8>, Error> {
+    decompress_with_limit(raw_data, DEFAULT_MAX_DECOMPRESSED_SIZE)
+}
 
-            let status =
-                decompress_with_options(&mut &raw_data[9..], output, uncompressed_size, options);
-            if !status.is_success() {
-                return Err(Error::DecompressionError(
-                    "Failed to decompress file".to_string(),
-                ));
-            }
+/// Largest size of decompressed data, in bytes, unless an option says
+/// otherwise.
+pub(crate) const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 512 * 1024 * 1024;
+
+/// Like [`decompress`], but fails once the output is larger than `limit`
+/// bytes, instead of 512 MiB.
+///
+/// Corrupt or hostile files can declare, or decompress to, far more data than
+/// any real file holds. Decompression stops when the limit is crossed, so no
+/// more memory than the limit is used for the output.
+///
+/// ## Errors
+///
+/// The errors are the same as those of [`decompress`]. If the output is
+/// larger than `limit`, [`Error::DecompressionError`] is returned with the
+/// limit and the size that was declared or reached.
+///
+/// [`decompress`]: ./fn.decompress.html
+/// [`Error::DecompressionError`]: ./error/enum.Error.html#variant.DecompressionError
+pub fn decompress_with_limit(raw_data: &[u8], limit: usize) -> Result<Vec<u8>, Error> {
+    let settings = Decompression {
+        limit,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    decompress_into(raw_data, &mut output, 0, &settings)?;
+
+    Ok(output)
+}
+
+/// How data is decompressed, from the options of the extractors.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Decompression<'a> {
+    /// Largest size of the output, in bytes.
+    pub limit: usize,
+    /// Dictionary that zstd data was compressed with, if any.
+    pub zstd_dictionary: Option<&'a [u8]>,
+}
+
+impl Default for Decompression<'_> {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_MAX_DECOMPRESSED_SIZE,
+            zstd_dictionary: None,
+        }
+    }
+}
+
+/// A writer that passes the output on to `inner` until it would have written
+/// more than `limit` bytes in all, and fails instead.
+struct LimitedWriter<'a> {
+    inner: &'a mut dyn Write,
+    /// Bytes of the whole output written so far, including those written
+    /// before this writer was made.
+    written: usize,
+    limit: usize,
+    /// The size the output would have had when the limit was crossed.
+    exceeded: Option<usize>,
+}
+
+impl<'a> LimitedWriter<'a> {
+    fn new(inner: &'a mut dyn Write, written: usize, limit: usize) -> Self {
+        Self {
+            inner,
+            written,
+            limit,
+            exceeded: None,
         }
-    } else if raw_data[..4] == [40, 181, 47, 253] {
-        if let Err(_) = zstd::stream::copy_decode(raw_data, output) {
-            return Err(Error::DecompressionError(
-                "Failed to decompress file".to_string(),
-            ));
+    }
+}
+
+impl Write for LimitedWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let size = self.written + buf.len();
+        if size > self.limit {
+            self.exceeded = Some(size);
+            return Err(io::Error::other("Decompressed data is too large."));
         }
+
+        self.inner.write_all(buf)?;
+        self.written = size;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns the error of decompressed data of `size` bytes, larger than
+/// `limit`. `declared` tells whether the size is that in a header, or the size
+/// reached when decompression stopped.
+fn too_large(size: usize, limit: usize, declared: bool) -> Error {
+    Error::DecompressionError(format!(
+        "Decompressed data ({}{} bytes) is larger than the limit of {} bytes.",
+        if declared { "" } else { "at least " },
+        size,
+        limit
+    ))
+}
+
+/// Returns the dictionary ID in the header of the zstd frame at the start of
+/// `data`, or `None` if it doesn't name one.
+fn zstd_dictionary_id(data: &[u8]) -> Option<u32> {
+    let descriptor = *data.get(4)?;
+    let size = [0, 1, 2, 4][(descriptor & 0b11) as usize];
+    // The window descriptor is left out of single-segment frames.
+    let start = if descriptor & 0b10_0000 == 0 { 6 } else { 5 };
+
+    let mut id = [0; 4];
+    id[..size].copy_from_slice(data.get(start..start + size)?);
+    Some(u32::from_le_bytes(id)).filter(|&id| id != 0)
+}
+
+/// Decompresses the zstd data `data`, compressed with `dictionary` if it is
+/// given.
+#[cfg(feature = "zstd")]
+fn decompress_zstd(
+    data: &[u8],
+    dictionary: Option<&[u8]>,
+    mut output: &mut dyn Write,
+) -> io::Result<()> {
+    match dictionary {
+        Some(dictionary) => zstd::stream::read::Decoder::with_dictionary(data, dictionary)
+            .and_then(|mut decoder| io::copy(&mut decoder, &mut output).map(|_| ())),
+        None => zstd::stream::copy_decode(data, output),
+    }
+}
+
+/// Fails to decompress the zstd data `data`, because no zstd decoder is built.
+#[cfg(not(feature = "zstd"))]
+fn decompress_zstd(
+    _data: &[u8],
+    _dictionary: Option<&[u8]>,
+    _output: &mut dyn Write,
+) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "no zstd decoder is built in; enable the `zstd` feature of sc_extract",
+    ))
+}
+
+/// Decompresses the LZHAM stream `data` with the C++ library. Errors hold the
+/// offset in `data` at which decompression stopped and the reason.
+#[cfg(all(
+    feature = "lzham-native",
+    any(target_os = "macos", target_os = "linux")
+))]
+fn decompress_lzham(
+    data: &[u8],
+    dict_size_log2: u8,
+    size: usize,
+    mut output: &mut dyn Write,
+) -> Result<(), (usize, String)> {
+    let options = DecompressionOptions {
+        dict_size_log2: dict_size_log2 as u32,
+        ..Default::default()
+    };
+
+    let mut input = data;
+    let status = decompress_with_options(&mut input, &mut output, size, options);
+    if status.is_success() {
+        Ok(())
     } else {
-        let data = [&raw_data[0..9], &[b'\x00'; 4], &raw_data[9..]].concat();
+        Err((
+            data.len() - input.len(),
+            "the LZHAM library couldn't decode it".to_string(),
+        ))
+    }
+}
 
-        if let Err(e) = lzma_decompress(&mut data.as_slice(), output) {
-            return Err(Error::DecompressionError(format!(
-                "Failed to decompress file: {}",
+/// Decompresses the LZHAM stream `data` with the decoder written in Rust, when
+/// the C++ library isn't built.
+#[cfg(all(
+    feature = "lzham-rust",
+    not(all(
+        feature = "lzham-native",
+        any(target_os = "macos", target_os = "linux")
+    ))
+))]
+fn decompress_lzham(
+    data: &[u8],
+    dict_size_log2: u8,
+    size: usize,
+    output: &mut dyn Write,
+) -> Result<(), (usize, String)> {
+    lzham_decoder::decompress(data, dict_size_log2, size, output).map_err(|e| (e.offset, e.reason))
+}
+
+/// Fails to decompress the LZHAM stream `data`, because no LZHAM decoder is
+/// built.
+#[cfg(not(any(
+    feature = "lzham-rust",
+    all(
+        feature = "lzham-native",
+        any(target_os = "macos", target_os = "linux")
+    )
+)))]
+fn decompress_lzham(
+    _data: &[u8],
+    _dict_size_log2: u8,
+    _size: usize,
+    _output: &mut dyn Write,
+) -> Result<(), (usize, String)> {
+    Err((
+        0,
+        "no LZHAM decoder is built in; enable the `lzham-rust` feature of sc_extract".to_string(),
+    ))
+}
+
+/// Decompresses `raw_data` like [`decompress`], writing the output to
+/// `output` as it is decompressed, and returns its size.
+///
+/// `start` is the number of bytes already written to `output`, by earlier
+/// sections. The whole output must not be larger than `settings.limit` bytes.
+///
+/// [`decompress`]: ./fn.decompress.html
+fn decompress_into(
+    raw_data: &[u8],
+    output: &mut dyn Write,
+    start: usize,
+    settings: &Decompression,
+) -> Result<usize, Error> {
+    if raw_data.len() < 9 {
+        return Err(Error::DecompressionError(
+            "Size of data is too small".to_string(),
+        ));
+    }
+
+    // Errors count the output of this data only.
+    let limit = settings.limit;
+    let mut writer = LimitedWriter::new(output, start, limit);
+
+    let codec = detect_codec(raw_data);
+    let result = if codec == Codec::Lzham {
+        let dict_size = (&raw_data[4..5]).read_u8().unwrap_or(0);
+        let uncompressed_size = (&raw_data[5..9]).read_u32::<LittleEndian>().unwrap_or(0) as usize;
+
+        if start + uncompressed_size > limit {
+            return Err(too_large(start + uncompressed_size, limit, true));
+        }
+
+        decompress_lzham(&raw_data[9..], dict_size, uncompressed_size, &mut writer).map_err(|(offset, reason)| {
+            format!(
+                "Failed to decompress LZHAM data at byte {} of {} of the compressed data, after {} of {} byte(s) of output: {}.",
+                9 + offset,
+                raw_data.len(),
+                writer.written - start,
+                uncompressed_size,
+                reason
+            )
+        })
+    } else if codec == Codec::Zstd {
+        decompress_zstd(raw_data, settings.zstd_dictionary, &mut writer).map_err(|e| {
+            // Data compressed with a dictionary can't be told apart from
+            // corrupt data, unless its frame names the dictionary.
+            let hint = match (settings.zstd_dictionary, zstd_dictionary_id(raw_data)) {
+                _ if e.kind() == io::ErrorKind::Unsupported => String::new(),
+                (Some(_), _) => String::new(),
+                (None, Some(id)) => format!(
+                    " The data needs zstd dictionary {}, which wasn't given.",
+                    id
+                ),
+                (None, None) => {
+                    " The data may need a zstd dictionary, which wasn't given.".to_string()
+                }
+            };
+            format!(
+                "Failed to decompress zstd data, after {} byte(s) of output: {}.{}",
+                writer.written - start,
+                e,
+                hint
+            )
+        })
+    } else if codec == Codec::Xz {
+        xz_decompress(&mut Cursor::new(raw_data), &mut writer).map_err(|e| {
+            format!(
+                "Failed to decompress xz data, after {} byte(s) of output: {}",
+                writer.written - start,
                 e
-            )));
+            )
+        })
+    } else if codec == Codec::Lz4 {
+        let mut decoder = FrameDecoder::new(raw_data);
+        io::copy(&mut decoder, &mut writer)
+            .map(|_| ())
+            .map_err(|e| {
+                format!(
+                    "Failed to decompress LZ4 data, after {} byte(s) of output: {}",
+                    writer.written - start,
+                    e
+                )
+            })
+    } else {
+        let uncompressed_size = (&raw_data[5..9]).read_u32::<LittleEndian>().unwrap_or(0);
+        if start + uncompressed_size as usize > limit {
+            return Err(too_large(start + uncompressed_size as usize, limit, true));
+        }
+
+        // The padding is read between the header and the rest of the data,
+        // without copying them.
+        let mut rest = &raw_data[9..];
+        let mut input = (&raw_data[0..9]).chain(&[b'\x00'; 4][..]).chain(&mut rest);
+        lzma_decompress(&mut input, &mut writer).map_err(|e| {
+            let offset = raw_data.len() - rest.len();
+            format!(
+                "Failed to decompress LZMA data at byte {} of {} of the compressed data, after {} of {} byte(s) of output: {}",
+                offset.min(raw_data.len()),
+                raw_data.len(),
+                writer.written - start,
+                uncompressed_size,
+                e
+            )
+        })
+    };
+
+    match (result, writer.exceeded) {
+        (_, Some(size)) => Err(too_large(size, limit, false)),
+        (Err(e), None) => Err(Error::DecompressionError(e)),
+        (Ok(()), None) => Ok(writer.written - start),
+    }
+}
+
+/// Compresses `data` with LZMA, the way Supercell compresses its files.
+///
+/// The output can be decompressed with [`decompress`]. Like Supercell's files,
+/// its header holds the size of `data` as a 32-bit integer, not the usual
+/// 64-bit one. The encoder is simple, so the output is not much smaller than
+/// `data`.
+///
+/// ## Error
+///
+/// If `data` is larger than 4 GiB, [`Error::Other`] is returned.
+///
+/// [`decompress`]: ./fn.decompress.html
+/// [`Error::Other`]: ./error/enum.Error.html#variant.Other
+pub fn compress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    if data.len() > u32::MAX as usize {
+        return Err(Error::Other(format!(
+            "Data ({} bytes) is too large to compress.",
+            data.len()
+        )));
+    }
+
+    let options = Options {
+        unpacked_size: UnpackedSize::WriteToHeader(Some(data.len() as u64)),
+    };
+    let mut output = Vec::new();
+    lzma_compress_with_options(&mut Cursor::new(data), &mut output, &options)?;
+
+    // Drop the high half of the 64-bit size, which `decompress` adds back.
+    output.drain(9..13);
+
+    Ok(output)
+}
+
+/// Returns `true` if `data` starts like LZHAM, zstd, xz, LZ4 or LZMA data that
+/// [`decompress`] can decompress.
+///
+/// [`decompress`]: ./fn.decompress.html
+fn is_compressed(data: &[u8]) -> bool {
+    detect_codec(data) != Codec::Uncompressed
+}
+
+/// Splits `data` into sections, each prefixed with its size (`u32`,
+/// little-endian), or returns `None` if it isn't laid out like that.
+///
+/// Every section must start like compressed data and the last one must end
+/// where `data` ends.
+fn split_sections(data: &[u8]) -> Option<Vec<&[u8]>> {
+    let mut sections = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let size = rest.get(..4)?.read_u32::<LittleEndian>().ok()? as usize;
+        let end = size.checked_add(4)?;
+        let section = rest.get(4..end)?;
+        if !is_compressed(section) {
+            return None;
+        }
+
+        sections.push(section);
+        rest = &rest[end..];
+    }
+
+    if sections.is_empty() {
+        None
+    } else {
+        Some(sections)
+    }
+}
+
+/// Splits the payload of a `.sc2` file into its chunks, or returns `None` if
+/// it isn't laid out like that.
+///
+/// Each chunk has an 8-byte header, the size of its compressed data and the
+/// size of its decompressed data (`u32`, little-endian), followed by the
+/// compressed data. Chunks are compressed independently, so every chunk must
+/// start like compressed data, and the last one must end where `data` ends.
+fn split_chunks(data: &[u8]) -> Option<Vec<(&[u8], usize)>> {
+    let mut chunks = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let size = rest.get(..4)?.read_u32::<LittleEndian>().ok()? as usize;
+        let decompressed_size = rest.get(4..8)?.read_u32::<LittleEndian>().ok()? as usize;
+        let end = size.checked_add(8)?;
+        let chunk = rest.get(8..end)?;
+        if !is_compressed(chunk) {
+            return None;
+        }
+
+        chunks.push((chunk, decompressed_size));
+        rest = &rest[end..];
+    }
+
+    if chunks.is_empty() {
+        None
+    } else {
+        Some(chunks)
+    }
+}
+
+/// Returns the codec of the payload of a `_tex.sc` (or `.sctx` or `.sc2`)
+/// file, or of its first section or chunk if it is split.
+pub(crate) fn payload_codec(data: &[u8]) -> Codec {
+    if let Some(sections) = split_sections(data) {
+        detect_codec(sections[0])
+    } else if let Some(chunks) = split_chunks(data) {
+        detect_codec(chunks[0].0)
+    } else {
+        detect_codec(data)
+    }
+}
+
+/// Decompresses the payload of a `_tex.sc` (or `.sctx` or `.sc2`) file,
+/// which follows its header.
+///
+/// See [`decompress_sections_into`].
+///
+/// [`decompress_sections_into`]: ./fn.decompress_sections_into.html
+pub(crate) fn decompress_sections(data: &[u8], settings: &Decompression) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+    decompress_sections_into(data, &mut output, settings)?;
+
+    Ok(output)
+}
+
+/// Decompresses the payload of a `_tex.sc` (or `.sctx` or `.sc2`) file,
+/// which follows its header, writing the output to `output` as it is
+/// decompressed.
+///
+/// The payload is usually a single LZMA, LZHAM or zstd stream. Newer files
+/// split it into several independently compressed sections instead, each
+/// prefixed with its size, and `.sc2` files into chunks with their
+/// decompressed sizes too. Sections and chunks can use
+/// different codecs. They are decompressed one by one and their outputs are
+/// concatenated.
+///
+/// The whole output must not be larger than `settings.limit` bytes.
+pub(crate) fn decompress_sections_into(
+    data: &[u8],
+    output: &mut dyn Write,
+    settings: &Decompression,
+) -> Result<(), Error> {
+    let chunks = match (split_sections(data), split_chunks(data)) {
+        (Some(sections), _) => sections.into_iter().map(|s| (s, None)).collect(),
+        (None, Some(chunks)) => chunks
+            .into_iter()
+            .map(|(chunk, size)| (chunk, Some(size)))
+            .collect::<Vec<_>>(),
+        (None, None) => return decompress_into(data, output, 0, settings).map(|_| ()),
+    };
+
+    let kind = if chunks[0].1.is_some() {
+        "Chunk"
+    } else {
+        "Section"
+    };
+    let mut written = 0;
+    for (i, (chunk, size)) in chunks.iter().enumerate() {
+        let result =
+            decompress_into(chunk, output, written, settings).and_then(|chunk_size| match size {
+                Some(size) if chunk_size != *size => Err(Error::DecompressionError(format!(
+                    "Decompressed to {} byte(s), but its header says {}.",
+                    chunk_size, size
+                ))),
+                _ => Ok(chunk_size),
+            });
+
+        match result {
+            Ok(chunk_size) => written += chunk_size,
+            Err(Error::DecompressionError(e)) => {
+                return Err(Error::DecompressionError(format!(
+                    "{} {} of {}: {}",
+                    kind,
+                    i + 1,
+                    chunks.len(),
+                    e
+                )))
+            }
+            Err(e) => return Err(e),
         }
     }
 
@@ -0,0 +1,120 @@
+#![cfg(feature = "fs")]
+
+mod utils;
+
+use sc_extract::{prefer_highres, process_dir, process_dir_with_options, DirOptions, Error};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use utils::*;
+
+#[test]
+fn test_summary() {
+    let out_dir = Path::new("./tests/out/batch");
+
+    prepare_out_dir(out_dir);
+
+    let summary = process_dir(Path::new("./tests/data/sctx"), out_dir, true).unwrap();
+    assert_eq!(summary.processed, 1);
+    assert!(summary.failed.is_empty());
+    assert_eq!(summary.images_written, 1);
+    assert!(out_dir.join("icons.png").exists());
+
+    let summary = process_dir(Path::new("./tests/data/csv"), out_dir, false).unwrap();
+    assert_eq!(summary.processed, 3);
+    assert!(summary.failed.is_empty());
+    assert_eq!(summary.images_written, 0);
+}
+
+#[test]
+fn test_failed() {
+    let out_dir = Path::new("./tests/out/batch_failed");
+
+    prepare_out_dir(out_dir);
+
+    let summary = process_dir(Path::new("./tests/data/corrupt"), out_dir, true).unwrap();
+    assert_eq!(summary.processed, 0);
+    assert_eq!(summary.images_written, 0);
+
+    let failed: Vec<_> = summary
+        .failed
+        .iter()
+        .map(|(path, _)| get_file_name(path))
+        .collect();
+    assert_eq!(
+        failed,
+        [
+            "invalid_int.csv",
+            "truncated_header_tex.sc",
+            "truncated_lzma_tex.sc"
+        ]
+    );
+    assert!(matches!(summary.failed[0].1, Error::Other(_)));
+
+    assert!(process_dir(Path::new("./tests/data/missing"), out_dir, true).is_err());
+}
+
+#[test]
+fn test_prefer_highres() {
+    let dir = Path::new("./tests/out/batch_variants");
+    let out_dir = Path::new("./tests/out/batch_variants_out");
+
+    prepare_out_dir(dir);
+    prepare_out_dir(out_dir);
+
+    let data = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+    for name in [
+        "ui_lowres_tex.sc",
+        "ui_tex.sc",
+        "ui_highres_tex.sc",
+        "ui_lowres_dl_tex.sc",
+        "ui_lowresx_tex.sc",
+    ]
+    .iter()
+    {
+        fs::write(dir.join(name), &data).unwrap();
+    }
+
+    let options = DirOptions {
+        prefer_highres: true,
+    };
+    let summary = process_dir_with_options(dir, out_dir, true, &options).unwrap();
+    assert_eq!(summary.processed, 3);
+    assert!(summary.failed.is_empty());
+    assert_eq!(
+        summary.skipped_variants,
+        [
+            (dir.join("ui_lowres_tex.sc"), dir.join("ui_highres_tex.sc")),
+            (dir.join("ui_tex.sc"), dir.join("ui_highres_tex.sc")),
+        ]
+    );
+
+    // Every variant is processed by default.
+    let summary = process_dir(dir, out_dir, true).unwrap();
+    assert_eq!(summary.processed, 5);
+    assert!(summary.skipped_variants.is_empty());
+
+    let mut paths = vec![
+        PathBuf::from("a/ui_tex.sc"),
+        PathBuf::from("b/ui_lowres_tex.sc"),
+        PathBuf::from("a/ui_lowres_tex.sc"),
+        PathBuf::from("a/ui.csv"),
+    ];
+    let skipped = prefer_highres(&mut paths);
+    assert_eq!(
+        paths,
+        [
+            PathBuf::from("a/ui_tex.sc"),
+            PathBuf::from("b/ui_lowres_tex.sc"),
+            PathBuf::from("a/ui.csv")
+        ]
+    );
+    assert_eq!(
+        skipped,
+        [(
+            PathBuf::from("a/ui_lowres_tex.sc"),
+            PathBuf::from("a/ui_tex.sc")
+        )]
+    );
+}
@@ -1,22 +1,24 @@
+#![cfg(feature = "fs")]
+
 mod utils;
 
 use rayon::prelude::*;
-use sc_extract::process_csv;
+use sc_extract::{
+    decompress, decompress_with_limit, is_decompressed_csv, pack_csv, process_csv,
+    process_csv_with_options, CsvOptions, Error,
+};
 use std::{fs, path::Path};
 use utils::*;
 
 #[test]
 fn test_single() {
     let path = Path::new("./tests/data/csv/alliance_badges.csv");
-    let data = fs::read(&path).unwrap();
+    let data = fs::read(path).unwrap();
     let out_dir = Path::new("./tests/out/csv");
 
-    prepare_out_dir(&out_dir);
+    prepare_out_dir(out_dir);
 
-    assert_eq!(
-        true,
-        process_csv(data.as_slice(), get_file_name(&path), &out_dir).is_ok()
-    );
+    assert!(process_csv(data.as_slice(), get_file_name(path), out_dir).is_ok());
 }
 
 #[test]
@@ -24,7 +26,7 @@ fn test_all_parallel() {
     let dir = Path::new("./tests/data/csv");
     let out_dir = Path::new("./tests/out/csv");
 
-    prepare_out_dir(&out_dir);
+    prepare_out_dir(out_dir);
 
     let dir_entries = match fs::read_dir(dir) {
         Ok(e) => e,
@@ -39,10 +41,7 @@ fn test_all_parallel() {
     entries.into_par_iter().for_each(|entry| {
         let path = entry.unwrap().path();
         let data = fs::read(&path).unwrap();
-        assert_eq!(
-            true,
-            process_csv(data.as_slice(), get_file_name(&path), &out_dir).is_ok()
-        );
+        assert!(process_csv(data.as_slice(), get_file_name(&path), out_dir).is_ok());
     });
 }
 
@@ -51,7 +50,7 @@ fn test_all_blocking() {
     let dir = Path::new("./tests/data/csv");
     let out_dir = Path::new("./tests/out/csv");
 
-    prepare_out_dir(&out_dir);
+    prepare_out_dir(out_dir);
 
     let dir_entries = match fs::read_dir(dir) {
         Ok(e) => e,
@@ -66,9 +65,203 @@ fn test_all_blocking() {
     for entry in entries {
         let path = entry.unwrap().path();
         let data = fs::read(&path).unwrap();
-        assert_eq!(
-            true,
-            process_csv(data.as_slice(), get_file_name(&path), &out_dir).is_ok()
-        );
+        assert!(process_csv(data.as_slice(), get_file_name(&path), out_dir).is_ok());
+    }
+}
+
+#[test]
+fn test_json() {
+    let path = Path::new("./tests/data/csv/alliance_roles.csv");
+    let data = fs::read(path).unwrap();
+    let out_dir = Path::new("./tests/out/csv_json");
+
+    prepare_out_dir(out_dir);
+
+    let options = CsvOptions {
+        json: true,
+        ..Default::default()
+    };
+    process_csv_with_options(data.as_slice(), get_file_name(path), out_dir, &options).unwrap();
+
+    let json = fs::read_to_string(out_dir.join("alliance_roles.json")).unwrap();
+    let rows: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    // Five roles, with typed values and `null` for empty cells.
+    let rows = rows.as_array().unwrap();
+    assert_eq!(rows.len(), 5);
+    assert_eq!(rows[2]["Name"], "Leader");
+    assert_eq!(rows[2]["Level"], 20);
+    assert_eq!(rows[2]["CanKick"], true);
+    assert_eq!(rows[0]["CanInvite"], false);
+    assert!(rows[0]["TID"].is_null());
+}
+
+#[test]
+fn test_types() {
+    let path = Path::new("./tests/data/csv/typed.csv");
+    let data = fs::read(path).unwrap();
+    let out_dir = Path::new("./tests/out/csv_types");
+
+    prepare_out_dir(out_dir);
+
+    let options = CsvOptions {
+        json: true,
+        ..Default::default()
+    };
+    process_csv_with_options(data.as_slice(), get_file_name(path), out_dir, &options).unwrap();
+
+    let json = fs::read_to_string(out_dir.join("typed.json")).unwrap();
+    let rows: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    // `1` and `0` are booleans too, and type names are case-insensitive.
+    assert_eq!(rows[0]["Id"], 1);
+    assert_eq!(rows[0]["Enabled"], true);
+    assert_eq!(rows[0]["Hidden"], true);
+    assert_eq!(rows[1]["Id"], -2);
+    assert_eq!(rows[1]["Enabled"], false);
+    assert!(rows[1]["Hidden"].is_null());
+    assert!(rows[2]["Id"].is_null());
+}
+
+#[test]
+fn test_invalid_type() {
+    let path = Path::new("./tests/data/corrupt/invalid_int.csv");
+    let data = fs::read(path).unwrap();
+    let out_dir = Path::new("./tests/out/csv_invalid");
+
+    prepare_out_dir(out_dir);
+
+    // The fourth line has `two` in an `int` column. The file isn't saved in
+    // either format.
+    for json in [false, true].iter() {
+        let options = CsvOptions {
+            json: *json,
+            ..Default::default()
+        };
+        let result =
+            process_csv_with_options(data.as_slice(), get_file_name(path), out_dir, &options);
+
+        match result {
+            Err(Error::Other(e)) => assert_eq!(
+                e,
+                "invalid_int.csv: Line 4, column `Id`: `two` is not a valid int value."
+            ),
+            _ => panic!("Expected an error, got {:?}", result),
+        }
+    }
+    assert_eq!(fs::read_dir(out_dir).unwrap().count(), 0);
+}
+
+#[test]
+fn test_decompress() {
+    let data = fs::read("./tests/data/csv/alliance_badges.csv").unwrap();
+
+    let output = decompress(data.as_slice()).unwrap();
+
+    assert_eq!(output.len(), 886);
+    assert!(output.starts_with(b"\"Name\",\"IconSWF\",\"IconExportName\""));
+}
+
+#[test]
+fn test_decompress_too_small() {
+    let result = decompress(&[0x5D, 0x00, 0x00]);
+
+    assert!(matches!(result, Err(Error::DecompressionError(_))));
+}
+
+#[test]
+fn test_pack() {
+    let path = Path::new("./tests/data/csv/alliance_badges.csv");
+    let data = fs::read(path).unwrap();
+    let out_dir = Path::new("./tests/out/csv_pack");
+
+    prepare_out_dir(out_dir);
+
+    let plain = decompress(data.as_slice()).unwrap();
+    let packed = pack_csv(&plain).unwrap();
+
+    // The packed file has the same properties and size in its header,
+    // decompresses to the same data and extracts the same way as the original.
+    assert_eq!(packed[0], data[0]);
+    assert_eq!(&packed[5..9], &data[5..9]);
+    assert_eq!(decompress(packed.as_slice()).unwrap(), plain);
+
+    process_csv(data.as_slice(), get_file_name(path), out_dir).unwrap();
+    let original = fs::read(out_dir.join("alliance_badges.csv")).unwrap();
+    process_csv(packed.as_slice(), get_file_name(path), out_dir).unwrap();
+    assert_eq!(
+        fs::read(out_dir.join("alliance_badges.csv")).unwrap(),
+        original
+    );
+}
+
+#[test]
+fn test_decompress_limit() {
+    let data = fs::read("./tests/data/csv/alliance_badges.csv").unwrap();
+
+    // The size in the header is checked before decompressing.
+    assert_eq!(
+        decompress_with_limit(data.as_slice(), 886).unwrap().len(),
+        886
+    );
+    match decompress_with_limit(data.as_slice(), 885) {
+        Err(Error::DecompressionError(e)) => assert_eq!(
+            e,
+            "Decompressed data (886 bytes) is larger than the limit of 885 bytes."
+        ),
+        result => panic!("Expected an error, got {:?}", result),
     }
 }
+
+#[test]
+fn test_sc2() {
+    let path = Path::new("./tests/data/csv/alliance_badges.csv");
+    let plain = decompress(fs::read(path).unwrap().as_slice()).unwrap();
+    let out_dir = Path::new("./tests/out/csv_sc2");
+
+    prepare_out_dir(out_dir);
+
+    // An `SC` header, followed by one LZMA chunk with its sizes.
+    let chunk = pack_csv(&plain).unwrap();
+    let mut data = b"SC\0\0\0\x01\0\0\0\x10".to_vec();
+    data.extend_from_slice(&[0; 16]);
+    data.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+    data.extend_from_slice(&(plain.len() as u32).to_le_bytes());
+    data.extend(chunk);
+
+    process_csv(data.as_slice(), "alliance_badges.csv.sc2", out_dir).unwrap();
+
+    assert_eq!(
+        fs::read(out_dir.join("alliance_badges.csv")).unwrap(),
+        plain
+    );
+    assert_eq!(get_file_name(path), "alliance_badges.csv");
+}
+
+#[test]
+fn test_decompressed_input() {
+    let data = fs::read("./tests/data/csv/alliance_badges.csv").unwrap();
+    let plain = decompress(data.as_slice()).unwrap();
+    let out_dir = Path::new("./tests/out/csv_decompressed_input");
+
+    prepare_out_dir(out_dir);
+
+    assert!(is_decompressed_csv(&plain));
+    assert!(!is_decompressed_csv(&data));
+    assert!(!is_decompressed_csv(b"\x01\x02,\x03"));
+
+    // Plain csv data is saved as it is.
+    process_csv(&plain, "alliance_badges.csv", out_dir).unwrap();
+    assert_eq!(
+        fs::read(out_dir.join("alliance_badges.csv")).unwrap(),
+        plain
+    );
+
+    // Data that doesn't look like csv can still be forced.
+    let options = CsvOptions {
+        assume_decompressed: true,
+        ..Default::default()
+    };
+    process_csv_with_options(b"\x01\x02", "binary.csv", out_dir, &options).unwrap();
+    assert_eq!(fs::read(out_dir.join("binary.csv")).unwrap(), b"\x01\x02");
+}
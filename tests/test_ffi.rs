@@ -0,0 +1,55 @@
+#![cfg(feature = "ffi")]
+
+mod utils;
+
+use sc_extract::ffi::{sce_last_error, sce_process_tex, SCE_ERROR, SCE_INVALID_ARGUMENT, SCE_OK};
+use std::{
+    ffi::{CStr, CString},
+    fs,
+    path::Path,
+    ptr,
+};
+use utils::*;
+
+fn last_error() -> Option<String> {
+    let message = sce_last_error();
+    if message.is_null() {
+        None
+    } else {
+        Some(
+            unsafe { CStr::from_ptr(message) }
+                .to_str()
+                .unwrap()
+                .to_string(),
+        )
+    }
+}
+
+#[test]
+fn test_process_tex() {
+    let path = Path::new("./tests/data/sc/margin_tex.sc");
+    let data = fs::read(path).unwrap();
+    let out_dir = Path::new("./tests/out/ffi");
+
+    prepare_out_dir(out_dir);
+
+    let file_name = CString::new(get_file_name(path)).unwrap();
+    let out = CString::new(out_dir.to_str().unwrap()).unwrap();
+    let status =
+        unsafe { sce_process_tex(data.as_ptr(), data.len(), file_name.as_ptr(), out.as_ptr()) };
+
+    assert_eq!(status, SCE_OK);
+    assert_eq!(last_error(), None);
+    assert!(out_dir.join("margin_tex.png").exists());
+
+    let status = unsafe { sce_process_tex(data.as_ptr(), 10, file_name.as_ptr(), out.as_ptr()) };
+    assert_eq!(status, SCE_ERROR);
+    assert_eq!(
+        last_error().unwrap(),
+        "margin_tex.sc: Size of file is too small"
+    );
+
+    let status = unsafe { sce_process_tex(ptr::null(), 0, file_name.as_ptr(), out.as_ptr()) };
+    assert_eq!(status, SCE_INVALID_ARGUMENT);
+    assert!(last_error().is_some());
+}
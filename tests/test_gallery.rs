@@ -0,0 +1,45 @@
+#![cfg(feature = "fs")]
+
+use sc_extract::{process_tex, save_html_index};
+use std::{fs, path::Path};
+
+#[test]
+fn test_html_index() {
+    let out_dir = Path::new("./tests/out/gallery");
+
+    fs::create_dir_all(out_dir.join("nested")).unwrap();
+
+    let data = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+    let margin = process_tex(data.as_slice(), "margin_tex.sc", out_dir, true).unwrap();
+    let data = fs::read("./tests/data/sc/rgb565_tex.sc").unwrap();
+    let rgb565 = process_tex(
+        data.as_slice(),
+        "a & b_tex.sc",
+        &out_dir.join("nested"),
+        true,
+    )
+    .unwrap();
+
+    let path = out_dir.join("index.html");
+    save_html_index(
+        &[
+            ("margin_tex.sc".to_string(), margin),
+            ("a & b_tex.sc".to_string(), rgb565),
+        ],
+        &path,
+    )
+    .unwrap();
+    let html = fs::read_to_string(path).unwrap();
+
+    // The images are linked relative to the page, grouped by file.
+    assert!(html.contains("<h2>margin_tex.sc (2 sprite(s))</h2>"));
+    assert!(html.contains(
+        "<img src=\"margin_tex.png\" alt=\"margin_tex.png\" width=\"6\" height=\"5\" loading=\"lazy\">"
+    ));
+    assert!(html.contains("6x5, RGBA8888 (0)"));
+    assert!(html.contains("<h2>a &amp; b_tex.sc (1 sprite(s))</h2>"));
+    assert!(html.contains("src=\"nested/a%20%26%20b_tex.png\""));
+    assert!(html.contains("RGB565 (4)"));
+    // No external assets are loaded.
+    assert!(!html.contains("http"));
+}
@@ -0,0 +1,82 @@
+use sc_extract::{Error, Reader};
+use std::{
+    convert::TryInto,
+    fs::{self, File},
+    io::{BufReader, Cursor, Seek, SeekFrom},
+};
+
+#[test]
+fn test_from_stream() {
+    let path = "./tests/data/sc/margin_tex.sc";
+    let data = fs::read(path).unwrap();
+
+    // Only the bytes after the position of the stream are read.
+    let mut file = BufReader::new(File::open(path).unwrap());
+    file.seek(SeekFrom::Start(2)).unwrap();
+    let mut reader = Reader::from_stream(file).unwrap();
+    assert_eq!(reader.len(), data.len() - 2);
+
+    assert_eq!(reader.try_read(4).unwrap(), &data[2..6]);
+    reader.try_skip(4).unwrap();
+    assert_eq!(
+        reader.try_read_uint32().unwrap(),
+        u32::from_le_bytes(data[10..14].try_into().unwrap())
+    );
+
+    reader.try_skip(reader.len() - 1).unwrap();
+    assert_eq!(reader.try_read_byte().unwrap(), data[data.len() - 1]);
+    assert!(reader.is_empty());
+    assert!(matches!(
+        reader.try_read_byte(),
+        Err(Error::UnexpectedEof(_))
+    ));
+    assert!(matches!(reader.try_skip(1), Err(Error::UnexpectedEof(_))));
+}
+
+#[test]
+fn test_slice() {
+    let data = [1, 2, 3, 4, 5];
+
+    let mut reader = Reader::new(Cursor::new(&data[..]));
+    assert_eq!(reader.try_read_uint16().unwrap(), 0x0201);
+    assert_eq!(reader.try_slice(2).unwrap(), [3, 4]);
+    assert_eq!(reader.len(), 1);
+
+    // Nothing is read if the stream ends first.
+    assert!(matches!(reader.try_slice(2), Err(Error::UnexpectedEof(_))));
+    assert_eq!(reader.len(), 1);
+}
+
+#[test]
+fn test_big_endian() {
+    let data = [0x12, 0x34, 0, 0, 0, 1, 0xFF, 0xFF, 0xFF, 0xFE, 0];
+
+    let mut reader = Reader::new(Cursor::new(&data[..]));
+    assert_eq!(reader.try_read_uint16_be().unwrap(), 0x1234);
+    assert_eq!(reader.try_read_uint32_be().unwrap(), 1);
+    assert_eq!(reader.try_read_int32_be().unwrap(), -2);
+
+    assert!(matches!(
+        reader.try_read_uint16_be(),
+        Err(Error::UnexpectedEof(_))
+    ));
+    assert_eq!(reader.len(), 1);
+}
+
+#[test]
+fn test_floats() {
+    let mut data = 1.5f32.to_le_bytes().to_vec();
+    data.extend_from_slice(&(-0.25f64).to_le_bytes());
+    data.extend_from_slice(&[0; 7]);
+
+    let mut reader = Reader::new(Cursor::new(&data[..]));
+    assert_eq!(reader.try_read_f32().unwrap(), 1.5);
+    assert_eq!(reader.try_read_f64().unwrap(), -0.25);
+
+    assert!(matches!(
+        reader.try_read_f64(),
+        Err(Error::UnexpectedEof(_))
+    ));
+    assert_eq!(reader.try_read_f32().unwrap(), 0.0);
+    assert_eq!(reader.len(), 3);
+}
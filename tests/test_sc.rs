@@ -0,0 +1,84 @@
+#![cfg(feature = "fs")]
+
+mod utils;
+
+use sc_extract::{compress, decompress_sc, process_sc, process_sc_with_options, ScOptions};
+use std::{fs, path::Path};
+use utils::*;
+
+#[test]
+fn test_single() {
+    let path = Path::new("./tests/data/extracted/shapes");
+    let data = fs::read(path).unwrap();
+    let out_dir = Path::new("./tests/out/extracted");
+
+    prepare_out_dir(out_dir);
+
+    let png_dir = Path::new("./tests/data/extracted");
+    assert!(process_sc(data.as_slice(), get_file_name(path), out_dir, png_dir, true).is_ok());
+
+    for i in 0..3 {
+        assert!(out_dir.join(format!("shapes_sprite_{}.png", i)).exists());
+    }
+}
+
+#[test]
+fn test_export_names() {
+    let path = Path::new("./tests/data/extracted/shapes");
+    let data = fs::read(path).unwrap();
+    let out_dir = Path::new("./tests/out/export_names");
+
+    prepare_out_dir(out_dir);
+
+    // Two movie clips are exported as `hero/idle`, each using one shape. The
+    // `icon` export refers to a shape directly. Shapes whose names aren't
+    // taken are named as before, but the second `hero/idle` shape keeps its
+    // number: appending `_`, as names taken were handled at first, could
+    // take the name of another export, like `hero/idle_`.
+    let options = ScOptions { export_names: true };
+    let png_dir = Path::new("./tests/data/extracted");
+    process_sc_with_options(
+        data.as_slice(),
+        get_file_name(path),
+        out_dir,
+        png_dir,
+        true,
+        &options,
+    )
+    .unwrap();
+
+    let mut names = fs::read_dir(out_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "shapes_hero_idle.png",
+            "shapes_icon.png",
+            "shapes_sprite_0.png"
+        ]
+    );
+}
+
+#[test]
+fn test_compressed() {
+    let path = Path::new("./tests/data/extracted/shapes");
+    let data = fs::read(path).unwrap();
+    let out_dir = Path::new("./tests/out/compressed_sc");
+
+    prepare_out_dir(out_dir);
+
+    // `.sc` files have the same header as `_tex.sc` files. Decompressed, they
+    // are the same as the files QuickBMS extracts.
+    let mut compressed = b"SC\0\0\0\x01\0\0\0\x10".to_vec();
+    compressed.extend_from_slice(&[0; 16]);
+    compressed.extend(compress(&data).unwrap());
+    let decompressed = decompress_sc(&compressed).unwrap();
+    assert_eq!(decompressed, data);
+
+    let png_dir = Path::new("./tests/data/extracted");
+    process_sc(&decompressed, "shapes", out_dir, png_dir, true).unwrap();
+    assert!(out_dir.join("shapes_sprite_0.png").exists());
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "fs")]
+
+mod utils;
+
+use sc_extract::{
+    process_sctx, process_sctx_with_options, Error, HashCheck, OutputFormat, SctxOptions,
+};
+use std::{fs, path::Path};
+use utils::*;
+
+#[test]
+fn test_single() {
+    let path = Path::new("./tests/data/sctx/icons.sctx");
+    let data = fs::read(path).unwrap();
+    let out_dir = Path::new("./tests/out/sctx");
+
+    prepare_out_dir(out_dir);
+
+    process_sctx(data.as_slice(), get_file_name(path), out_dir, true).unwrap();
+
+    // A 4x2 RGB565 texture. The 2x1 mipmap level after it is not saved.
+    let image = image::open(out_dir.join("icons.png")).unwrap().to_rgba8();
+    let expected = [
+        [255, 0, 0, 255],
+        [0, 255, 0, 255],
+        [0, 0, 255, 255],
+        [255, 255, 255, 255],
+        [0, 0, 0, 255],
+        [132, 130, 132, 255],
+        [255, 255, 0, 255],
+        [0, 255, 255, 255],
+    ];
+    assert_eq!(image.dimensions(), (4, 2));
+    for (pixel, expected) in image.pixels().zip(&expected) {
+        assert_eq!(&pixel.0, expected);
+    }
+    assert_eq!(fs::read_dir(out_dir).unwrap().count(), 1);
+}
+
+#[test]
+fn test_format() {
+    let path = Path::new("./tests/data/sctx/icons.sctx");
+    let data = fs::read(path).unwrap();
+    let out_dir = Path::new("./tests/out/sctx_format");
+
+    prepare_out_dir(out_dir);
+
+    let options = SctxOptions {
+        format: OutputFormat::Bmp,
+        ..Default::default()
+    };
+    process_sctx_with_options(
+        data.as_slice(),
+        get_file_name(path),
+        out_dir,
+        true,
+        &options,
+    )
+    .unwrap();
+
+    let image = image::open(out_dir.join("icons.bmp")).unwrap().to_rgba8();
+    assert_eq!(image.dimensions(), (4, 2));
+    assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+    assert!(!out_dir.join("icons.png").exists());
+}
+
+#[test]
+fn test_hash_check() {
+    let path = Path::new("./tests/data/sctx/icons.sctx");
+    let mut data = fs::read(path).unwrap();
+    let out_dir = Path::new("./tests/out/sctx_hash");
+
+    prepare_out_dir(out_dir);
+
+    let options = SctxOptions {
+        hash_check: HashCheck::Fail,
+        ..Default::default()
+    };
+    process_sctx_with_options(data.as_slice(), "icons.sctx", out_dir, true, &options).unwrap();
+
+    // The 16-byte hash starts after the 10-byte header.
+    data[10] ^= 0xFF;
+    match process_sctx_with_options(data.as_slice(), "icons.sctx", out_dir, true, &options) {
+        Err(Error::HashMismatch(e)) => assert!(e.contains("e1e9337190c858ed0e59aacc4fd9e4d4")),
+        res => panic!("Expected a hash mismatch, got {:?}", res),
+    }
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "fs")]
+
+use sc_extract::{process_tex, save_contact_sheets, ContactSheetOptions};
+use std::{fs, path::Path};
+
+#[test]
+fn test_contact_sheet() {
+    let out_dir = Path::new("./tests/out/sheet");
+
+    fs::create_dir_all(out_dir).unwrap();
+
+    let mut images = Vec::new();
+    for file in ["luminance_tex.sc", "la44_tex.sc", "tiled_tex.sc"].iter() {
+        let data = fs::read(Path::new("./tests/data/sc").join(file)).unwrap();
+        let extraction = process_tex(data.as_slice(), file, out_dir, true).unwrap();
+        images.extend(extraction.sprites.into_iter().map(|s| s.path));
+    }
+    assert_eq!(images.len(), 6);
+
+    // Two sheets of 2x2 thumbnails, the second with a single row.
+    let options = ContactSheetOptions {
+        thumbnail_size: 16,
+        columns: 2,
+        rows: 2,
+    };
+    let sheets = save_contact_sheets(&images, &out_dir.join("sheet.png"), &options).unwrap();
+
+    assert_eq!(
+        sheets,
+        vec![out_dir.join("sheet_0.png"), out_dir.join("sheet_1.png")]
+    );
+    // Each cell is the thumbnail, its caption and the spacing.
+    assert_eq!(image::image_dimensions(&sheets[0]).unwrap(), (44, 62));
+    assert_eq!(image::image_dimensions(&sheets[1]).unwrap(), (44, 33));
+
+    // Images that fit on one sheet are saved at the given path.
+    let sheets = save_contact_sheets(
+        &images,
+        &out_dir.join("single.png"),
+        &ContactSheetOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(sheets, vec![out_dir.join("single.png")]);
+
+    let options = ContactSheetOptions {
+        columns: 0,
+        ..Default::default()
+    };
+    assert!(save_contact_sheets(&images, &out_dir.join("sheet.png"), &options).is_err());
+}
@@ -1,30 +1,48 @@
+// Most tests write the sprites, which needs the `fs` feature, so their helpers
+// are unused without it.
+#![cfg_attr(not(feature = "fs"), allow(dead_code, unused_imports))]
+
 mod utils;
 
+use image::{DynamicImage, GenericImageView};
 use rayon::prelude::*;
-use sc_extract::process_tex;
-use std::{fs, path::Path};
+use sc_extract::{
+    compress, decode_pixel, decompress, decompress_sc, decompress_with_limit, detect_codec,
+    extract_tex, extract_tex_to_pngs, is_decompressed_tex, pack_tex, read_sc_header,
+    read_tex_sprites, split_alpha, Codec, DedupeMode, Deduplicator, Error, HashCheck, OutputFormat,
+    PngCompression, Resize, ScHeader, SpriteCrop, SpriteNaming, TexMetadata, TexOptions, TexSprite,
+};
+#[cfg(feature = "fs")]
+use sc_extract::{process_tex, process_tex_with};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::{
+    fs,
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use utils::*;
 
 #[test]
+#[cfg(feature = "fs")]
 fn test_single() {
     let path = Path::new("./tests/data/sc/background_basic_tex.sc");
-    let data = fs::read(&path).unwrap();
+    let data = fs::read(path).unwrap();
     let out_dir = Path::new("./tests/out/sc");
 
-    prepare_out_dir(&out_dir);
+    prepare_out_dir(out_dir);
 
-    assert_eq!(
-        true,
-        process_tex(data.as_slice(), get_file_name(path), &out_dir, true).is_ok()
-    );
+    assert!(process_tex(data.as_slice(), get_file_name(path), out_dir, true).is_ok());
 }
 
 #[test]
+#[cfg(feature = "fs")]
 fn test_all_parallel() {
     let dir = Path::new("./tests/data/sc");
     let out_dir = Path::new("./tests/out/sc");
 
-    prepare_out_dir(&out_dir);
+    prepare_out_dir(out_dir);
 
     let dir_entries = match fs::read_dir(dir) {
         Ok(e) => e,
@@ -39,19 +57,17 @@ fn test_all_parallel() {
     entries.into_par_iter().for_each(|entry| {
         let path = entry.unwrap().path();
         let data = fs::read(&path).unwrap();
-        assert_eq!(
-            true,
-            process_tex(data.as_slice(), get_file_name(&path), &out_dir, true).is_ok()
-        );
+        assert!(process_tex(data.as_slice(), get_file_name(&path), out_dir, true).is_ok());
     });
 }
 
 #[test]
+#[cfg(feature = "fs")]
 fn test_all_blocking() {
     let dir = Path::new("./tests/data/sc");
     let out_dir = Path::new("./tests/out/sc");
 
-    prepare_out_dir(&out_dir);
+    prepare_out_dir(out_dir);
 
     let dir_entries = match fs::read_dir(dir) {
         Ok(e) => e,
@@ -66,9 +82,2242 @@ fn test_all_blocking() {
     for entry in entries {
         let path = entry.unwrap().path();
         let data = fs::read(&path).unwrap();
-        assert_eq!(
-            true,
-            process_tex(data.as_slice(), get_file_name(&path), &out_dir, false).is_ok()
-        );
+        assert!(process_tex(data.as_slice(), get_file_name(&path), out_dir, false).is_ok());
+    }
+}
+
+#[test]
+fn test_extract_in_memory() {
+    let path = Path::new("./tests/data/sc/background_basic_tex.sc");
+    let data = fs::read(path).unwrap();
+
+    let images = extract_tex(data.as_slice()).unwrap();
+
+    assert!(!images.is_empty());
+    for image in images {
+        assert!(image.width() > 0 && image.height() > 0);
+    }
+}
+
+#[test]
+fn test_etc1() {
+    let data = fs::read("./tests/data/sc/etc1_tex.sc").unwrap();
+    // The golden was decoded with texture2ddecoder 0.1.2.
+    let golden = image::open("./tests/data/golden/etc1_tex.png")
+        .unwrap()
+        .to_rgba8();
+
+    let images = extract_tex(data.as_slice()).unwrap();
+
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0], golden);
+}
+
+#[test]
+fn test_etc2() {
+    let data = fs::read("./tests/data/sc/etc2_tex.sc").unwrap();
+
+    // The goldens were decoded with texture2ddecoder 0.1.2.
+    let images = extract_tex(data.as_slice()).unwrap();
+
+    assert_eq!(images.len(), 2);
+    for (image, golden) in images.iter().zip(&["etc2_tex.png", "etc2_tex_.png"]) {
+        let golden = image::open(Path::new("./tests/data/golden").join(golden))
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(image, &golden);
+    }
+}
+
+#[test]
+fn test_astc() {
+    let data = fs::read("./tests/data/sc/astc_tex.sc").unwrap();
+
+    // 4x4, 6x5 and 8x5 footprints, including clipped edge blocks and
+    // void-extent blocks. The goldens were decoded with ARM's astcenc 5.3.0
+    // (`astcenc -dl`).
+    let images = extract_tex(data.as_slice()).unwrap();
+
+    assert_eq!(images.len(), 3);
+    for (image, golden) in images
+        .iter()
+        .zip(&["astc_tex.png", "astc_tex_.png", "astc_tex__.png"])
+    {
+        let golden = image::open(Path::new("./tests/data/golden").join(golden))
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(image, &golden);
+    }
+}
+
+#[test]
+fn test_pvrtc() {
+    let data = fs::read("./tests/data/sc/pvrtc_tex.sc").unwrap();
+
+    // 16x8, 5x3 (padded to 8x8) and 8x32 textures. The goldens were decoded
+    // with texture2ddecoder 0.1.2.
+    let images = extract_tex(data.as_slice()).unwrap();
+
+    let goldens = ["pvrtc_tex.png", "pvrtc_tex_.png", "pvrtc_tex__.png"];
+    assert_eq!(images.len(), goldens.len());
+    for (image, golden) in images.iter().zip(&goldens) {
+        let golden = image::open(Path::new("./tests/data/golden").join(golden))
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(image, &golden);
+    }
+}
+
+#[test]
+fn test_bc() {
+    let data = fs::read("./tests/data/sc/bc_tex.sc").unwrap();
+
+    // BC1, BC3 and BC3 with premultiplied alpha. The goldens were decoded
+    // with bcdec (bcdec_rs 0.2.0), then the last one was unpremultiplied.
+    let images = extract_tex(data.as_slice()).unwrap();
+
+    let goldens = ["bc_tex.png", "bc_tex_.png", "bc_tex__.png"];
+    assert_eq!(images.len(), goldens.len());
+    for (image, golden) in images.iter().zip(&goldens) {
+        let golden = image::open(Path::new("./tests/data/golden").join(golden))
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(image, &golden);
+    }
+}
+
+#[test]
+fn test_etc2_truncated() {
+    let data = fs::read("./tests/data/sc/etc2_truncated_tex.sc").unwrap();
+
+    // The truncated sprite is skipped instead of being filled with garbage.
+    let images = extract_tex(data.as_slice()).unwrap();
+
+    assert!(images.is_empty());
+}
+
+#[test]
+fn test_truncated_pixels() {
+    let data = fs::read("./tests/data/sc/truncated_pixels_tex.sc").unwrap();
+
+    // The sprite is skipped instead of being padded with black pixels.
+    let images = extract_tex(data.as_slice()).unwrap();
+
+    assert!(images.is_empty());
+}
+
+#[test]
+fn test_truncated_header() {
+    let data = fs::read("./tests/data/corrupt/truncated_header_tex.sc").unwrap();
+
+    let result = extract_tex(data.as_slice());
+
+    assert!(matches!(result, Err(Error::UnexpectedEof(_))));
+}
+
+#[test]
+fn test_sections() {
+    let data = fs::read("./tests/data/sc/sections_tex.sc").unwrap();
+
+    // The payload is split into two LZMA sections, one for each sprite.
+    let images = extract_tex(data.as_slice()).unwrap();
+
+    assert_eq!(images.len(), 2);
+    assert_eq!(images[0].dimensions(), (2, 2));
+    assert!(images[0].pixels().all(|p| p.0 == [10, 20, 30, 255]));
+    assert_eq!(images[1].get_pixel(0, 0).0, [40, 50, 60, 255]);
+}
+
+/// Builds `.sc2` file data whose payload is `payload`, split in two chunks,
+/// compressed with LZMA and zstd. The sizes in the chunks' headers are those
+/// of `sizes`, if given.
+#[cfg(feature = "zstd")]
+fn sc2_data(payload: &[u8], sizes: Option<[u32; 2]>) -> Vec<u8> {
+    let (first, second) = payload.split_at(payload.len() / 2);
+    let sizes = sizes.unwrap_or([first.len() as u32, second.len() as u32]);
+
+    let mut data = b"SC\0\0\0\x01\0\0\0\x10".to_vec();
+    data.extend_from_slice(&[0x11; 16]);
+    for (chunk, size) in [
+        compress(first).unwrap(),
+        zstd::stream::encode_all(second, 0).unwrap(),
+    ]
+    .iter()
+    .zip(sizes.iter())
+    {
+        data.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        data.extend_from_slice(&size.to_le_bytes());
+        data.extend_from_slice(chunk);
+    }
+
+    data
+}
+
+#[test]
+#[cfg(all(feature = "fs", feature = "zstd"))]
+fn test_sc2() {
+    let original = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+    let payload = decompress_sc(&original).unwrap();
+    let data = sc2_data(&payload, None);
+    let out_dir = Path::new("./tests/out/sc2");
+
+    prepare_out_dir(out_dir);
+
+    assert_eq!(read_sc_header(&data).unwrap().codec, Codec::Lzma);
+    assert_eq!(decompress_sc(&data).unwrap(), payload);
+    assert_eq!(extract_tex(&data).unwrap(), extract_tex(&original).unwrap());
+
+    // The images are named after the file without `.sc2`.
+    let sprites = process_tex(&data, "margin_tex.sc2", out_dir, true)
+        .unwrap()
+        .sprites;
+    assert_eq!(sprites[0].path, out_dir.join("margin_tex.png"));
+
+    // Each chunk must decompress to the size in its header.
+    let size = (payload.len() - payload.len() / 2) as u32;
+    let data = sc2_data(&payload, Some([(payload.len() / 2) as u32, size + 1]));
+    match decompress_sc(&data) {
+        Err(Error::DecompressionError(e)) => assert_eq!(
+            e,
+            format!(
+                "Chunk 2 of 2: Decompressed to {} byte(s), but its header says {}.",
+                size,
+                size + 1
+            )
+        ),
+        result => panic!("Expected an error, got {:?}", result),
+    }
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_decompressed_input() {
+    let original = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+    let payload = decompress_sc(&original).unwrap();
+    let out_dir = Path::new("./tests/out/decompressed_input");
+
+    prepare_out_dir(out_dir);
+
+    assert!(is_decompressed_tex(&payload));
+    assert!(!is_decompressed_tex(&original));
+    assert!(!is_decompressed_tex(&payload[..payload.len() - 1]));
+    assert!(!is_decompressed_tex(
+        &[payload.as_slice(), &[0xFF]].concat()
+    ));
+
+    // Decompressed data is decoded directly.
+    let sprites = process_tex(&payload, "margin_tex.sc", out_dir, true)
+        .unwrap()
+        .sprites;
+    assert_eq!(
+        image::open(&sprites[0].path).unwrap().to_rgba8(),
+        extract_tex(&original).unwrap()[0]
+    );
+
+    // Data that looks compressed, here because its first chunk has the type of
+    // the LZMA properties byte and a size like a dictionary size, can still be
+    // forced.
+    let mut data = vec![0x5D, 0, 0x10, 0, 0];
+    data.extend_from_slice(&[0xFF; 0x1000]);
+    data.extend_from_slice(&payload);
+    assert!(!is_decompressed_tex(&data));
+    assert!(process_tex(&data, "margin_tex.sc", out_dir, true).is_err());
+    let options = TexOptions {
+        assume_decompressed: true,
+        ..Default::default()
+    };
+    let extraction = process_tex_with(&data, "margin_tex.sc", out_dir, &options);
+    assert_eq!(extraction.unwrap().sprites.len(), 2);
+}
+
+#[test]
+#[cfg(all(feature = "fs", feature = "zstd"))]
+fn test_zstd_dictionary() {
+    use std::io::Write;
+
+    let original = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+    let payload = decompress_sc(&original).unwrap();
+    let dictionary: Vec<u8> = payload
+        .iter()
+        .rev()
+        .copied()
+        .chain(payload.clone())
+        .collect();
+
+    let mut encoder =
+        zstd::stream::write::Encoder::with_dictionary(Vec::new(), 3, &dictionary).unwrap();
+    encoder.write_all(&payload).unwrap();
+    let mut data = original[..26].to_vec();
+    data.extend(encoder.finish().unwrap());
+
+    let out_dir = Path::new("./tests/out/zstd_dictionary");
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        zstd_dictionary: Some(Arc::new(dictionary)),
+        ..Default::default()
+    };
+    let sprites = process_tex_with(&data, "margin_tex.sc", out_dir, &options)
+        .unwrap()
+        .sprites;
+    assert_eq!(sprites.len(), 2);
+
+    // Without the dictionary, the error says one may be needed.
+    match process_tex(&data, "margin_tex.sc", out_dir, true) {
+        Err(Error::DecompressionError(e)) => assert!(
+            e.ends_with("The data may need a zstd dictionary, which wasn't given."),
+            "{}",
+            e
+        ),
+        result => panic!("Expected an error, got {:?}", result),
+    }
+
+    // A frame that names its dictionary, with a one-byte ID.
+    let frame = [0x28, 0xB5, 0x2F, 0xFD, 0x01, 0x48, 0x07, 0x01, 0x00, 0x00];
+    match decompress(&frame) {
+        Err(Error::DecompressionError(e)) => assert!(
+            e.ends_with("The data needs zstd dictionary 7, which wasn't given."),
+            "{}",
+            e
+        ),
+        result => panic!("Expected an error, got {:?}", result),
+    }
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_decompression_error() {
+    let data = fs::read("./tests/data/corrupt/truncated_lzma_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/decompression_error");
+
+    prepare_out_dir(out_dir);
+
+    // The message names the file and tells where decompression stopped.
+    match process_tex(data.as_slice(), "truncated_lzma_tex.sc", out_dir, true) {
+        Err(Error::DecompressionError(e)) => {
+            assert!(e.starts_with("truncated_lzma_tex.sc: "));
+            assert!(e.contains("of 49 byte(s) of output"));
+        }
+        _ => panic!("Expected a decompression error."),
+    }
+}
+
+#[test]
+fn test_error_trait() {
+    fn extract(path: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        let data = fs::read(path)?;
+        Ok(extract_tex(data.as_slice())?.len())
+    }
+
+    // Errors convert into boxed standard errors, and display their messages.
+    let error = extract("./tests/data/corrupt/truncated_header_tex.sc").unwrap_err();
+    let error = error.downcast::<Error>().unwrap();
+    assert_eq!(error.to_string(), *error.inner());
+
+    let error = Error::from(std::io::Error::other("disk full"));
+    assert!(matches!(error, Error::IoError(ref e) if e == "disk full"));
+}
+
+#[test]
+fn test_rgba5551() {
+    let data = fs::read("./tests/data/sc/rgba5551_tex.sc").unwrap();
+
+    // Alpha comes from bit 0 only. Read as ARGB1555, `0x0001` would be blue
+    // and `0xF801` would be transparent.
+
+    let images = extract_tex(data.as_slice()).unwrap();
+    let pixels: Vec<_> = images[0].pixels().map(|p| p.0).collect();
+
+    assert_eq!(
+        pixels,
+        vec![
+            [255, 0, 0, 255],
+            [0, 255, 0, 0],
+            [0, 0, 255, 0],
+            [0, 0, 0, 255],
+            [255, 255, 255, 0],
+            [132, 132, 132, 255],
+        ]
+    );
+}
+
+#[test]
+fn test_rgba4444() {
+    let data = fs::read("./tests/data/sc/rgba4444_tex.sc").unwrap();
+
+    let images = extract_tex(data.as_slice()).unwrap();
+    let pixels: Vec<_> = images[0].pixels().map(|p| p.0).collect();
+
+    assert_eq!(
+        pixels,
+        vec![
+            [255, 255, 255, 255],
+            [255, 0, 0, 0],
+            [0x11, 0x22, 0x33, 0x44],
+            [0, 0, 0, 0],
+        ]
+    );
+}
+
+#[test]
+fn test_rgb565() {
+    let data = fs::read("./tests/data/sc/rgb565_tex.sc").unwrap();
+
+    let images = extract_tex(data.as_slice()).unwrap();
+    let pixels: Vec<_> = images[0].pixels().map(|p| p.0).collect();
+
+    assert_eq!(
+        pixels,
+        vec![
+            [255, 255, 255, 255],
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [132, 130, 132, 255],
+            [0, 0, 255, 255],
+            [0, 0, 0, 255],
+        ]
+    );
+}
+
+#[test]
+fn test_tiled() {
+    let data = fs::read("./tests/data/sc/tiled_tex.sc").unwrap();
+
+    // A 64x48 file type 27 image, stored in 32x32 tiles. Each pixel holds its
+    // own coordinates and the index of its tile.
+    let images = extract_tex(data.as_slice()).unwrap();
+
+    assert_eq!(images[0].dimensions(), (64, 48));
+    for (x, y, pixel) in images[0].enumerate_pixels() {
+        let tile = (y / 32) * 2 + x / 32;
+        assert_eq!(pixel.0, [x as u8, y as u8, tile as u8 * 50, 255]);
+    }
+}
+
+/// Builds `_tex.sc` file data with a single 40x2 RGBA8888 sprite of
+/// `file_type`, whose pixels hold the order they are stored in.
+fn ordered_tex_data(file_type: u8) -> Vec<u8> {
+    let mut payload = vec![file_type];
+    payload.extend_from_slice(&(5 + 40 * 2 * 4u32).to_le_bytes());
+    payload.extend_from_slice(&[0, 40, 0, 2, 0]);
+    for index in 0..80u8 {
+        payload.extend_from_slice(&[index, 0, 0, 255]);
+    }
+    payload.extend_from_slice(&[0; 5]);
+
+    let mut data = b"SC\0\0\0\x01\0\0\0\x10".to_vec();
+    data.extend_from_slice(&[0; 16]);
+    data.extend(compress(&payload).unwrap());
+
+    data
+}
+
+#[test]
+fn test_scanlines() {
+    // File type 1 stores its pixels in plain scanlines, like type 24.
+    for file_type in [1, 24] {
+        let image = &extract_tex(&ordered_tex_data(file_type)).unwrap()[0];
+
+        assert_eq!(image.dimensions(), (40, 2));
+        for (x, y, pixel) in image.enumerate_pixels() {
+            assert_eq!(pixel.0[0], (y * 40 + x) as u8);
+        }
+    }
+
+    // The same data of a tiled type starts with a 32x2 tile.
+    let image = &extract_tex(&ordered_tex_data(27)).unwrap()[0];
+    assert_eq!(image.get_pixel(0, 1).0[0], 32);
+    assert_eq!(image.get_pixel(32, 0).0[0], 64);
+}
+
+#[test]
+fn test_la44() {
+    let data = fs::read("./tests/data/sc/la44_tex.sc").unwrap();
+
+    // An LA44 sprite followed by an RGBA8888 sprite.
+    let images = extract_tex(data.as_slice()).unwrap();
+
+    assert_eq!(images.len(), 2);
+    let pixels: Vec<_> = images[0].pixels().map(|p| p.0).collect();
+    assert_eq!(
+        pixels,
+        vec![
+            [255, 255, 255, 0],
+            [0, 0, 0, 255],
+            [136, 136, 136, 204],
+            [0, 0, 0, 0],
+        ]
+    );
+    let pixels: Vec<_> = images[1].pixels().map(|p| p.0).collect();
+    assert_eq!(pixels, vec![[1, 2, 3, 4], [5, 6, 7, 8]]);
+}
+
+#[test]
+#[cfg(all(feature = "fs", feature = "webp"))]
+fn test_webp() {
+    let path = Path::new("./tests/data/sc/etc1_tex.sc");
+    let data = fs::read(path).unwrap();
+    let out_dir = Path::new("./tests/out/webp");
+
+    prepare_out_dir(out_dir);
+
+    let lossless = TexOptions {
+        format: OutputFormat::WebP {
+            lossless: true,
+            quality: 0.0,
+        },
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "etc1_tex.sc", out_dir, &lossless).unwrap();
+
+    // Lossless WebP decodes to exactly the extracted image. The image is
+    // opaque, so it is decoded without alpha.
+    let saved = fs::read(out_dir.join("etc1_tex.webp")).unwrap();
+    let decoded = webp::Decoder::new(&saved).decode().unwrap();
+    let image = &extract_tex(data.as_slice()).unwrap()[0];
+    let rgb: Vec<_> = image.pixels().flat_map(|p| p.0[..3].to_vec()).collect();
+    assert_eq!(&*decoded, rgb.as_slice());
+
+    let lossy = TexOptions {
+        format: OutputFormat::WebP {
+            lossless: false,
+            quality: 75.0,
+        },
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "etc1_tex.sc", out_dir, &lossy).unwrap();
+
+    let saved = fs::read(out_dir.join("etc1_tex.webp")).unwrap();
+    assert_eq!(&saved[..4], b"RIFF");
+    assert_eq!(&saved[8..12], b"WEBP");
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_jpeg() {
+    let data = fs::read("./tests/data/sc/la44_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/jpeg");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        format: OutputFormat::Jpeg {
+            quality: 100,
+            background: [255, 255, 255],
+        },
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "la44_tex.sc", out_dir, &options).unwrap();
+
+    // Transparent pixels are blended onto the white background, opaque ones
+    // are kept as they are.
+    let saved = image::open(out_dir.join("la44_tex.jpg")).unwrap().to_rgb8();
+    let expected = [255, 0, 160, 255];
+    for (pixel, expected) in saved.pixels().zip(&expected) {
+        for channel in pixel.0.iter() {
+            assert!((*channel as i32 - expected).abs() <= 8, "{:?}", pixel);
+        }
+    }
+    assert!(out_dir.join("la44_tex_.jpg").exists());
+}
+
+/// Reads a DDS file with uncompressed BGRA pixels, checking every header
+/// field strict viewers look at.
+fn read_dds(data: &[u8]) -> image::RgbaImage {
+    let field = |i: usize| u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+
+    assert_eq!(&data[..4], b"DDS ");
+    // Header size and `DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PITCH |
+    // DDSD_PIXELFORMAT`.
+    assert_eq!((field(4), field(8)), (124, 0x100F));
+    let (height, width) = (field(12), field(16));
+    assert_eq!(field(20), width * 4);
+    // Pixel format size, `DDPF_ALPHAPIXELS | DDPF_RGB`, no FourCC, 32 bits
+    // per pixel and the BGRA masks.
+    let pixel_format: Vec<_> = (0..8).map(|i| field(76 + i * 4)).collect();
+    assert_eq!(
+        pixel_format,
+        [32, 0x41, 0, 32, 0xFF_0000, 0xFF00, 0xFF, 0xFF00_0000]
+    );
+    // `DDSCAPS_TEXTURE`.
+    assert_eq!(field(108), 0x1000);
+    assert_eq!(data.len(), 128 + (width * height * 4) as usize);
+
+    let pixels = data[128..]
+        .chunks(4)
+        .flat_map(|p| vec![p[2], p[1], p[0], p[3]])
+        .collect();
+    image::RgbaImage::from_raw(width, height, pixels).unwrap()
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_dds() {
+    let data = fs::read("./tests/data/sc/la44_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/dds");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        format: OutputFormat::Dds,
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "la44_tex.sc", out_dir, &options).unwrap();
+
+    let images = extract_tex(data.as_slice()).unwrap();
+    let saved = fs::read(out_dir.join("la44_tex.dds")).unwrap();
+    assert_eq!(read_dds(&saved), images[0]);
+    let saved = fs::read(out_dir.join("la44_tex_.dds")).unwrap();
+    assert_eq!(read_dds(&saved), images[1]);
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_raw() {
+    let data = fs::read("./tests/data/sc/la44_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/raw");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        format: OutputFormat::Raw,
+        naming: SpriteNaming::Numbered,
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "la44_tex.sc", out_dir, &options).unwrap();
+
+    // The width and height, followed by the pixels.
+    let images = extract_tex(data.as_slice()).unwrap();
+    for (i, image) in images.iter().enumerate() {
+        let saved = fs::read(out_dir.join(format!("la44_tex_{}.rgba", i))).unwrap();
+        assert_eq!(&saved[..4], &image.width().to_le_bytes());
+        assert_eq!(&saved[4..8], &image.height().to_le_bytes());
+        assert_eq!(&saved[8..], image.as_raw().as_slice());
+    }
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_png_compression() {
+    let path = Path::new("./tests/data/sc/tiled_tex.sc");
+    let data = fs::read(path).unwrap();
+    let image = &extract_tex(data.as_slice()).unwrap()[0];
+
+    // Every level saves the same pixels, with more compression making smaller
+    // files.
+    let mut sizes = Vec::new();
+    for (level, png_compression) in [
+        ("fast", PngCompression::Fast),
+        ("default", PngCompression::Default),
+        ("best", PngCompression::Best),
+    ]
+    .iter()
+    {
+        let out_dir = Path::new("./tests/out/png_compression").join(level);
+        prepare_out_dir(&out_dir);
+
+        let options = TexOptions {
+            png_compression: *png_compression,
+            ..Default::default()
+        };
+        process_tex_with(data.as_slice(), get_file_name(path), &out_dir, &options).unwrap();
+
+        let saved = out_dir.join("tiled_tex.png");
+        assert_eq!(&image::open(&saved).unwrap().to_rgba8(), image);
+        sizes.push(fs::metadata(&saved).unwrap().len());
+    }
+    assert!(sizes[0] > sizes[1] && sizes[1] > sizes[2], "{:?}", sizes);
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_file_name() {
+    let data = fs::read("./tests/data/sc/etc1_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/file_name");
+
+    prepare_out_dir(out_dir);
+
+    // Only the trailing `.sc` is stripped.
+    process_tex(data.as_slice(), "ui.sc_atlas_tex.sc", out_dir, true).unwrap();
+    assert!(out_dir.join("ui.sc_atlas_tex.png").exists());
+
+    process_tex(data.as_slice(), "ui.sc_atlas_tex", out_dir, true).unwrap();
+    assert!(out_dir.join("ui.sc_atlas_tex.png").exists());
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_split_alpha() {
+    let out_dir = Path::new("./tests/out/split_alpha");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        split_alpha: true,
+        ..Default::default()
+    };
+
+    // LA44 and RGB565 sprites, the latter without alpha.
+    for name in ["la44_tex.sc", "rgb565_tex.sc"].iter() {
+        let data = fs::read(Path::new("./tests/data/sc").join(name)).unwrap();
+        let extraction = process_tex_with(data.as_slice(), name, out_dir, &options).unwrap();
+
+        let image = &extract_tex(data.as_slice()).unwrap()[0];
+        let sprite = &extraction.sprites[0];
+        let color = image::open(&sprite.path).unwrap();
+        let alpha = image::open(sprite.alpha_path.as_ref().unwrap()).unwrap();
+
+        assert!(matches!(color, DynamicImage::ImageRgb8(_)));
+        assert!(matches!(alpha, DynamicImage::ImageLuma8(_)));
+        assert_eq!((color.to_rgb8(), alpha.to_luma8()), split_alpha(image));
+    }
+
+    assert!(out_dir.join("la44_tex_alpha.png").exists());
+    assert!(out_dir.join("la44_tex__alpha.png").exists());
+    let alpha = image::open(out_dir.join("rgb565_tex_alpha.png")).unwrap();
+    assert!(alpha.to_luma8().pixels().all(|p| p.0 == [255]));
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_bmp_tga() {
+    let data = fs::read("./tests/data/sc/la44_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/bmp_tga");
+
+    prepare_out_dir(out_dir);
+
+    let images = extract_tex(data.as_slice()).unwrap();
+    for format in [OutputFormat::Bmp, OutputFormat::Tga].iter() {
+        let options = TexOptions {
+            format: *format,
+            ..Default::default()
+        };
+        process_tex_with(data.as_slice(), "la44_tex.sc", out_dir, &options).unwrap();
+
+        let path = out_dir.join(format!("la44_tex.{}", format.extension()));
+        assert_eq!(image::open(path).unwrap().to_rgba8(), images[0]);
+        let path = out_dir.join(format!("la44_tex_.{}", format.extension()));
+        assert_eq!(image::open(path).unwrap().to_rgba8(), images[1]);
+    }
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_no_alpha() {
+    let out_dir = Path::new("./tests/out/no_alpha");
+
+    prepare_out_dir(out_dir);
+
+    let mut options = TexOptions {
+        no_alpha: true,
+        ..Default::default()
+    };
+
+    // RGB565 sprites are opaque, so they lose nothing.
+    let data = fs::read("./tests/data/sc/rgb565_tex.sc").unwrap();
+    process_tex_with(data.as_slice(), "rgb565_tex.sc", out_dir, &options).unwrap();
+    let saved = image::open(out_dir.join("rgb565_tex.png")).unwrap();
+    assert!(matches!(saved, DynamicImage::ImageRgb8(_)));
+    assert_eq!(saved.to_rgba8(), extract_tex(data.as_slice()).unwrap()[0]);
+
+    // Transparent sprites keep their alpha channel without a background.
+    let data = fs::read("./tests/data/sc/la44_tex.sc").unwrap();
+    process_tex_with(data.as_slice(), "la44_tex.sc", out_dir, &options).unwrap();
+    let saved = image::open(out_dir.join("la44_tex.png")).unwrap();
+    assert!(matches!(saved, DynamicImage::ImageRgba8(_)));
+
+    // Or are blended onto it.
+    options.alpha_background = Some([255, 255, 255]);
+    process_tex_with(data.as_slice(), "la44_tex.sc", out_dir, &options).unwrap();
+    let saved = image::open(out_dir.join("la44_tex.png")).unwrap();
+    let pixels: Vec<_> = saved.as_rgb8().unwrap().pixels().map(|p| p.0).collect();
+    assert_eq!(
+        pixels,
+        vec![[255, 255, 255], [0, 0, 0], [160, 160, 160], [255, 255, 255]]
+    );
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_tiff() {
+    let data = fs::read("./tests/data/sc/la44_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/tiff");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        format: OutputFormat::Tiff,
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "la44_tex.sc", out_dir, &options).unwrap();
+
+    // The sprites are named like PNG images, and keep their alpha channel.
+    let images = extract_tex(data.as_slice()).unwrap();
+    let saved = image::open(out_dir.join("la44_tex.tiff"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(saved, images[0]);
+    assert!(out_dir.join("la44_tex_.tiff").exists());
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_grayscale() {
+    let data = fs::read("./tests/data/sc/luminance_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/grayscale");
+
+    prepare_out_dir(out_dir);
+
+    // LA88, L8 and RGBA8888 sprites in the same file.
+    let options = TexOptions {
+        grayscale: true,
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "luminance_tex.sc", out_dir, &options).unwrap();
+
+    match image::open(out_dir.join("luminance_tex.png")).unwrap() {
+        DynamicImage::ImageLumaA8(img) => assert_eq!(img.into_raw(), vec![0, 255, 128, 64]),
+        _ => panic!("Expected a grayscale-alpha image."),
+    }
+    match image::open(out_dir.join("luminance_tex_.png")).unwrap() {
+        DynamicImage::ImageLuma8(img) => assert_eq!(img.into_raw(), vec![7, 200]),
+        _ => panic!("Expected a grayscale image."),
     }
+    match image::open(out_dir.join("luminance_tex__.png")).unwrap() {
+        DynamicImage::ImageRgba8(img) => assert_eq!(img.into_raw(), vec![1, 2, 3, 4]),
+        _ => panic!("Expected an RGBA image."),
+    }
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_block_size() {
+    let data = fs::read("./tests/data/sc/tiled64_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/block_size");
+
+    prepare_out_dir(out_dir);
+
+    // A 100x70 file type 28 image, stored in 64x64 tiles. Each pixel holds its
+    // own coordinates and the index of its tile.
+    let options = TexOptions {
+        block_size: Some(64),
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "tiled64_tex.sc", out_dir, &options).unwrap();
+
+    let image = image::open(out_dir.join("tiled64_tex.png"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(image.dimensions(), (100, 70));
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let tile = (y / 64) * 2 + x / 64;
+        assert_eq!(pixel.0, [x as u8, y as u8, tile as u8 * 50, 255]);
+    }
+
+    let options = TexOptions {
+        block_size: Some(0),
+        ..Default::default()
+    };
+    assert!(process_tex_with(data.as_slice(), "tiled64_tex.sc", out_dir, &options).is_err());
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_zero_size() {
+    let data = fs::read("./tests/data/sc/zero_size_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/zero_size");
+
+    prepare_out_dir(out_dir);
+
+    // The 0x4 placeholder sprite is skipped, along with its stray bytes.
+    let images = extract_tex(data.as_slice()).unwrap();
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].dimensions(), (2, 2));
+    assert!(images[0].pixels().all(|p| p.0 == [10, 20, 30, 255]));
+
+    process_tex(data.as_slice(), "zero_size_tex.sc", out_dir, false).unwrap();
+    assert_eq!(fs::read_dir(out_dir).unwrap().count(), 1);
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_max_size() {
+    let data = fs::read("./tests/data/sc/huge_tex.sc").unwrap();
+
+    // The 20000x2 sprite is wider than the default maximum, so it is skipped.
+    let images = extract_tex(data.as_slice()).unwrap();
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].dimensions(), (2, 2));
+
+    let out_dir = Path::new("./tests/out/max_size");
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        max_size: Some(20000),
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "huge_tex.sc", out_dir, &options).unwrap();
+
+    let image = image::open(out_dir.join("huge_tex.png"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(image.dimensions(), (20000, 2));
+    assert!(out_dir.join("huge_tex_.png").exists());
+}
+
+#[test]
+fn test_ktx() {
+    let data = fs::read("./tests/data/sc/ktx_tex.sc").unwrap();
+
+    // A 13x7 ASTC 6x5 texture, a 3x2 RGBA8 texture with two mipmap levels and
+    // an RGTC texture, which can't be decoded and is skipped.
+    let images = extract_tex(data.as_slice()).unwrap();
+
+    assert_eq!(images.len(), 2);
+    let golden = image::open("./tests/data/golden/astc_tex_.png")
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(images[0], golden);
+    assert_eq!(images[1].dimensions(), (3, 2));
+    assert_eq!(images[1].as_raw(), &(0..24).collect::<Vec<u8>>());
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_extraction() {
+    let data = fs::read("./tests/data/sc/la44_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/extraction");
+
+    prepare_out_dir(out_dir);
+
+    let extraction = process_tex(data.as_slice(), "la44_tex.sc", out_dir, true).unwrap();
+
+    // A 2x2 LA44 sprite and a 2x1 RGBA8888 sprite.
+    let sprites = extraction.sprites;
+    assert_eq!(sprites.len(), 2);
+    assert_eq!(
+        sprites[0].metadata,
+        TexMetadata {
+            index: 0,
+            file_type: 1,
+            sub_type: 5,
+            width: 2,
+            height: 2,
+            partial: false,
+            crop: None,
+        }
+    );
+    assert_eq!(sprites[0].chunk_size, 9);
+    assert_eq!(sprites[0].path, out_dir.join("la44_tex.png"));
+    assert_eq!(sprites[1].metadata.sub_type, 0);
+    assert_eq!(sprites[1].path, out_dir.join("la44_tex_.png"));
+    assert!(sprites.iter().all(|sprite| sprite.path.exists()));
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_numbered_names() {
+    let data = fs::read("./tests/data/sc/la44_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/numbered_names");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        naming: SpriteNaming::Numbered,
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "la44_tex.sc", out_dir, &options).unwrap();
+
+    assert!(out_dir.join("la44_tex_0.png").exists());
+    assert!(out_dir.join("la44_tex_1.png").exists());
+    assert!(!out_dir.join("la44_tex.png").exists());
+
+    // The indices are padded to the width of the last one.
+    assert_eq!(
+        SpriteNaming::Numbered.sprite_name("ui_tex", 7, 12),
+        "ui_tex_07"
+    );
+    assert_eq!(SpriteNaming::Numbered.sprite_name("ui_tex", 0, 1), "ui_tex");
+    assert_eq!(
+        SpriteNaming::Underscores.sprite_name("ui_tex", 2, 12),
+        "ui_tex__"
+    );
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_metadata() {
+    let data = fs::read("./tests/data/sc/la44_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/metadata");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        metadata: true,
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "la44_tex.sc", out_dir, &options).unwrap();
+
+    // A 2x2 LA44 sprite and a 2x1 RGBA8888 sprite.
+    let expected = [
+        ("la44_tex.json", 0, 5, 2, 2),
+        ("la44_tex_.json", 1, 0, 2, 1),
+    ];
+    for (name, index, sub_type, width, height) in expected.iter() {
+        let json = fs::read_to_string(out_dir.join(name)).unwrap();
+        assert_eq!(
+            json,
+            format!(
+                "{{\n  \"index\": {},\n  \"file_type\": 1,\n  \"sub_type\": {},\n  \"width\": {},\n  \"height\": {},\n  \"partial\": false\n}}",
+                index, sub_type, width, height
+            )
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_crop() {
+    let data = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/crop");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        crop: true,
+        metadata: true,
+        ..Default::default()
+    };
+    let sprites = process_tex_with(data.as_slice(), "margin_tex.sc", out_dir, &options)
+        .unwrap()
+        .sprites;
+
+    // A 6x5 sprite whose only visible pixels are at x 2-3, y 1-3. Pixels with
+    // any alpha are kept.
+    let image = &extract_tex(data.as_slice()).unwrap()[0];
+    let saved = image::open(&sprites[0].path).unwrap().to_rgba8();
+    assert_eq!(
+        saved,
+        image::imageops::crop_imm(image, 2, 1, 2, 3).to_image()
+    );
+    assert_eq!(
+        sprites[0].metadata.crop,
+        Some(SpriteCrop {
+            x: 2,
+            y: 1,
+            original_width: 6,
+            original_height: 5,
+        })
+    );
+    let json = fs::read_to_string(out_dir.join("margin_tex.json")).unwrap();
+    assert!(json.contains("\"width\": 2,\n  \"height\": 3,"));
+    assert!(json.contains("\"crop\": {\n    \"x\": 2,\n    \"y\": 1,\n    \"original_width\": 6,\n    \"original_height\": 5\n  }"));
+
+    // A fully transparent sprite is saved as a single transparent pixel.
+    let saved = image::open(&sprites[1].path).unwrap().to_rgba8();
+    assert_eq!(saved.dimensions(), (1, 1));
+    assert_eq!(saved.get_pixel(0, 0).0, [0, 0, 0, 0]);
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_skip_transparent() {
+    let data = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/skip_transparent");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        crop: true,
+        skip_transparent: true,
+        ..Default::default()
+    };
+    let sprites = process_tex_with(data.as_slice(), "margin_tex.sc", out_dir, &options)
+        .unwrap()
+        .sprites;
+
+    // The second sprite is fully transparent.
+    assert_eq!(sprites.len(), 1);
+    assert_eq!(sprites[0].metadata.index, 0);
+    assert!(!out_dir.join("margin_tex_.png").exists());
+}
+
+#[test]
+fn test_overflowing_sprites() {
+    let data = fs::read("./tests/data/sc/overflow_tex.sc").unwrap();
+
+    // The first two sprites need more data than their chunks hold. They are
+    // skipped without reading into the sprite after them.
+    let images = extract_tex(data.as_slice()).unwrap();
+
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].dimensions(), (1, 1));
+    assert_eq!(images[0].get_pixel(0, 0).0, [1, 2, 3, 4]);
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_only_index() {
+    let data = fs::read("./tests/data/sc/astc_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/only_index");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        only_index: Some(1),
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "astc_tex.sc", out_dir, &options).unwrap();
+
+    // Only the second sprite is saved, with the same name it gets when all
+    // sprites are saved.
+    assert_eq!(fs::read_dir(out_dir).unwrap().count(), 1);
+    let image = image::open(out_dir.join("astc_tex_.png")).unwrap();
+    let golden = image::open("./tests/data/golden/astc_tex_.png").unwrap();
+    assert_eq!(image.to_rgba8(), golden.to_rgba8());
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_builder() {
+    let options = TexOptions::new()
+        .format(OutputFormat::Bmp)
+        .only_index(1)
+        .crop(true);
+    assert_eq!(
+        options,
+        TexOptions {
+            format: OutputFormat::Bmp,
+            only_index: Some(1),
+            crop: true,
+            ..Default::default()
+        }
+    );
+
+    let data = fs::read("./tests/data/sc/astc_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/builder");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions::new().only_index(1).parallelize(true);
+    process_tex_with(data.as_slice(), "astc_tex.sc", out_dir, &options).unwrap();
+
+    assert_eq!(fs::read_dir(out_dir).unwrap().count(), 1);
+    let image = image::open(out_dir.join("astc_tex_.png")).unwrap();
+    let golden = image::open("./tests/data/golden/astc_tex_.png").unwrap();
+    assert_eq!(image.to_rgba8(), golden.to_rgba8());
+
+    // The deprecated function takes `parallelize` as a parameter instead.
+    #[allow(deprecated)]
+    let extraction = sc_extract::process_tex_with_options(
+        data.as_slice(),
+        "astc_tex.sc",
+        out_dir,
+        true,
+        &TexOptions::new().only_index(1),
+    )
+    .unwrap();
+    assert_eq!(extraction.sprites.len(), 1);
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_sprites() {
+    let data = fs::read("./tests/data/sc/astc_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/sprites");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        sprites: Some(vec![0..=0, 2..=5]),
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "astc_tex.sc", out_dir, &options).unwrap();
+
+    // The second sprite is skipped. The others keep their names.
+    assert_eq!(fs::read_dir(out_dir).unwrap().count(), 2);
+    assert!(out_dir.join("astc_tex.png").exists());
+    let image = image::open(out_dir.join("astc_tex__.png")).unwrap();
+    let golden = image::open("./tests/data/golden/astc_tex__.png").unwrap();
+    assert_eq!(image.to_rgba8(), golden.to_rgba8());
+}
+
+#[test]
+fn test_trailing_data() {
+    let data = fs::read("./tests/data/sc/trailing_tex.sc").unwrap();
+
+    // The first sprite's chunk has 12 bytes after its pixels. The second
+    // sprite is still read from the start of its own chunk.
+    let images = extract_tex(data.as_slice()).unwrap();
+
+    assert_eq!(images.len(), 2);
+    assert_eq!(images[0].get_pixel(0, 0).0, [1, 2, 3, 4]);
+    assert_eq!(images[1].dimensions(), (1, 1));
+    assert_eq!(images[1].get_pixel(0, 0).0, [5, 6, 7, 8]);
+}
+
+#[test]
+fn test_unknown_pixel() {
+    let data = fs::read("./tests/data/sc/unknown_pixel_tex.sc").unwrap();
+
+    // The sprite with the unknown sub-type is skipped as a whole.
+    let images = extract_tex(data.as_slice()).unwrap();
+
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].get_pixel(0, 0).0, [9, 8, 7, 6]);
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_dump_unknown() {
+    let data = fs::read("./tests/data/sc/unknown_chunk_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/dump_unknown");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        dump_unknown: true,
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "unknown_chunk_tex.sc", out_dir, &options).unwrap();
+
+    // The unknown chunk comes after a 14-byte sprite chunk.
+    let chunk = fs::read(out_dir.join("unknown_chunk_tex_14_type9.bin")).unwrap();
+    assert_eq!(chunk, b"NEWFMT");
+    let index = fs::read_to_string(out_dir.join("unknown_chunk_tex_unknown.json")).unwrap();
+    assert_eq!(
+        index,
+        "[\n  {\n    \"offset\": 14,\n    \"file_type\": 9,\n    \"size\": 6\n  }\n]"
+    );
+    assert!(out_dir.join("unknown_chunk_tex_.png").exists());
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_fill_invalid() {
+    let data = fs::read("./tests/data/sc/truncated_pixels_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/fill_invalid");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        fill_invalid: true,
+        metadata: true,
+        ..Default::default()
+    };
+    process_tex_with(
+        data.as_slice(),
+        "truncated_pixels_tex.sc",
+        out_dir,
+        &options,
+    )
+    .unwrap();
+
+    // A 4x4 RGBA8888 sprite with 10 bytes of data: two whole pixels.
+    let image = image::open(out_dir.join("truncated_pixels_tex.png"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(image.get_pixel(0, 0).0, [0, 1, 2, 3]);
+    assert_eq!(image.get_pixel(1, 0).0, [4, 5, 6, 7]);
+    assert!(image.pixels().skip(2).all(|p| p.0 == [255, 0, 255, 255]));
+
+    let json = fs::read_to_string(out_dir.join("truncated_pixels_tex.json")).unwrap();
+    assert!(json.contains("\"partial\": true"));
+}
+
+#[test]
+fn test_pack() {
+    // The file and pixel types of every sprite in each file.
+    let files: [(&str, &[(u8, u8)]); 6] = [
+        ("tiled_tex.sc", &[(27, 0)]),
+        ("luminance_tex.sc", &[(1, 6), (1, 10), (1, 0)]),
+        ("la44_tex.sc", &[(1, 5), (1, 0)]),
+        ("rgba4444_tex.sc", &[(1, 2)]),
+        ("rgb565_tex.sc", &[(1, 4)]),
+        ("rgba5551_tex.sc", &[(1, 3)]),
+    ];
+
+    for (file, types) in files.iter() {
+        let data = fs::read(Path::new("./tests/data/sc").join(file)).unwrap();
+        let images = extract_tex(data.as_slice()).unwrap();
+
+        let sprites: Vec<_> = images
+            .iter()
+            .zip(types.iter())
+            .map(|(image, (file_type, sub_type))| TexSprite {
+                image: image.clone(),
+                file_type: *file_type,
+                sub_type: *sub_type,
+            })
+            .collect();
+        let packed = pack_tex(&sprites).unwrap();
+
+        assert_eq!(extract_tex(packed.as_slice()).unwrap(), images, "{}", file);
+    }
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_read_sprites() {
+    let cases = [
+        (
+            "luminance_tex.sc",
+            SpriteNaming::Numbered,
+            false,
+            "luminance_tex_0.png",
+        ),
+        (
+            "margin_tex.sc",
+            SpriteNaming::Underscores,
+            true,
+            "margin_tex.png",
+        ),
+    ];
+
+    for (file, naming, crop, first) in cases.iter() {
+        let data = fs::read(Path::new("./tests/data/sc").join(file)).unwrap();
+        let out_dir = Path::new("./tests/out/read_sprites").join(file);
+
+        prepare_out_dir(&out_dir);
+
+        let options = TexOptions {
+            naming: *naming,
+            crop: *crop,
+            metadata: true,
+            ..Default::default()
+        };
+        process_tex_with(data.as_slice(), file, &out_dir, &options).unwrap();
+
+        // Only the image of the first sprite finds the others.
+        let second = fs::read_dir(&out_dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .find(|p| p.extension().unwrap() == "png" && !p.ends_with(first))
+            .unwrap();
+        assert!(read_tex_sprites(&second, 1, 0).unwrap().is_none());
+
+        // The types are read from the metadata, and cropped sprites are
+        // restored, so packing them gives back the same pixels. Only the color
+        // of fully transparent pixels cut off by cropping is lost.
+        let (name, sprites) = read_tex_sprites(&out_dir.join(first), 1, 0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(&name, file);
+
+        let clear = |images: Vec<image::RgbaImage>| {
+            images
+                .into_iter()
+                .map(|mut image| {
+                    image
+                        .pixels_mut()
+                        .filter(|p| p[3] == 0)
+                        .for_each(|p| p.0 = [0; 4]);
+                    image
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            clear(extract_tex(&pack_tex(&sprites).unwrap()).unwrap()),
+            clear(extract_tex(data.as_slice()).unwrap()),
+            "{}",
+            file
+        );
+    }
+
+    // Sprites without metadata use the given types.
+    let out_dir = Path::new("./tests/out/read_sprites/plain");
+    prepare_out_dir(out_dir);
+    let data = fs::read("./tests/data/sc/rgba4444_tex.sc").unwrap();
+    process_tex(data.as_slice(), "rgba4444_tex.sc", out_dir, true).unwrap();
+
+    let (_, sprites) = read_tex_sprites(&out_dir.join("rgba4444_tex.png"), 28, 2)
+        .unwrap()
+        .unwrap();
+    assert_eq!((sprites[0].file_type, sprites[0].sub_type), (28, 2));
+    assert!(read_tex_sprites(&out_dir.join("missing.png"), 1, 0)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_large() {
+    let out_dir = Path::new("./tests/out/large");
+
+    prepare_out_dir(out_dir);
+
+    let image = image::RgbaImage::from_fn(300, 301, |x, y| {
+        image::Rgba([x as u8, y as u8, (x ^ y) as u8, (x + y) as u8])
+    });
+
+    for sub_type in [0, 4, 6].iter() {
+        let sprite = TexSprite {
+            image: image.clone(),
+            file_type: 1,
+            sub_type: *sub_type,
+        };
+        let packed = pack_tex(std::slice::from_ref(&sprite)).unwrap();
+        let expected = extract_tex(
+            &pack_tex(&[TexSprite {
+                file_type: 28,
+                ..sprite
+            }])
+            .unwrap(),
+        )
+        .unwrap();
+
+        // Tiled images are decoded one pixel at a time.
+        assert_eq!(extract_tex(&packed).unwrap(), expected, "{}", sub_type);
+
+        // Large untiled images are decoded in parallel, in bands of rows,
+        // with `parallelize`.
+        let options = TexOptions::new().parallelize(true);
+        let extraction = process_tex_with(&packed, "large_tex.sc", out_dir, &options).unwrap();
+        let saved = image::open(&extraction.sprites[0].path).unwrap();
+        assert_eq!(saved.to_rgba8(), expected[0], "{}", sub_type);
+    }
+}
+
+#[test]
+#[cfg(all(feature = "fs", feature = "zstd"))]
+fn test_streaming() {
+    let out_dir = Path::new("./tests/out/streaming");
+
+    prepare_out_dir(out_dir);
+
+    let sprites: Vec<_> = (0..3u8)
+        .map(|i| TexSprite {
+            image: image::RgbaImage::from_fn(300, 301, |x, y| {
+                image::Rgba([x as u8, y as u8, i, (x ^ y) as u8])
+            }),
+            file_type: 1,
+            sub_type: 0,
+        })
+        .collect();
+    let payload = decompress_sc(&pack_tex(&sprites).unwrap()).unwrap();
+
+    // zstd writes its output in blocks of at most 128 KiB, so every sprite is
+    // split across several writes.
+    let mut data = b"SC\0\0\0\x01\0\0\0\x10".to_vec();
+    data.extend_from_slice(&[0x11; 16]);
+    data.extend_from_slice(&zstd::stream::encode_all(payload.as_slice(), 0).unwrap());
+
+    let images = extract_tex(&data).unwrap();
+    assert_eq!(images.len(), 3);
+    for (image, sprite) in images.iter().zip(&sprites) {
+        assert_eq!(image, &sprite.image);
+    }
+
+    // Sprites are saved as they are decompressed, before the data ends.
+    data.truncate(data.len() / 2);
+    assert!(matches!(
+        process_tex(&data, "streaming_tex.sc", out_dir, true),
+        Err(Error::DecompressionError(_))
+    ));
+    assert_eq!(
+        image::open(out_dir.join("streaming_tex.png"))
+            .unwrap()
+            .to_rgba8(),
+        sprites[0].image
+    );
+}
+
+#[test]
+fn test_pack_header() {
+    let image = image::RgbaImage::from_raw(1, 1, vec![1, 2, 3, 4]).unwrap();
+    let sprite = TexSprite {
+        image,
+        file_type: 1,
+        sub_type: 0,
+    };
+    let packed = pack_tex(std::slice::from_ref(&sprite)).unwrap();
+
+    // Version 1, followed by the MD5 hash of the uncompressed chunks.
+    assert_eq!(&packed[..10], b"SC\0\0\0\x01\0\0\0\x10");
+    assert_eq!(
+        hex::encode(&packed[10..26]),
+        "13b3a11d41d9d1b34c53c36c9103f3ff"
+    );
+    assert_eq!(
+        decompress(&packed[26..]).unwrap(),
+        [1, 9, 0, 0, 0, 0, 1, 0, 1, 0, 1, 2, 3, 4, 0, 0, 0, 0, 0]
+    );
+
+    let block = TexSprite {
+        sub_type: 15,
+        ..sprite.clone()
+    };
+    assert!(matches!(pack_tex(&[block]), Err(Error::UnknownPixel(_))));
+
+    let ktx = TexSprite {
+        file_type: 45,
+        ..sprite
+    };
+    assert!(matches!(pack_tex(&[ktx]), Err(Error::Other(_))));
+}
+
+#[test]
+fn test_compress() {
+    let data: Vec<u8> = (0..1000).map(|i| (i % 7) as u8).collect();
+
+    let compressed = compress(&data).unwrap();
+
+    // The size of the data is stored in 4 bytes, like Supercell's files.
+    assert_eq!(&compressed[5..9], &1000u32.to_le_bytes());
+    assert_eq!(decompress(&compressed).unwrap(), data);
+}
+
+#[test]
+fn test_xz() {
+    let data: Vec<u8> = (0..1000).map(|i| (i % 7) as u8).collect();
+
+    let mut compressed = Vec::new();
+    lzma_rs::xz_compress(&mut data.as_slice(), &mut compressed).unwrap();
+    assert_eq!(decompress(&compressed).unwrap(), data);
+
+    // Truncated streams fail like other codecs.
+    let truncated = &compressed[..compressed.len() - 10];
+    assert!(matches!(
+        decompress(truncated),
+        Err(Error::DecompressionError(_))
+    ));
+}
+
+#[test]
+fn test_lz4() {
+    let data = fs::read("./tests/data/sc/lz4_tex.sc").unwrap();
+    assert_eq!(detect_codec(&data[26..]), Codec::Lz4);
+
+    // The file holds the sprites of `margin_tex.sc` in an LZ4 frame.
+    let expected = extract_tex(&fs::read("./tests/data/sc/margin_tex.sc").unwrap()).unwrap();
+    assert_eq!(extract_tex(data.as_slice()).unwrap(), expected);
+
+    let truncated = &data[26..data.len() - 10];
+    assert!(matches!(
+        decompress(truncated),
+        Err(Error::DecompressionError(_))
+    ));
+}
+
+#[test]
+#[cfg(any(
+    feature = "lzham-rust",
+    all(
+        feature = "lzham-native",
+        any(target_os = "macos", target_os = "linux")
+    )
+))]
+fn test_lzham() {
+    let data = fs::read("./tests/data/sc/lzham_tex.sc").unwrap();
+    assert_eq!(detect_codec(&data[26..]), Codec::Lzham);
+
+    // The file holds the sprites of `background_basic_tex.sc`, compressed
+    // with the LZHAM library and a dictionary of 2^18 bytes.
+    let expected =
+        extract_tex(&fs::read("./tests/data/sc/background_basic_tex.sc").unwrap()).unwrap();
+    assert_eq!(extract_tex(data.as_slice()).unwrap(), expected);
+
+    let truncated = &data[26..data.len() - 10];
+    match decompress(truncated) {
+        Err(Error::DecompressionError(e)) => assert!(e.contains("LZHAM"), "{}", e),
+        other => panic!("unexpected result: {:?}", other.map(|d| d.len())),
+    }
+
+    let mut corrupt = data[26..].to_vec();
+    corrupt[1000] ^= 0xFF;
+    assert!(matches!(
+        decompress(&corrupt),
+        Err(Error::DecompressionError(_))
+    ));
+}
+
+#[test]
+#[cfg(not(any(
+    feature = "lzham-rust",
+    all(
+        feature = "lzham-native",
+        any(target_os = "macos", target_os = "linux")
+    )
+)))]
+fn test_lzham_unavailable() {
+    let data = fs::read("./tests/data/sc/lzham_tex.sc").unwrap();
+    match decompress(&data[26..]) {
+        Err(Error::DecompressionError(e)) => assert!(e.contains("`lzham-rust` feature"), "{}", e),
+        other => panic!("unexpected result: {:?}", other.map(|d| d.len())),
+    }
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_decompressed_size_limit() {
+    let data = fs::read("./tests/data/sc/lz4_tex.sc").unwrap();
+    let size = decompress(&data[26..]).unwrap().len();
+
+    // LZ4 frames don't declare their size, so decompression stops once the
+    // limit is crossed.
+    assert_eq!(
+        decompress_with_limit(&data[26..], size).unwrap().len(),
+        size
+    );
+    match decompress_with_limit(&data[26..], size - 1) {
+        Err(Error::DecompressionError(e)) => assert!(
+            e.contains(&format!("is larger than the limit of {} bytes", size - 1)),
+            "{}",
+            e
+        ),
+        result => panic!("Expected an error, got {:?}", result),
+    }
+
+    let out_dir = Path::new("./tests/out/decompressed_size_limit");
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        max_decompressed_size: Some(size - 1),
+        ..Default::default()
+    };
+    let result = process_tex_with(&data, "lz4_tex.sc", out_dir, &options);
+    assert!(matches!(result, Err(Error::DecompressionError(_))));
+}
+
+#[test]
+fn test_decode_pixel() {
+    // RGB565 white, followed by a byte of the next pixel.
+    assert_eq!(
+        decode_pixel(&[0xFF, 0xFF, 0x12], 4).unwrap(),
+        ([255, 255, 255, 255], 2)
+    );
+    // RGBA5551 with red and alpha.
+    assert_eq!(
+        decode_pixel(&0xF801u16.to_le_bytes(), 3).unwrap(),
+        ([255, 0, 0, 255], 2)
+    );
+    assert_eq!(decode_pixel(&[0x8F], 5).unwrap(), ([136, 136, 136, 255], 1));
+    assert_eq!(decode_pixel(&[1, 2, 3, 4], 0).unwrap(), ([1, 2, 3, 4], 4));
+
+    assert!(matches!(
+        decode_pixel(&[1, 2, 3], 1),
+        Err(Error::UnexpectedEof(_))
+    ));
+    assert!(matches!(
+        decode_pixel(&[1, 2, 3, 4], 11),
+        Err(Error::UnknownPixel(_))
+    ));
+}
+
+#[test]
+fn test_versions() {
+    let data = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+    let expected = extract_tex(data.as_slice()).unwrap();
+
+    let with_version = |version: u32| {
+        let mut file = b"SC".to_vec();
+        file.extend_from_slice(&version.to_be_bytes());
+        if version == 4 {
+            file.extend_from_slice(&1u32.to_be_bytes());
+        }
+        // The hash and the compressed data.
+        file.extend_from_slice(&data[6..]);
+        file
+    };
+
+    for version in 0..=6 {
+        let file = with_version(version);
+        assert_eq!(
+            extract_tex(file.as_slice()).unwrap(),
+            expected,
+            "version {}",
+            version
+        );
+    }
+
+    assert!(matches!(
+        extract_tex(with_version(7).as_slice()),
+        Err(Error::UnsupportedVersion(_))
+    ));
+
+    let mut file = with_version(1);
+    file[..2].copy_from_slice(b"XX");
+    assert!(matches!(
+        extract_tex(file.as_slice()),
+        Err(Error::DecompressionError(_))
+    ));
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_hash_check() {
+    let out_dir = Path::new("./tests/out/hash_check");
+
+    prepare_out_dir(out_dir);
+
+    let image = image::RgbaImage::from_raw(1, 1, vec![1, 2, 3, 4]).unwrap();
+    let sprite = TexSprite {
+        image,
+        file_type: 1,
+        sub_type: 0,
+    };
+    let packed = pack_tex(&[sprite]).unwrap();
+
+    let options = TexOptions {
+        hash_check: HashCheck::Fail,
+        ..Default::default()
+    };
+    process_tex_with(packed.as_slice(), "packed_tex.sc", out_dir, &options).unwrap();
+
+    // The hash of this file isn't that of its data.
+    let data = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+    match process_tex_with(data.as_slice(), "margin_tex.sc", out_dir, &options) {
+        Err(Error::HashMismatch(e)) => assert!(e.contains("11111111111111111111111111111111")),
+        res => panic!("Expected a hash mismatch, got {:?}", res),
+    }
+
+    // SHA-1 and SHA-256 hashes are told apart by their lengths.
+    let output = decompress_sc(&data).unwrap();
+    let with_hash = |hash: &[u8]| {
+        let mut with_hash = data[..6].to_vec();
+        with_hash.extend_from_slice(&(hash.len() as u32).to_be_bytes());
+        with_hash.extend_from_slice(hash);
+        with_hash.extend_from_slice(&data[26..]);
+        with_hash
+    };
+    for hash in [
+        Sha1::digest(&output).to_vec(),
+        Sha256::digest(&output).to_vec(),
+    ]
+    .iter()
+    {
+        let sprites = process_tex_with(&with_hash(hash), "sha_tex.sc", out_dir, &options)
+            .unwrap()
+            .sprites;
+        assert_eq!(sprites.len(), 2);
+
+        let mut wrong_hash = hash.clone();
+        wrong_hash[0] ^= 0xFF;
+        match process_tex_with(&with_hash(&wrong_hash), "sha_tex.sc", out_dir, &options) {
+            Err(Error::HashMismatch(e)) => assert!(e.contains(&hex::encode(&wrong_hash))),
+            res => panic!("Expected a hash mismatch, got {:?}", res),
+        }
+    }
+
+    // Hashes of other lengths can't be verified, so they are skipped.
+    let short_hash = with_hash(&[0x22; 8]);
+    let sprites = process_tex_with(&short_hash, "margin_tex.sc", out_dir, &options)
+        .unwrap()
+        .sprites;
+    assert_eq!(sprites.len(), 2);
+
+    let options = TexOptions {
+        hash_check: HashCheck::Warn,
+        ..Default::default()
+    };
+    let sprites = process_tex_with(&data, "margin_tex.sc", out_dir, &options)
+        .unwrap()
+        .sprites;
+    assert_eq!(sprites.len(), 2);
+}
+
+#[test]
+fn test_extract_to_pngs() {
+    let data = fs::read("./tests/data/sc/luminance_tex.sc").unwrap();
+
+    let images = extract_tex(data.as_slice()).unwrap();
+    let pngs = extract_tex_to_pngs(data.as_slice()).unwrap();
+
+    assert_eq!(pngs.len(), images.len());
+    for (png, image) in pngs.iter().zip(&images) {
+        let decoded = image::load_from_memory_with_format(png, image::ImageFormat::Png).unwrap();
+        assert_eq!(&decoded.to_rgba8(), image);
+    }
+}
+
+#[test]
+fn test_sc_header() {
+    let data = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+
+    let header = read_sc_header(&data).unwrap();
+    assert_eq!(
+        header,
+        ScHeader {
+            version: 1,
+            hash: vec![0x11; 16],
+            data_offset: 26,
+            codec: Codec::Lzma,
+        }
+    );
+    assert_eq!(
+        decompress_sc(&data).unwrap(),
+        decompress(&data[26..]).unwrap()
+    );
+
+    let data = fs::read("./tests/data/sc/lz4_tex.sc").unwrap();
+    assert_eq!(read_sc_header(&data).unwrap().codec, Codec::Lz4);
+
+    // The codec of split data is that of its first section.
+    let data = fs::read("./tests/data/sc/sections_tex.sc").unwrap();
+    let header = read_sc_header(&data).unwrap();
+    assert_eq!(header.codec, Codec::Lzma);
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_combine() {
+    let data = fs::read("./tests/data/sc/luminance_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/combine");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        combine: true,
+        ..Default::default()
+    };
+    let extraction =
+        process_tex_with(data.as_slice(), "luminance_tex.sc", out_dir, &options).unwrap();
+
+    // Only the atlas and its manifest are saved.
+    assert!(extraction.sprites.is_empty());
+    assert_eq!(fs::read_dir(out_dir).unwrap().count(), 2);
+
+    let atlas = extraction.atlas.unwrap();
+    assert_eq!(atlas.path, out_dir.join("luminance_tex_atlas.png"));
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&atlas.manifest_path).unwrap()).unwrap();
+    assert_eq!(manifest.as_array().unwrap().len(), 3);
+    assert_eq!(manifest[1]["index"], 1);
+
+    // Every sprite is found at its rectangle, and none overlap.
+    let image = image::open(&atlas.path).unwrap().to_rgba8();
+    let images = extract_tex(data.as_slice()).unwrap();
+    for (sprite, expected) in atlas.sprites.iter().zip(&images) {
+        let placed =
+            image::imageops::crop_imm(&image, sprite.x, sprite.y, sprite.width, sprite.height)
+                .to_image();
+        assert_eq!(&placed, expected, "{}", sprite.index);
+        assert_eq!(manifest[sprite.index]["x"], sprite.x);
+
+        for other in atlas.sprites.iter().filter(|o| o.index != sprite.index) {
+            assert!(
+                sprite.x + sprite.width <= other.x
+                    || other.x + other.width <= sprite.x
+                    || sprite.y + sprite.height <= other.y
+                    || other.y + other.height <= sprite.y
+            );
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_unmultiply_alpha() {
+    // Premultiplied colors, with half, full and no alpha.
+    let image = image::RgbaImage::from_raw(3, 1, vec![64, 32, 0, 128, 10, 20, 30, 255, 5, 5, 5, 0])
+        .unwrap();
+    let sprite = TexSprite {
+        image,
+        file_type: 1,
+        sub_type: 0,
+    };
+    let data = pack_tex(&[sprite]).unwrap();
+    let out_dir = Path::new("./tests/out/unmultiply_alpha");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        unmultiply_alpha: true,
+        ..Default::default()
+    };
+    let sprites = process_tex_with(&data, "unmultiply_tex.sc", out_dir, &options)
+        .unwrap()
+        .sprites;
+
+    let saved = image::open(&sprites[0].path).unwrap().to_rgba8();
+    assert_eq!(
+        saved.into_raw(),
+        [128, 64, 0, 128, 10, 20, 30, 255, 0, 0, 0, 0]
+    );
+}
+
+#[test]
+fn test_hash_length() {
+    let mut data = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+
+    // A hash length far past the end of the file.
+    data[6..10].copy_from_slice(&0xFFFF_FFF0u32.to_be_bytes());
+
+    let expected = format!(
+        "Hash length in the header (4294967280 bytes) is larger than the rest of the file ({} bytes).",
+        data.len() - 10
+    );
+    for result in [
+        read_sc_header(&data).map(|_| ()),
+        extract_tex(&data).map(|_| ()),
+    ]
+    .iter()
+    {
+        match result {
+            Err(Error::DecompressionError(e)) => assert_eq!(e, &expected),
+            _ => panic!("Expected an error, got {:?}", result),
+        }
+    }
+}
+
+#[test]
+fn test_detect_codec() {
+    let data: Vec<u8> = (0..100).collect();
+
+    assert_eq!(detect_codec(&compress(&data).unwrap()), Codec::Lzma);
+    assert_eq!(detect_codec(b"SCLZ\x12\x00\x00\x00\x00"), Codec::Lzham);
+    assert_eq!(detect_codec(&[40, 181, 47, 253, 0]), Codec::Zstd);
+    assert_eq!(detect_codec(b"\xFD7zXZ\x00\x00\x04"), Codec::Xz);
+    assert_eq!(detect_codec(&[4, 34, 77, 24, 96]), Codec::Lz4);
+    assert_eq!(detect_codec(&data), Codec::Uncompressed);
+    assert_eq!(detect_codec(&[]), Codec::Uncompressed);
+
+    // This file's LZMA data uses the `0x5E` properties byte, not `0x5D`.
+    let data = fs::read("./tests/data/sc/background_basic_tex.sc").unwrap();
+    assert_eq!(data[26], 0x5E);
+    assert_eq!(detect_codec(&data[26..]), Codec::Lzma);
+
+    assert_eq!(detect_codec(&[0, 0, 0, 0x40, 0]), Codec::Lzma);
+    assert_eq!(detect_codec(&[0x5D, 0, 0, 0x30, 0]), Codec::Lzma);
+    assert_eq!(detect_codec(&[0xE1, 0, 0, 0x40, 0]), Codec::Uncompressed);
+    assert_eq!(detect_codec(&[0x5D, 0x05, 0, 0x48, 0]), Codec::Uncompressed);
+    assert_eq!(detect_codec(&[0x5D, 0, 0x08, 0, 0]), Codec::Uncompressed);
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_dry_run() {
+    let data = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/dry_run");
+
+    if out_dir.exists() {
+        fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    let options = TexOptions {
+        dry_run: true,
+        metadata: true,
+        split_alpha: true,
+        ..Default::default()
+    };
+    let sprites = process_tex_with(data.as_slice(), "margin_tex.sc", out_dir, &options)
+        .unwrap()
+        .sprites;
+
+    // Nothing is written, not even the output directory.
+    assert!(!out_dir.exists());
+
+    assert_eq!(sprites.len(), 2);
+    assert_eq!(sprites[0].path, out_dir.join("margin_tex.png"));
+    assert_eq!(
+        sprites[0].alpha_path,
+        Some(out_dir.join("margin_tex_alpha.png"))
+    );
+    assert_eq!(sprites[1].path, out_dir.join("margin_tex_.png"));
+    assert_eq!(
+        (sprites[0].metadata.width, sprites[0].metadata.height),
+        (6, 5)
+    );
+    assert_eq!(
+        (sprites[1].metadata.width, sprites[1].metadata.height),
+        (3, 3)
+    );
+    assert_eq!(sprites[0].metadata.file_type, 1);
+
+    // Sprites with too little data for their pixels are skipped, like when
+    // they are decoded.
+    let data = fs::read("./tests/data/sc/truncated_pixels_tex.sc").unwrap();
+    let sprites = process_tex_with(
+        data.as_slice(),
+        "truncated_pixels_tex.sc",
+        out_dir,
+        &options,
+    )
+    .unwrap()
+    .sprites;
+
+    assert!(sprites.is_empty());
+    assert!(!out_dir.exists());
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_resize() {
+    let data = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/resize");
+
+    prepare_out_dir(out_dir);
+
+    // The 6x5 and 3x3 sprites.
+    let options = TexOptions {
+        resize: Some(Resize::Scale(0.5)),
+        ..Default::default()
+    };
+    let sprites = process_tex_with(data.as_slice(), "margin_tex.sc", out_dir, &options)
+        .unwrap()
+        .sprites;
+
+    assert_eq!(image::image_dimensions(&sprites[0].path).unwrap(), (3, 3));
+    assert_eq!(image::image_dimensions(&sprites[1].path).unwrap(), (2, 2));
+    // The metadata keeps the size of the sprite.
+    assert_eq!(
+        (sprites[0].metadata.width, sprites[0].metadata.height),
+        (6, 5)
+    );
+
+    let options = TexOptions {
+        resize: Some(Resize::MaxDimension(3)),
+        ..Default::default()
+    };
+    let sprites = process_tex_with(data.as_slice(), "margin_tex.sc", out_dir, &options)
+        .unwrap()
+        .sprites;
+
+    assert_eq!(image::image_dimensions(&sprites[0].path).unwrap(), (3, 3));
+    // Smaller images are saved as they are.
+    let image = &extract_tex(data.as_slice()).unwrap()[1];
+    assert_eq!(&image::open(&sprites[1].path).unwrap().to_rgba8(), image);
+
+    // No side is made smaller than a pixel.
+    assert_eq!(Resize::Scale(0.01).dimensions(6, 5), (1, 1));
+    assert_eq!(Resize::MaxDimension(4).dimensions(1000, 1), (4, 1));
+    assert_eq!(Resize::Scale(2.0).dimensions(1, 1), (2, 2));
+
+    let options = TexOptions {
+        resize: Some(Resize::Scale(0.0)),
+        ..Default::default()
+    };
+    assert!(process_tex_with(data.as_slice(), "margin_tex.sc", out_dir, &options).is_err());
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_flip() {
+    let out_dir = Path::new("./tests/out/flip");
+
+    prepare_out_dir(out_dir);
+
+    // Neither side is a multiple of the tile size, so the last row and column
+    // of tiles are partial.
+    let image = image::RgbaImage::from_fn(40, 35, |x, y| image::Rgba([x as u8, y as u8, 7, 255]));
+
+    for file_type in [1, 27].iter() {
+        let sprite = TexSprite {
+            image: image.clone(),
+            file_type: *file_type,
+            sub_type: 0,
+        };
+        let data = pack_tex(std::slice::from_ref(&sprite)).unwrap();
+        let file_name = format!("flip{}_tex.sc", file_type);
+
+        let options = TexOptions {
+            flip_vertical: true,
+            ..Default::default()
+        };
+        let sprites = process_tex_with(data.as_slice(), &file_name, out_dir, &options)
+            .unwrap()
+            .sprites;
+        let saved = image::open(&sprites[0].path).unwrap().to_rgba8();
+        assert_eq!(
+            saved,
+            image::imageops::flip_vertical(&image),
+            "{}",
+            file_type
+        );
+
+        let options = TexOptions {
+            flip_vertical: true,
+            flip_horizontal: true,
+            ..Default::default()
+        };
+        let sprites = process_tex_with(data.as_slice(), &file_name, out_dir, &options)
+            .unwrap()
+            .sprites;
+        let saved = image::open(&sprites[0].path).unwrap().to_rgba8();
+        assert_eq!(saved, image::imageops::rotate180(&image), "{}", file_type);
+    }
+
+    // The tiles of a real tiled sprite are flipped as a whole.
+    let data = fs::read("./tests/data/sc/tiled_tex.sc").unwrap();
+    let options = TexOptions {
+        flip_horizontal: true,
+        ..Default::default()
+    };
+    let sprites = process_tex_with(data.as_slice(), "tiled_tex.sc", out_dir, &options)
+        .unwrap()
+        .sprites;
+    let image = &extract_tex(data.as_slice()).unwrap()[0];
+    let saved = image::open(&sprites[0].path).unwrap().to_rgba8();
+    assert_eq!(saved, image::imageops::flip_horizontal(image));
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_dedupe() {
+    let data = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/dedupe");
+
+    prepare_out_dir(out_dir);
+
+    for mode in [DedupeMode::Skip, DedupeMode::HardLink, DedupeMode::Report].iter() {
+        let dedupe = Arc::new(Deduplicator::new(*mode));
+        let options = TexOptions {
+            dedupe: Some(dedupe.clone()),
+            ..Default::default()
+        };
+        let name = format!("{:?}", mode).to_lowercase();
+        let first = format!("{}_a_tex.sc", name);
+        let second = format!("{}_b_tex.sc", name);
+
+        let originals = process_tex_with(data.as_slice(), &first, out_dir, &options)
+            .unwrap()
+            .sprites;
+        let duplicates = process_tex_with(data.as_slice(), &second, out_dir, &options)
+            .unwrap()
+            .sprites;
+
+        assert_eq!(originals.len(), 2);
+        assert!(originals.iter().all(|s| s.duplicate_of.is_none()));
+        assert_eq!(dedupe.duplicates().len(), 2, "{:?}", mode);
+        assert_eq!(dedupe.duplicates()[0].original, originals[0].path);
+
+        if *mode == DedupeMode::Skip {
+            // Nothing is saved for skipped duplicates.
+            assert!(duplicates.is_empty());
+            assert!(!dedupe.duplicates()[0].path.exists());
+            continue;
+        }
+
+        assert_eq!(
+            duplicates[0].duplicate_of.as_ref(),
+            Some(&originals[0].path)
+        );
+        // Reported duplicates name their own file in their text chunks.
+        assert_eq!(
+            image::open(&duplicates[1].path).unwrap().to_rgba8(),
+            image::open(&originals[1].path).unwrap().to_rgba8()
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_skip_existing() {
+    let data = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/skip_existing");
+
+    prepare_out_dir(out_dir);
+
+    process_tex(data.as_slice(), "margin_tex.sc", out_dir, true).unwrap();
+
+    // The images aren't decoded again, so a changed image is kept.
+    let path = out_dir.join("margin_tex.png");
+    fs::write(&path, b"changed").unwrap();
+
+    let options = TexOptions {
+        skip_existing: Some(UNIX_EPOCH),
+        ..Default::default()
+    };
+    let sprites = process_tex_with(data.as_slice(), "margin_tex.sc", out_dir, &options)
+        .unwrap()
+        .sprites;
+    assert_eq!(sprites.len(), 2);
+    assert_eq!(sprites[1].path, out_dir.join("margin_tex_.png"));
+    assert_eq!(fs::read(&path).unwrap(), b"changed");
+
+    // Images older than the given time are extracted again.
+    let options = TexOptions {
+        skip_existing: Some(SystemTime::now() + Duration::from_secs(3600)),
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "margin_tex.sc", out_dir, &options).unwrap();
+    assert_eq!(image::open(&path).unwrap().dimensions(), (6, 5));
+
+    // So is every image if one is missing.
+    fs::write(&path, b"changed").unwrap();
+    fs::remove_file(out_dir.join("margin_tex_.png")).unwrap();
+
+    let options = TexOptions {
+        skip_existing: Some(UNIX_EPOCH),
+        ..Default::default()
+    };
+    process_tex_with(data.as_slice(), "margin_tex.sc", out_dir, &options).unwrap();
+    assert!(out_dir.join("margin_tex_.png").exists());
+    assert_eq!(image::open(&path).unwrap().dimensions(), (6, 5));
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_thumbnails() {
+    let data = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/thumbnails");
+
+    prepare_out_dir(out_dir);
+
+    let options = TexOptions {
+        thumbnail_size: Some(4),
+        ..Default::default()
+    };
+    let sprites = process_tex_with(data.as_slice(), "margin_tex.sc", out_dir, &options)
+        .unwrap()
+        .sprites;
+
+    let path = out_dir.join("margin_tex.thumb.png");
+    assert_eq!(sprites[0].thumbnail_path.as_ref(), Some(&path));
+    assert_eq!(image::open(&path).unwrap().dimensions(), (4, 3));
+
+    // Smaller sprites are kept at their size.
+    let path = out_dir.join("margin_tex_.thumb.png");
+    assert_eq!(sprites[1].thumbnail_path.as_ref(), Some(&path));
+    assert_eq!(
+        image::open(&path).unwrap().to_rgba8(),
+        image::open(&sprites[1].path).unwrap().to_rgba8()
+    );
+
+    let options = TexOptions {
+        thumbnail_size: Some(0),
+        ..Default::default()
+    };
+    assert!(process_tex_with(data.as_slice(), "margin_tex.sc", out_dir, &options).is_err());
+}
+
+#[test]
+#[cfg(feature = "fs")]
+fn test_png_text() {
+    let data = fs::read("./tests/data/sc/margin_tex.sc").unwrap();
+    let out_dir = Path::new("./tests/out/png_text");
+
+    prepare_out_dir(out_dir);
+
+    let text = |options: &TexOptions| {
+        process_tex_with(data.as_slice(), "margin_tex.sc", out_dir, options).unwrap();
+
+        let file = fs::File::open(out_dir.join("margin_tex_.png")).unwrap();
+        let reader = png::Decoder::new(file).read_info().unwrap();
+        reader
+            .info()
+            .uncompressed_latin1_text
+            .iter()
+            .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    let options = TexOptions {
+        crop: true,
+        ..Default::default()
+    };
+    let chunks = text(&options);
+    assert!(chunks.contains(&("Source file".to_string(), "margin_tex.sc".to_string())));
+    assert!(chunks.contains(&("Sprite index".to_string(), "1".to_string())));
+    assert!(chunks.contains(&("Original size".to_string(), "3x3".to_string())));
+    assert!(chunks.contains(&(
+        "Software".to_string(),
+        format!("sc_extract {}", env!("CARGO_PKG_VERSION"))
+    )));
+
+    let options = TexOptions {
+        no_png_text: true,
+        ..Default::default()
+    };
+    assert!(text(&options).is_empty());
 }